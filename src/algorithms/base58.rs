@@ -1,3 +1,4 @@
+use std::io::{self, Error, ErrorKind};
 use crate::algorithms::hash256::hash256;
 
 pub const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
@@ -51,4 +52,45 @@ pub fn base58_check(bytes: &[u8]) -> String {
     let mut extended = Vec::from(bytes);
     extended.extend_from_slice(&checksum[0..4]);
     encode_base58(&extended).into()
-}
\ No newline at end of file
+}
+
+pub fn decode_base58(s: &str) -> io::Result<Vec<u8>> {
+    let mut num: Vec<u8> = Vec::new(); // big-endian, without the leading zero bytes
+
+    for c in s.bytes() {
+        let digit = BASE58_ALPHABET.iter().position(|&a| a == c)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("Invalid base58 character {:?}", c as char)))?;
+
+        // num = num * 58 + digit
+        let mut carry = digit as u32;
+        for byte in num.iter_mut().rev() {
+            let acc = (*byte as u32) * 58 + carry;
+            *byte = (acc & 0xff) as u8;
+            carry = acc >> 8;
+        }
+        while carry > 0 {
+            num.insert(0, (carry & 0xff) as u8);
+            carry >>= 8;
+        }
+    }
+
+    // Each leading '1' stands for a 0x00
+    let leading_ones = s.bytes().take_while(|&c| c == b'1').count();
+    let mut res = vec![0u8; leading_ones];
+    res.extend_from_slice(&num);
+    Ok(res)
+}
+
+// Payload without the 4-byte checksum
+pub fn decode_base58_check(s: &str) -> io::Result<Vec<u8>> {
+    let mut bytes = decode_base58(s)?;
+    if bytes.len() < 4 {
+        return Err(Error::new(ErrorKind::InvalidData, "Base58check data too short"));
+    }
+
+    let checksum = bytes.split_off(bytes.len() - 4);
+    if hash256(&bytes)[..4] != checksum[..] {
+        return Err(Error::new(ErrorKind::InvalidData, "Base58check checksum mismatch"));
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,167 @@
+use std::io::{self, Error, ErrorKind};
+
+// BIP173 bech32 and BIP350 bech32m. They differ only in the checksum constant.
+
+pub const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    Bech32,
+    Bech32m,
+}
+
+impl Variant {
+    fn constant(self) -> u32 {
+        match self {
+            Variant::Bech32 => 1,
+            Variant::Bech32m => 0x2bc830a3,
+        }
+    }
+}
+
+fn polymod(values: &[u8]) -> u32 {
+    let mut chk = 1u32;
+    for &v in values {
+        let top = chk >> 25;
+        chk = (chk & 0x1ffffff) << 5 ^ v as u32;
+        for (i, g) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= g;
+            }
+        }
+    }
+    chk
+}
+
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut res: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    res.push(0);
+    res.extend(hrp.bytes().map(|b| b & 31));
+    res
+}
+
+// data is 5-bit groups, see convert_bits
+pub fn encode(hrp: &str, data: &[u8], variant: Variant) -> String {
+    let mut values = hrp_expand(hrp);
+    values.extend_from_slice(data);
+    values.extend_from_slice(&[0u8; 6]);
+    let checksum = polymod(&values) ^ variant.constant();
+
+    let mut res = String::with_capacity(hrp.len() + 1 + data.len() + 6);
+    res.push_str(hrp);
+    res.push('1');
+    for &d in data {
+        res.push(CHARSET[d as usize] as char);
+    }
+    for i in 0..6 {
+        res.push(CHARSET[((checksum >> (5 * (5 - i))) & 31) as usize] as char);
+    }
+    res
+}
+
+// Returns (hrp, 5-bit data, variant). max_len is 90 for addresses; BIP352 allows 1023.
+pub fn decode(s: &str, max_len: usize) -> io::Result<(String, Vec<u8>, Variant)> {
+    let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, msg.to_string());
+
+    if s.len() > max_len {
+        return Err(invalid("Bech32 string too long"));
+    }
+    if s.bytes().any(|b| b.is_ascii_lowercase()) && s.bytes().any(|b| b.is_ascii_uppercase()) {
+        return Err(invalid("Bech32 string has mixed case"));
+    }
+    let s = s.to_ascii_lowercase();
+
+    let pos = s.rfind('1').ok_or_else(|| invalid("Bech32 separator missing"))?;
+    if pos == 0 || pos + 7 > s.len() {
+        return Err(invalid("Bech32 hrp or checksum too short"));
+    }
+    let hrp = &s[..pos];
+    if hrp.bytes().any(|b| !(33..=126).contains(&b)) {
+        return Err(invalid("Bech32 hrp has invalid characters"));
+    }
+
+    let mut data = Vec::with_capacity(s.len() - pos - 1);
+    for c in s[pos + 1..].bytes() {
+        let d = CHARSET.iter().position(|&x| x == c).ok_or_else(|| invalid("Bech32 data has invalid characters"))?;
+        data.push(d as u8);
+    }
+
+    let mut values = hrp_expand(hrp);
+    values.extend_from_slice(&data);
+    let variant = match polymod(&values) {
+        c if c == Variant::Bech32.constant() => Variant::Bech32,
+        c if c == Variant::Bech32m.constant() => Variant::Bech32m,
+        _ => return Err(invalid("Bech32 checksum mismatch")),
+    };
+
+    data.truncate(data.len() - 6);
+    Ok((hrp.to_string(), data, variant))
+}
+
+// Regroups bits, e.g. bytes (8) into bech32 characters (5) and back
+pub fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> io::Result<Vec<u8>> {
+    let mut acc = 0u32;
+    let mut bits = 0u32;
+    let max = (1u32 << to) - 1;
+    let mut res = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+
+    for &value in data {
+        if (value as u32) >> from != 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "Value wider than the source group"));
+        }
+        acc = (acc << from) | value as u32;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            res.push(((acc >> bits) & max) as u8);
+        }
+    }
+
+    if pad {
+        if bits > 0 {
+            res.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || ((acc << (to - bits)) & max) != 0 {
+        return Err(Error::new(ErrorKind::InvalidData, "Invalid padding"));
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod bech32_tests {
+    use super::*;
+
+    #[test]
+    fn valid_checksums() -> Result<(), Box<dyn std::error::Error>> {
+        // BIP173 and BIP350 test strings
+        assert_eq!(decode("A12UEL5L", 90)?.2, Variant::Bech32);
+        assert_eq!(decode("abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw", 90)?.2, Variant::Bech32);
+        assert_eq!(decode("A1LQFN3A", 90)?.2, Variant::Bech32m);
+        assert_eq!(decode("abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx", 90)?.2, Variant::Bech32m);
+        Ok(())
+    }
+
+    #[test]
+    fn segwit_addresses() -> Result<(), Box<dyn std::error::Error>> {
+        // BIP173 P2WPKH and BIP350 v1 example
+        let (hrp, data, variant) = decode("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4", 90)?;
+        assert_eq!((hrp.as_str(), data[0], variant), ("bc", 0, Variant::Bech32));
+        assert_eq!(hex::encode(convert_bits(&data[1..], 5, 8, false)?), "751e76e8199196d454941c45d1b3a323f1433bd6");
+
+        let (hrp, data, variant) = decode("bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kt5nd6y", 90)?;
+        assert_eq!((hrp.as_str(), data[0], variant), ("bc", 1, Variant::Bech32m));
+        assert_eq!(encode(&hrp, &data, variant), "bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kt5nd6y");
+        Ok(())
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(decode("A12UEL5M", 90).is_err()); // bad checksum
+        assert!(decode("A12uEL5L", 90).is_err()); // mixed case
+        assert!(decode("12UEL5L", 90).is_err()); // empty hrp
+        assert!(decode("a1lqfn3a", 7).is_err()); // over the length limit
+        assert!(convert_bits(&[0xff], 8, 5, false).is_err()); // leftover bits
+    }
+}
@@ -0,0 +1,145 @@
+use std::io::{self, Error, ErrorKind};
+use crypto_bigint::U256;
+
+fn encode_int(x: U256) -> Vec<u8> {
+    let mut bytes = x.to_be_bytes().to_vec();
+
+    // Remove leading zeros
+    while bytes.len() > 1 && bytes[0] == 0 {
+        bytes.remove(0);
+    }
+
+    // If high bit is set, prepend 0x00
+    if bytes[0] & 0x80 != 0 {
+        let mut prefixed = vec![0x00];
+        prefixed.extend_from_slice(&bytes);
+        bytes = prefixed;
+    }
+
+    bytes
+}
+
+// SEQUENCE { INTEGER r, INTEGER s }
+pub fn encode_signature(r: U256, s: U256) -> Vec<u8> {
+    let rbin = encode_int(r);
+    let sbin = encode_int(s);
+
+    let mut result = Vec::with_capacity(72);
+
+    // INTEGER r
+    result.push(0x02);
+    result.push(rbin.len() as u8);
+    result.extend_from_slice(&rbin);
+
+    // INTEGER s
+    result.push(0x02);
+    result.push(sbin.len() as u8);
+    result.extend_from_slice(&sbin);
+
+    // Wrap in SEQUENCE
+    let mut der = Vec::with_capacity(result.len() + 2);
+    der.push(0x30);
+    der.push(result.len() as u8);
+    der.extend_from_slice(&result);
+
+    der
+}
+
+fn parse_int(bytes: &[u8]) -> io::Result<(U256, &[u8])> {
+    if bytes.len() < 2 || bytes[0] != 0x02 {
+        return Err(Error::new(ErrorKind::InvalidData, "Bad DER integer marker"));
+    }
+
+    let len = bytes[1] as usize;
+    if len == 0 || bytes.len() < 2 + len {
+        return Err(Error::new(ErrorKind::InvalidData, "Bad DER integer length"));
+    }
+
+    // Drop the sign padding (and any other leading zeros lax encoders left behind)
+    let mut value = &bytes[2..2 + len];
+    while value.len() > 1 && value[0] == 0 {
+        value = &value[1..];
+    }
+    if value.len() > 32 {
+        return Err(Error::new(ErrorKind::InvalidData, "DER integer larger than 256 bits"));
+    }
+
+    let mut buf = [0u8; 32];
+    buf[32 - value.len()..].copy_from_slice(value);
+
+    Ok((U256::from_be_slice(&buf), &bytes[2 + len..]))
+}
+
+pub fn parse_signature(der: &[u8]) -> io::Result<(U256, U256)> {
+    if der.len() < 8 || der[0] != 0x30 {
+        return Err(Error::new(ErrorKind::InvalidData, "Bad DER signature marker"));
+    }
+    if der[1] as usize != der.len() - 2 {
+        return Err(Error::new(ErrorKind::InvalidData, "Bad DER signature length"));
+    }
+
+    let (r, rest) = parse_int(&der[2..])?;
+    let (s, rest) = parse_int(rest)?;
+
+    if !rest.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidData, "Trailing bytes after DER signature"));
+    }
+
+    Ok((r, s))
+}
+
+// BIP66 strict DER, without the hash type byte: minimal lengths and non-negative, minimally
+// encoded integers. parse_signature accepts more than this.
+pub fn is_strict_der(der: &[u8]) -> bool {
+    if der.len() < 8 || der.len() > 72 || der[0] != 0x30 || der[1] as usize != der.len() - 2 {
+        return false;
+    }
+    let r_len = der[3] as usize;
+    if 5 + r_len >= der.len() {
+        return false;
+    }
+    let s_len = der[5 + r_len] as usize;
+    if r_len + s_len + 6 != der.len() {
+        return false;
+    }
+    // Zero padding only when the next byte would otherwise read as negative
+    let minimal_int = |int: &[u8]| !int.is_empty() && int[0] & 0x80 == 0 && !(int.len() > 1 && int[0] == 0 && int[1] & 0x80 == 0);
+    der[2] == 0x02 && minimal_int(&der[4..4 + r_len]) && der[4 + r_len] == 0x02 && minimal_int(&der[6 + r_len..])
+}
+
+#[cfg(test)]
+mod der_tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() -> Result<(), Box<dyn std::error::Error>> {
+        let der = hex::decode("3045022037206a0610995c58074999cb9767b87af4c4978db68c06e8e6e81d282047a7c60221008ca63759c1157ebeaec0d03cecca119fc9a75bf8e6d0fa65c841c8e2738cdaec")?;
+        let (r, s) = parse_signature(&der)?;
+        assert_eq!(r, U256::from_be_hex("37206a0610995c58074999cb9767b87af4c4978db68c06e8e6e81d282047a7c6"));
+        assert_eq!(s, U256::from_be_hex("8ca63759c1157ebeaec0d03cecca119fc9a75bf8e6d0fa65c841c8e2738cdaec"));
+        assert_eq!(encode_signature(r, s), der);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(parse_signature(&[]).is_err());
+        assert!(parse_signature(&hex::decode("3007020101020102").unwrap()).is_err()); // length mismatch
+        assert!(parse_signature(&hex::decode("3006020101020201").unwrap()).is_err()); // truncated s
+        assert!(parse_signature(&hex::decode("3106020101020101").unwrap()).is_err()); // not a SEQUENCE
+    }
+
+    #[test]
+    fn strict_der() {
+        let der = hex::decode("3045022037206a0610995c58074999cb9767b87af4c4978db68c06e8e6e81d282047a7c60221008ca63759c1157ebeaec0d03cecca119fc9a75bf8e6d0fa65c841c8e2738cdaec").unwrap();
+        assert!(is_strict_der(&der));
+        assert!(is_strict_der(&hex::decode("3006020101020101").unwrap()));
+        // Parsed leniently, but not BIP66
+        for lax in ["300702020001020101", "3006020181020101", "3006020101020180"] {
+            assert!(!is_strict_der(&hex::decode(lax).unwrap()), "{lax}");
+        }
+        assert!(parse_signature(&hex::decode("300702020001020101").unwrap()).is_ok());
+        assert!(!is_strict_der(&hex::decode("3007020101020201").unwrap()));
+        assert!(!is_strict_der(&[]));
+    }
+}
@@ -0,0 +1,16 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha512;
+
+pub fn hmac_sha512(key: &[u8], data: &[u8]) -> [u8; 64] {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC takes keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+// PBKDF2 with HMAC-SHA512, limited to a single 64-byte output block
+pub fn pbkdf2_hmac_sha512(password: &[u8], salt: &[u8], rounds: u32) -> [u8; 64] {
+    assert!(rounds > 0, "PBKDF2 needs at least one round");
+    let mut result = [0u8; 64];
+    pbkdf2::pbkdf2_hmac::<Sha512>(password, salt, rounds, &mut result);
+    result
+}
@@ -1,4 +1,9 @@
 pub mod base58;
 pub mod hash256;
 pub mod hash160;
-pub mod endian;
\ No newline at end of file
+pub mod endian;
+pub mod der;
+pub mod bech32;
+pub mod tagged_hash;
+pub mod hmac_sha512;
+pub mod sha1;
\ No newline at end of file
@@ -0,0 +1,12 @@
+use sha2::{Digest, Sha256};
+
+// BIP340 tagged hash: sha256(sha256(tag) || sha256(tag) || data)
+pub fn tagged_hash(tag: &str, data: &[u8]) -> [u8; 32] {
+    let tag_hash = Sha256::digest(tag.as_bytes());
+
+    let mut hasher = Sha256::new();
+    hasher.update(tag_hash);
+    hasher.update(tag_hash);
+    hasher.update(data);
+    hasher.finalize().into()
+}
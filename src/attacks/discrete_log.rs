@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::ops;
+use crypto_bigint::U256;
+use crate::primitives::{field::Field, point::{Curve, Point}, point_fe_u32::PointFEU32};
+
+// Generic discrete log solvers: given G and Q = xG, find x.
+// They only scale to tiny groups or tiny ranges of x, which is the point:
+// the work grows with sqrt of the search space, hopeless at 2^256.
+
+pub trait DlogGroup: Copy + PartialEq + ops::Add<Output = Self> + ops::Neg<Output = Self> {
+    fn identity(&self) -> Self;
+
+    fn scale(self, k: u64) -> Self;
+
+    // Stable hash of the point, used for lookup tables and random-walk partitions
+    fn digest(&self) -> u64;
+}
+
+impl DlogGroup for PointFEU32 {
+    fn identity(&self) -> Self {
+        PointFEU32::new_inf(self.a, self.b)
+    }
+
+    fn scale(self, k: u64) -> Self {
+        self.smul(i32::try_from(k).expect("scalar too large for PointFEU32"))
+    }
+
+    fn digest(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.x.map(|x| x.num).hash(&mut hasher);
+        self.y.map(|y| y.num).hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl<C: Curve> DlogGroup for Point<C> {
+    fn identity(&self) -> Self {
+        Point::new_inf()
+    }
+
+    fn scale(self, k: u64) -> Self {
+        self.smul(U256::from(k))
+    }
+
+    fn digest(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.x().map(|x| x.as_u256().to_words()).hash(&mut hasher);
+        self.y().map(|y| y.as_u256().to_words()).hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+// Baby-step giant-step: x in 0..bound with about 2 * sqrt(bound) additions and as much memory
+pub fn bsgs<G: DlogGroup>(g: G, q: G, bound: u64) -> Option<u64> {
+    let m = (bound as f64).sqrt().ceil() as u64;
+
+    // Baby steps: jG for j in 0..m
+    let mut table: HashMap<u64, Vec<(G, u64)>> = HashMap::new();
+    let mut baby = g.identity();
+    for j in 0..m {
+        table.entry(baby.digest()).or_default().push((baby, j));
+        baby = baby + g;
+    }
+
+    // Giant steps: Q - i*mG
+    let giant = -g.scale(m);
+    let mut gamma = q;
+    for i in 0..=m {
+        if let Some((_, j)) = table.get(&gamma.digest()).and_then(|hits| hits.iter().find(|(point, _)| *point == gamma)) {
+            let x = i * m + j;
+            if x < bound {
+                return Some(x);
+            }
+        }
+        gamma = gamma + giant;
+    }
+    None
+}
+
+// Pollard's rho with Floyd cycle detection. n is the order of g; x comes back mod n.
+// Constant memory, same sqrt(n) running time as BSGS on average.
+pub fn pollard_rho<G: DlogGroup>(g: G, q: G, n: u64) -> Option<u64> {
+    if q == g.identity() {
+        return Some(0);
+    }
+
+    // X = aG + bQ, walked by a deterministic function of X
+    let step = |(x, a, b): (G, u64, u64)| match x.digest() % 3 {
+        0 => (x + g, add_mod(a, 1, n), b),
+        1 => (x + x, add_mod(a, a, n), add_mod(b, b, n)),
+        _ => (x + q, a, add_mod(b, 1, n)),
+    };
+
+    for attempt in 1..=20u64 {
+        let a0 = mix(attempt, 1) % n;
+        let b0 = mix(attempt, 2) % n;
+        let start = (g.scale(a0) + q.scale(b0), a0, b0);
+
+        let mut tortoise = step(start);
+        let mut hare = step(step(start));
+        while tortoise.0 != hare.0 {
+            tortoise = step(tortoise);
+            hare = step(step(hare));
+        }
+
+        // a1 + b1 x = a2 + b2 x  =>  (b2 - b1) x = a1 - a2 (mod n)
+        let db = sub_mod(hare.2, tortoise.2, n);
+        let da = sub_mod(tortoise.1, hare.1, n);
+        if let Some(x) = solve_linear(db, da, n).into_iter().find(|&x| g.scale(x) == q) {
+            return Some(x);
+        }
+    }
+    None
+}
+
+// Pollard's kangaroo (lambda) for x in lo..hi, the rho idea applied to an interval.
+// This is the one to use on secp256k1, where only the range of x is small.
+pub fn kangaroo<G: DlogGroup>(g: G, q: G, lo: u64, hi: u64) -> Option<u64> {
+    if hi <= lo {
+        return None;
+    }
+    let width = hi - lo;
+    let target = ((width as f64).sqrt() / 2.0).max(1.0);
+
+    // Jumps of 2^i, with k picked so the mean jump is about sqrt(width) / 2
+    let mut k = 1u32;
+    while ((1u64 << k) - 1) as f64 / (k as f64) < target {
+        k += 1;
+    }
+    let mean = ((1u64 << k) - 1) / k as u64;
+    let mut jumps = vec![g];
+    for i in 1..k as usize {
+        jumps.push(jumps[i - 1] + jumps[i - 1]);
+    }
+
+    for salt in 0..8u64 {
+        let pick = |point: &G| (mix(point.digest(), salt) % k as u64) as usize;
+
+        // Tame kangaroo starts at hi and leaves a trap where it stops
+        let mut tame = g.scale(hi);
+        let mut tame_dist = 0u64;
+        for _ in 0..4 * mean {
+            let i = pick(&tame);
+            tame = tame + jumps[i];
+            tame_dist += 1 << i;
+        }
+
+        // Wild kangaroo starts at Q and follows the same path once it lands on the tame track
+        let mut wild = q;
+        let mut wild_dist = 0u64;
+        while wild_dist <= width + tame_dist {
+            if wild == tame {
+                // A collision outside the range is a false trap, so try the next salt
+                if let Some(x) = (hi + tame_dist).checked_sub(wild_dist).filter(|x| (lo..hi).contains(x)) {
+                    return Some(x);
+                }
+                break;
+            }
+            let i = pick(&wild);
+            wild = wild + jumps[i];
+            wild_dist += 1 << i;
+        }
+    }
+    None
+}
+
+// Pohlig-Hellman: split a smooth order n into prime powers, solve each with BSGS
+// in a subgroup of prime order, then glue the pieces with the CRT.
+pub fn pohlig_hellman<G: DlogGroup>(g: G, q: G, n: u64) -> Option<u64> {
+    let mut x = 0u64;
+    let mut modulus = 1u64;
+
+    for (p, e) in factor(n) {
+        let pe = p.pow(e);
+        let g_i = g.scale(n / pe);
+        let q_i = q.scale(n / pe);
+        let gamma = g_i.scale(pe / p);
+
+        // x_i = d_0 + d_1 p + ... + d_{e-1} p^{e-1}, one digit at a time
+        let mut x_i = 0u64;
+        let mut pk = 1u64;
+        for _ in 0..e {
+            let h = (q_i + -g_i.scale(x_i)).scale(pe / (pk * p));
+            let d = bsgs(gamma, h, p)?;
+            x_i += d * pk;
+            pk *= p;
+        }
+
+        x = crt(x, modulus, x_i, pe);
+        modulus *= pe;
+    }
+
+    if g.scale(x) == q { Some(x) } else { None }
+}
+
+// Trial division, fine for the group orders these solvers can handle anyway
+pub fn factor(mut n: u64) -> Vec<(u64, u32)> {
+    let mut res = Vec::new();
+    let mut p = 2u64;
+    while p * p <= n {
+        let mut e = 0;
+        while n.is_multiple_of(p) {
+            n /= p;
+            e += 1;
+        }
+        if e > 0 {
+            res.push((p, e));
+        }
+        p += 1;
+    }
+    if n > 1 {
+        res.push((n, 1));
+    }
+    res
+}
+
+// All x in 0..n with a * x = b (mod n)
+fn solve_linear(a: u64, b: u64, n: u64) -> Vec<u64> {
+    let d = gcd(a, n);
+    if a == 0 || !b.is_multiple_of(d) {
+        return Vec::new();
+    }
+    let m = n / d;
+    let x0 = mul_mod(b / d, inv_mod(a / d % m, m), m);
+    (0..d).map(|i| x0 + i * m).collect()
+}
+
+// x = a1 (mod m1), x = a2 (mod m2) with coprime moduli
+fn crt(a1: u64, m1: u64, a2: u64, m2: u64) -> u64 {
+    let m = m1 * m2;
+    let t = mul_mod(sub_mod(a2, a1 % m2, m2), inv_mod(m1 % m2, m2), m2);
+    (a1 + m1 * t) % m
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+fn inv_mod(a: u64, m: u64) -> u64 {
+    if m == 1 {
+        return 0;
+    }
+    let (mut old_r, mut r) = (a as i128, m as i128);
+    let (mut old_s, mut s) = (1i128, 0i128);
+    while r != 0 {
+        let quotient = old_r / r;
+        (old_r, r) = (r, old_r - quotient * r);
+        (old_s, s) = (s, old_s - quotient * s);
+    }
+    assert_eq!(old_r, 1, "{} has no inverse mod {}", a, m);
+    old_s.rem_euclid(m as i128) as u64
+}
+
+fn add_mod(a: u64, b: u64, n: u64) -> u64 {
+    ((a as u128 + b as u128) % n as u128) as u64
+}
+
+fn sub_mod(a: u64, b: u64, n: u64) -> u64 {
+    ((a as u128 + n as u128 - b as u128) % n as u128) as u64
+}
+
+fn mul_mod(a: u64, b: u64, n: u64) -> u64 {
+    ((a as u128 * b as u128) % n as u128) as u64
+}
+
+// splitmix64, to derive start points and partitions from a seed
+fn mix(value: u64, salt: u64) -> u64 {
+    let mut z = value.wrapping_add(salt.wrapping_mul(0x9e3779b97f4a7c15)).wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod discrete_log_tests {
+    use crate::{fe32, p_fe32};
+    use crate::primitives::{curve_group::{generators, generators_by_order, group_order, point_order}, ecdsa::EcdsaCurve, toy_curves::Toy1051};
+    use crate::s256::{private_key::PrivateKey, s256_point::S256Point};
+    use super::*;
+
+    fn chapter_point(x: u32, y: u32) -> PointFEU32 {
+        p_fe32!(fe32!(x, 223), fe32!(y, 223), fe32!(0, 223), fe32!(7, 223))
+    }
+
+    #[test]
+    fn chapter_curve_subgroups() {
+        // (47, 71) generates a group of order 21, (15, 86) one of order 7
+        for (g, n) in [(chapter_point(47, 71), 21u64), (chapter_point(15, 86), 7)] {
+            assert_eq!(point_order(g) as u64, n);
+            for secret in 0..n {
+                let q = g.scale(secret);
+                assert_eq!(bsgs(g, q, n), Some(secret));
+                assert_eq!(pollard_rho(g, q, n), Some(secret));
+                assert_eq!(pohlig_hellman(g, q, n), Some(secret));
+            }
+        }
+    }
+
+    #[test]
+    fn chapter_curve_largest_subgroup() {
+        // The group of order 252 is Z6 x Z42, not cyclic, so the best generator has order 42 = 2 * 3 * 7.
+        // Smooth orders like this fall to Pohlig-Hellman in a handful of tiny BSGS runs.
+        let a = fe32!(0, 223);
+        let b = fe32!(7, 223);
+        assert_eq!(group_order(a, b), 252);
+        assert!(generators(a, b).is_empty());
+
+        let (n, points) = generators_by_order(a, b).pop_last().unwrap();
+        assert_eq!(n, 42);
+        let g = points[0];
+        for secret in [1u64, 20, 41] {
+            assert_eq!(pohlig_hellman(g, g.scale(secret), n as u64), Some(secret));
+        }
+    }
+
+    #[test]
+    fn not_in_subgroup() {
+        let g = chapter_point(15, 86);
+        let q = chapter_point(47, 71);
+        assert_eq!(bsgs(g, q, 7), None);
+        assert_eq!(pohlig_hellman(g, q, 7), None);
+    }
+
+    #[test]
+    fn prime_order_toy_curve() {
+        let g = Toy1051::g();
+        let n = Toy1051::n().as_words()[0];
+        for secret in [2u64, 500, 1092] {
+            let q = g.scale(secret);
+            assert_eq!(bsgs(g, q, n), Some(secret));
+            assert_eq!(pollard_rho(g, q, n), Some(secret));
+        }
+    }
+
+    #[test]
+    fn factor_orders() {
+        assert_eq!(factor(252), vec![(2, 2), (3, 2), (7, 1)]);
+        assert_eq!(factor(1093), vec![(1093, 1)]);
+    }
+
+    #[test]
+    fn tiny_private_key_bsgs() {
+        let key = PrivateKey::new(U256::from(54321u64));
+        assert_eq!(bsgs(S256Point::g(), key.point, 1 << 16), Some(54321));
+    }
+
+    #[test]
+    fn tiny_private_key_kangaroo() {
+        let key = PrivateKey::new(U256::from(1_000_777u64));
+        assert_eq!(kangaroo(S256Point::g(), key.point, 1_000_000, 1_004_096), Some(1_000_777));
+        assert_eq!(kangaroo(S256Point::g(), key.point, 1_004_096, 1_000_000), None);
+    }
+}
@@ -0,0 +1,2 @@
+pub mod discrete_log;
+pub mod nonce_reuse;
\ No newline at end of file
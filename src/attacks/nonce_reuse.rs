@@ -0,0 +1,241 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Cursor};
+use crypto_bigint::U256;
+use crate::{bitcoin::{op_code::script::Script, tx::tx::Tx, varint::encode_varint}, s256::{s256_point::S256Point, scalr::Scalar, signature::Signature}};
+
+// Auditing ECDSA signatures for nonce weaknesses. With s = (z + r e) / k, anyone who
+// learns k for a single signature, or sees two signatures sharing k, can solve for e.
+//
+// Biased nonces (k drawn with a few known leading bits) are out of scope. r = x(kG)
+// carries no trace of the bias, so statistics over r cannot flag it, and recovering
+// the key takes a lattice reduction over many signatures (hidden number problem).
+// Only the special case of k <= bound is covered, by find_small_nonces.
+
+// One signature found in a transaction input, with the message hash it commits to
+pub struct SignatureRecord {
+    pub tx_id: String,
+    pub input: usize,
+    pub pubkey: S256Point,
+    pub sig: Signature,
+    pub z: U256,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weakness {
+    ReusedNonce,
+    SmallNonce,
+}
+
+pub struct RecoveredKey {
+    pub pubkey: S256Point,
+    pub secret: U256,
+    pub weakness: Weakness,
+    // (tx id, input index) of every signature that leaked the key
+    pub inputs: Vec<(String, usize)>,
+}
+
+// Pulls <sig> <pubkey> pairs out of P2PKH-style script_sigs. The sighash is left to the
+// caller since computing it needs the previous outputs.
+pub fn collect_signatures<F>(txs: &[Tx], mut sig_hash: F) -> Vec<SignatureRecord>
+where
+    F: FnMut(&Tx, usize) -> U256,
+{
+    let mut res = Vec::new();
+    for tx in txs {
+        for (index, tx_in) in tx.tx_ins.iter().enumerate() {
+            let Ok(Some((sig, pubkey))) = parse_p2pkh_script_sig(&tx_in.script_sig) else {
+                continue;
+            };
+            res.push(SignatureRecord { tx_id: tx.id.clone(), input: index, pubkey, sig, z: sig_hash(tx, index) });
+        }
+    }
+    res
+}
+
+// Groups signatures by (pubkey, r). Two different messages under one group give the key away.
+pub fn find_reused_nonces(records: &[SignatureRecord]) -> Vec<RecoveredKey> {
+    let mut groups: BTreeMap<(Vec<u8>, [u8; 32]), Vec<&SignatureRecord>> = BTreeMap::new();
+    for record in records {
+        groups.entry((record.pubkey.sec(true), record.sig.r.num.to_be_bytes())).or_default().push(record);
+    }
+
+    let mut res = Vec::new();
+    for group in groups.values().filter(|group| group.len() > 1) {
+        let first = group[0];
+        let secret = group[1..].iter()
+            .filter(|other| other.z != first.z)
+            .find_map(|other| recover_from_reused_nonce(first, other));
+
+        if let Some(secret) = secret {
+            res.push(RecoveredKey {
+                pubkey: first.pubkey,
+                secret,
+                weakness: Weakness::ReusedNonce,
+                inputs: group.iter().map(|record| (record.tx_id.clone(), record.input)).collect(),
+            });
+        }
+    }
+    res
+}
+
+// Both signatures must share pubkey and r. Low-s normalization may have flipped either s,
+// which only changes the sign of k, so s1 - s2 and s1 + s2 are the two cases to try.
+pub fn recover_from_reused_nonce(a: &SignatureRecord, b: &SignatureRecord) -> Option<U256> {
+    if a.pubkey != b.pubkey || a.sig.r != b.sig.r {
+        return None;
+    }
+
+    let z1 = scalar(a.z);
+    let z2 = scalar(b.z);
+    let s1 = scalar(a.sig.s.num);
+    let s2 = scalar(b.sig.s.num);
+
+    for denominator in [s1 - s2, s1 + s2] {
+        if denominator.num == U256::ZERO {
+            continue;
+        }
+        let k = (z1 - z2) / denominator;
+        if let Some(secret) = secret_from_nonce(a, k.num) {
+            return Some(secret);
+        }
+    }
+    None
+}
+
+// Looks every r up in a table of x(kG) for k <= bound. A hit means the signer drew k
+// from a tiny range (a broken RNG, a counter, a truncated value) and the key follows.
+// Nonces that are merely biased rather than small are not caught here, see above.
+pub fn find_small_nonces(records: &[SignatureRecord], bound: u64) -> Vec<RecoveredKey> {
+    let g = S256Point::g();
+    let mut table: HashMap<[u8; 32], u64> = HashMap::new();
+    let mut point = g;
+    for k in 1..=bound {
+        table.insert(point.x().unwrap().num.to_be_bytes(), k);
+        point = point + g;
+    }
+
+    let mut found: BTreeMap<Vec<u8>, RecoveredKey> = BTreeMap::new();
+    for record in records {
+        let Some(&k) = table.get(&record.sig.r.num.to_be_bytes()) else {
+            continue;
+        };
+
+        // x(kG) = x(-kG), so the nonce was k or n - k
+        let k = U256::from(k);
+        let secret = secret_from_nonce(record, k).or_else(|| secret_from_nonce(record, S256Point::n() - k));
+        let Some(secret) = secret else {
+            continue;
+        };
+
+        found.entry(record.pubkey.sec(true))
+            .or_insert_with(|| RecoveredKey { pubkey: record.pubkey, secret, weakness: Weakness::SmallNonce, inputs: Vec::new() })
+            .inputs.push((record.tx_id.clone(), record.input));
+    }
+    found.into_values().collect()
+}
+
+// e = (s k - z) / r, checked against the public key
+pub fn secret_from_nonce(record: &SignatureRecord, k: U256) -> Option<U256> {
+    let r = scalar(record.sig.r.num);
+    if r.num == U256::ZERO {
+        return None;
+    }
+
+    let secret = (scalar(record.sig.s.num) * scalar(k) - scalar(record.z)) / r;
+    if S256Point::g().rmul(secret.num) == record.pubkey {
+        Some(secret.num)
+    } else {
+        None
+    }
+}
+
+// Ok(None) for script_sigs that are not <DER sig + hash type> <SEC pubkey>
+fn parse_p2pkh_script_sig(script_sig: &[u8]) -> io::Result<Option<(Signature, S256Point)>> {
+    let mut raw = encode_varint(U256::from_u64(script_sig.len() as u64));
+    raw.extend_from_slice(script_sig);
+    let script = Script::parse(&mut Cursor::new(raw))?;
+
+    let [sig_bytes, sec] = &script.cmds[..] else {
+        return Ok(None);
+    };
+    if sig_bytes.len() < 9 {
+        return Ok(None);
+    }
+    let Ok(point) = S256Point::parse(sec) else {
+        return Ok(None);
+    };
+
+    // Last byte is the sighash type
+    let sig = Signature::parse(&sig_bytes[..sig_bytes.len() - 1])?;
+    Ok(Some((sig, point)))
+}
+
+fn scalar(num: U256) -> Scalar {
+    Scalar::new(num % S256Point::n())
+}
+
+#[cfg(test)]
+mod nonce_reuse_tests {
+    use crate::bitcoin::tx::{tx::Network, tx_in::TxIn};
+    use crate::s256::private_key::PrivateKey;
+    use super::*;
+
+    fn p2pkh_script_sig(sig: &Signature, pubkey: S256Point) -> Vec<u8> {
+        let mut der = sig.der();
+        der.push(0x01);
+        let sec = pubkey.sec(true);
+
+        let mut res = vec![der.len() as u8];
+        res.extend_from_slice(&der);
+        res.push(sec.len() as u8);
+        res.extend_from_slice(&sec);
+        res
+    }
+
+    #[test]
+    fn reused_nonce_in_transaction() {
+        let key = PrivateKey::new(U256::from_be_hex("00000000000000000000000000000000000000000000000000000000deadbeef"));
+        let k = U256::from_be_hex("a3f1c2d4e5b69788123456789000abcdef1234567890abcdef1234567890abcd");
+        let hashes = [U256::from_u64(1111), U256::from_u64(2222)];
+
+        let tx_ins = hashes.iter().enumerate().map(|(i, &z)| {
+            let sig = key.sign_with_nonce(z, k);
+            TxIn::new([i as u8; 32], 0, p2pkh_script_sig(&sig, key.point), 0xffffffff)
+        }).collect();
+        let tx = Tx::new(1, tx_ins, Vec::new(), 0, Network::TESTNET);
+
+        let records = collect_signatures(std::slice::from_ref(&tx), |_, index| hashes[index]);
+        assert_eq!(records.len(), 2);
+
+        let found = find_reused_nonces(&records);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].secret, key.secret);
+        assert_eq!(found[0].weakness, Weakness::ReusedNonce);
+        assert_eq!(found[0].inputs, vec![(tx.id.clone(), 0), (tx.id.clone(), 1)]);
+    }
+
+    #[test]
+    fn invalid_pubkey_is_skipped() {
+        let key = PrivateKey::new(U256::from_u64(0xc0ffee));
+        let mut script_sig = p2pkh_script_sig(&key.sign_with_nonce(U256::from_u64(1), U256::from_u64(7)), key.point);
+        // x = 5 is not on the curve
+        let len = script_sig.len();
+        script_sig[len - 33..].copy_from_slice(&[[0x02].as_slice(), &U256::from_u64(5).to_be_bytes()].concat());
+        assert!(parse_p2pkh_script_sig(&script_sig).unwrap().is_none());
+    }
+
+    #[test]
+    fn small_nonce() {
+        let key = PrivateKey::new(U256::from_u64(0xc0ffee));
+        let z = U256::from_u64(4242);
+        let record = SignatureRecord { tx_id: String::from("tx"), input: 3, pubkey: key.point, sig: key.sign_with_nonce(z, U256::from_u64(9)), z };
+
+        assert!(find_small_nonces(std::slice::from_ref(&record), 8).is_empty());
+
+        let found = find_small_nonces(&[record], 16);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].secret, key.secret);
+        assert_eq!(found[0].weakness, Weakness::SmallNonce);
+        assert_eq!(found[0].inputs, vec![(String::from("tx"), 3)]);
+    }
+}
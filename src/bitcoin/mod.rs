@@ -2,4 +2,6 @@ pub mod varint;
 pub mod utils;
 pub mod tx;
 pub mod tx_fetcher;
-pub mod op_code;
\ No newline at end of file
+pub mod op_code;
+pub mod silent_payments;
+pub mod payment_code;
\ No newline at end of file
@@ -0,0 +1,541 @@
+use std::{io::{self, Error, ErrorKind}, slice};
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+use crate::{algorithms::sha1::sha1, bitcoin::op_code::op_code::{op_dup, op_hash160, op_hash256}};
+
+// Consensus script evaluation over raw script bytes. Script::cmds can't tell a one-byte push
+// from an opcode, so this walks the bytes instead. Signature and timelock checks depend on the
+// spending transaction, which the caller provides as a Checker.
+
+pub trait Checker {
+    // The script code a checking opcode signs, given the script after the last executed
+    // OP_CODESEPARATOR and all of the opcode's signatures: legacy scripts delete them first
+    fn script_code(&self, script_code: &[u8], sigs: &[Vec<u8>]) -> Vec<u8>;
+    // sig ends with its hash type byte; script_code comes from script_code()
+    fn check_sig(&self, sig: &[u8], pubkey: &[u8], script_code: &[u8]) -> bool;
+    // BIP65 OP_CHECKLOCKTIMEVERIFY, locktime is non-negative
+    fn check_locktime(&self, locktime: i64) -> bool;
+    // BIP112 OP_CHECKSEQUENCEVERIFY, sequence is non-negative
+    fn check_sequence(&self, sequence: i64) -> bool;
+}
+
+const MAX_SCRIPT_SIZE: usize = 10_000;
+const MAX_ELEMENT_SIZE: usize = 520;
+const MAX_OPS: usize = 201;
+const MAX_STACK_SIZE: usize = 1000;
+const MAX_PUBKEYS: i64 = 20;
+
+fn fail(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.to_string())
+}
+
+// Script numbers are minimal little-endian with a sign bit
+pub fn encode_num(num: i64) -> Vec<u8> {
+    let mut abs = num.unsigned_abs();
+    let mut result = Vec::new();
+    while abs > 0 {
+        result.push((abs & 0xff) as u8);
+        abs >>= 8;
+    }
+    match result.last_mut() {
+        Some(last) if *last & 0x80 != 0 => result.push(if num < 0 { 0x80 } else { 0x00 }),
+        Some(last) if num < 0 => *last |= 0x80,
+        _ => {}
+    }
+    result
+}
+
+// Operands are at most 4 bytes, 5 for the timelock opcodes
+pub fn decode_num(bytes: &[u8], max_len: usize) -> io::Result<i64> {
+    if bytes.len() > max_len {
+        return Err(fail("Script number overflow"));
+    }
+    let Some(&last) = bytes.last() else {
+        return Ok(0);
+    };
+    let mut result = bytes.iter().rev().fold(0i64, |acc, &b| acc << 8 | b as i64);
+    if last & 0x80 != 0 {
+        result &= !(0x80i64 << (8 * (bytes.len() - 1)));
+        result = -result;
+    }
+    Ok(result)
+}
+
+// Zero of any length, including negative zero, is false
+pub fn cast_to_bool(bytes: &[u8]) -> bool {
+    match bytes.split_last() {
+        Some((&last, rest)) => rest.iter().any(|&b| b != 0) || (last != 0 && last != 0x80),
+        None => false,
+    }
+}
+
+// The opcode at pc and, for OP_0 through OP_PUSHDATA4, the pushed bytes
+pub fn next_op<'a>(script: &'a [u8], pc: &mut usize) -> io::Result<(u8, Option<&'a [u8]>)> {
+    let opcode = script[*pc];
+    *pc += 1;
+    let len = match opcode {
+        0x00..=0x4b => opcode as usize,
+        0x4c..=0x4e => {
+            let width = 1 << (opcode - 0x4c);
+            let len_bytes = script.get(*pc..*pc + width).ok_or_else(|| fail("Truncated push"))?;
+            *pc += width;
+            len_bytes.iter().rev().fold(0usize, |acc, &b| acc << 8 | b as usize)
+        }
+        _ => return Ok((opcode, None)),
+    };
+    let data = script.get(*pc..*pc + len).ok_or_else(|| fail("Truncated push"))?;
+    *pc += len;
+    Ok((opcode, Some(data)))
+}
+
+// Only pushes and OP_1NEGATE..OP_16, as BIP16 requires of P2SH scriptSigs
+pub fn is_push_only(script: &[u8]) -> bool {
+    let mut pc = 0;
+    while pc < script.len() {
+        match next_op(script, &mut pc) {
+            Ok((opcode, _)) if opcode <= 0x60 => {}
+            _ => return false,
+        }
+    }
+    true
+}
+
+fn pop(stack: &mut Vec<Vec<u8>>) -> io::Result<Vec<u8>> {
+    stack.pop().ok_or_else(|| fail("Stack underflow"))
+}
+
+fn pop_num(stack: &mut Vec<Vec<u8>>) -> io::Result<i64> {
+    decode_num(&pop(stack)?, 4)
+}
+
+// depth 0 is the top
+fn peek(stack: &[Vec<u8>], depth: usize) -> io::Result<&Vec<u8>> {
+    stack.len().checked_sub(depth + 1).map(|i| &stack[i]).ok_or_else(|| fail("Stack underflow"))
+}
+
+// The top count elements, in stack order
+fn pop_many(stack: &mut Vec<Vec<u8>>, count: usize) -> io::Result<Vec<Vec<u8>>> {
+    let at = stack.len().checked_sub(count).ok_or_else(|| fail("Stack underflow"))?;
+    Ok(stack.split_off(at))
+}
+
+fn push_bool(stack: &mut Vec<Vec<u8>>, value: bool) {
+    stack.push(encode_num(value as i64));
+}
+
+fn verify(stack: &mut Vec<Vec<u8>>, opcode_name: &str) -> io::Result<()> {
+    if cast_to_bool(&pop(stack)?) {
+        Ok(())
+    } else {
+        Err(fail(&format!("{opcode_name} failed")))
+    }
+}
+
+// Runs script on top of stack. Success only means nothing failed: the caller decides what the
+// final stack has to look like.
+pub fn evaluate(script: &[u8], stack: &mut Vec<Vec<u8>>, checker: &dyn Checker) -> io::Result<()> {
+    if script.len() > MAX_SCRIPT_SIZE {
+        return Err(fail("Script too long"));
+    }
+    let mut alt_stack: Vec<Vec<u8>> = Vec::new();
+    // One entry per open OP_IF: whether its current branch runs
+    let mut conditions: Vec<bool> = Vec::new();
+    let mut op_count = 0;
+    let mut code_start = 0;
+    let mut pc = 0;
+
+    while pc < script.len() {
+        let (opcode, data) = next_op(script, &mut pc)?;
+        let executing = conditions.iter().all(|&c| c);
+
+        if data.is_some_and(|data| data.len() > MAX_ELEMENT_SIZE) {
+            return Err(fail("Push larger than 520 bytes"));
+        }
+        if opcode > 0x60 {
+            op_count += 1;
+            if op_count > MAX_OPS {
+                return Err(fail("Too many opcodes"));
+            }
+        }
+        // OP_CAT, OP_MUL and the rest of the disabled opcodes fail even in unexecuted branches
+        if matches!(opcode, 0x7e..=0x81 | 0x83..=0x86 | 0x8d | 0x8e | 0x95..=0x99) {
+            return Err(fail(&format!("Disabled opcode 0x{opcode:02x}")));
+        }
+        if !executing && !(0x63..=0x68).contains(&opcode) {
+            continue;
+        }
+        match opcode {
+            // OP_0 through OP_PUSHDATA4
+            0x00..=0x4e => stack.push(data.unwrap_or_default().to_vec()),
+            0x4f => stack.push(encode_num(-1)),
+            0x51..=0x60 => stack.push(encode_num(opcode as i64 - 0x50)),
+            // OP_NOP, OP_NOP1, OP_NOP4..OP_NOP10
+            0x61 | 0xb0 | 0xb3..=0xb9 => {}
+
+            // OP_IF, OP_NOTIF, OP_ELSE, OP_ENDIF
+            0x63 | 0x64 => {
+                let mut condition = false;
+                if executing {
+                    condition = cast_to_bool(&pop(stack)?) == (opcode == 0x63);
+                }
+                conditions.push(condition);
+            }
+            0x67 => {
+                let last = conditions.last_mut().ok_or_else(|| fail("OP_ELSE without OP_IF"))?;
+                *last = !*last;
+            }
+            0x68 => {
+                conditions.pop().ok_or_else(|| fail("OP_ENDIF without OP_IF"))?;
+            }
+            0x69 => verify(stack, "OP_VERIFY")?,
+            0x6a => return Err(fail("OP_RETURN")),
+
+            // Stack manipulation
+            0x6b => {
+                let top = pop(stack)?;
+                alt_stack.push(top);
+            }
+            0x6c => {
+                let top = alt_stack.pop().ok_or_else(|| fail("Alt stack underflow"))?;
+                stack.push(top);
+            }
+            0x6d => {
+                pop_many(stack, 2)?;
+            }
+            // OP_2DUP, OP_3DUP, OP_2OVER copy the same depth repeatedly
+            0x6e..=0x70 => {
+                let (count, depth) = match opcode {
+                    0x6e => (2, 1),
+                    0x6f => (3, 2),
+                    _ => (2, 3),
+                };
+                for _ in 0..count {
+                    let item = peek(stack, depth)?.clone();
+                    stack.push(item);
+                }
+            }
+            // OP_2ROT, OP_2SWAP move a pair from the given depth to the top
+            0x71 | 0x72 => {
+                let depth = if opcode == 0x71 { 6 } else { 4 };
+                let at = stack.len().checked_sub(depth).ok_or_else(|| fail("Stack underflow"))?;
+                let pair: Vec<Vec<u8>> = stack.drain(at..at + 2).collect();
+                stack.extend(pair);
+            }
+            0x73 => {
+                let top = peek(stack, 0)?.clone();
+                if cast_to_bool(&top) {
+                    stack.push(top);
+                }
+            }
+            0x74 => stack.push(encode_num(stack.len() as i64)),
+            0x75 => {
+                pop(stack)?;
+            }
+            0x76 => {
+                if !op_dup(stack) {
+                    return Err(fail("Stack underflow"));
+                }
+            }
+            0x77 => {
+                let second = pop_many(stack, 2)?.pop().unwrap();
+                stack.push(second);
+            }
+            0x78 => {
+                let item = peek(stack, 1)?.clone();
+                stack.push(item);
+            }
+            // OP_PICK copies, OP_ROLL moves
+            0x79 | 0x7a => {
+                let depth = pop_num(stack)?;
+                if depth < 0 || depth as usize >= stack.len() {
+                    return Err(fail("OP_PICK/OP_ROLL out of range"));
+                }
+                let at = stack.len() - 1 - depth as usize;
+                let item = if opcode == 0x7a { stack.remove(at) } else { stack[at].clone() };
+                stack.push(item);
+            }
+            0x7b => {
+                let at = stack.len().checked_sub(3).ok_or_else(|| fail("Stack underflow"))?;
+                let item = stack.remove(at);
+                stack.push(item);
+            }
+            0x7c => {
+                let len = stack.len();
+                if len < 2 {
+                    return Err(fail("Stack underflow"));
+                }
+                stack.swap(len - 1, len - 2);
+            }
+            0x7d => {
+                let top = peek(stack, 0)?.clone();
+                let at = stack.len().checked_sub(2).ok_or_else(|| fail("Stack underflow"))?;
+                stack.insert(at, top);
+            }
+            0x82 => {
+                let size = peek(stack, 0)?.len();
+                stack.push(encode_num(size as i64));
+            }
+
+            // OP_EQUAL, OP_EQUALVERIFY
+            0x87 | 0x88 => {
+                let a = pop(stack)?;
+                let b = pop(stack)?;
+                push_bool(stack, a == b);
+                if opcode == 0x88 {
+                    verify(stack, "OP_EQUALVERIFY")?;
+                }
+            }
+
+            // Arithmetic on 4-byte numbers
+            0x8b..=0x92 => {
+                let n = pop_num(stack)?;
+                let result = match opcode {
+                    0x8b => n + 1,
+                    0x8c => n - 1,
+                    0x8f => -n,
+                    0x90 => n.abs(),
+                    0x91 => (n == 0) as i64,
+                    _ => (n != 0) as i64,
+                };
+                stack.push(encode_num(result));
+            }
+            0x93..=0xa4 => {
+                let b = pop_num(stack)?;
+                let a = pop_num(stack)?;
+                let result = match opcode {
+                    0x93 => a + b,
+                    0x94 => a - b,
+                    0x9a => (a != 0 && b != 0) as i64,
+                    0x9b => (a != 0 || b != 0) as i64,
+                    0x9c | 0x9d => (a == b) as i64,
+                    0x9e => (a != b) as i64,
+                    0x9f => (a < b) as i64,
+                    0xa0 => (a > b) as i64,
+                    0xa1 => (a <= b) as i64,
+                    0xa2 => (a >= b) as i64,
+                    0xa3 => a.min(b),
+                    _ => a.max(b),
+                };
+                stack.push(encode_num(result));
+                if opcode == 0x9d {
+                    verify(stack, "OP_NUMEQUALVERIFY")?;
+                }
+            }
+            0xa5 => {
+                let max = pop_num(stack)?;
+                let min = pop_num(stack)?;
+                let x = pop_num(stack)?;
+                push_bool(stack, min <= x && x < max);
+            }
+
+            // Hashes
+            0xa6 => {
+                let top = pop(stack)?;
+                stack.push(Ripemd160::digest(top).to_vec());
+            }
+            0xa7 => {
+                let top = pop(stack)?;
+                stack.push(sha1(&top).to_vec());
+            }
+            0xa8 => {
+                let top = pop(stack)?;
+                stack.push(Sha256::digest(top).to_vec());
+            }
+            0xa9 | 0xaa => {
+                let ok = if opcode == 0xa9 { op_hash160(stack) } else { op_hash256(stack) };
+                if !ok {
+                    return Err(fail("Stack underflow"));
+                }
+            }
+
+            // Signatures
+            0xab => code_start = pc,
+            0xac | 0xad => {
+                let pubkey = pop(stack)?;
+                let sig = pop(stack)?;
+                let script_code = checker.script_code(&script[code_start..], slice::from_ref(&sig));
+                let valid = checker.check_sig(&sig, &pubkey, &script_code);
+                push_bool(stack, valid);
+                if opcode == 0xad {
+                    verify(stack, "OP_CHECKSIGVERIFY")?;
+                }
+            }
+            0xae | 0xaf => {
+                let key_count = pop_num(stack)?;
+                if !(0..=MAX_PUBKEYS).contains(&key_count) {
+                    return Err(fail("Bad public key count"));
+                }
+                op_count += key_count as usize;
+                if op_count > MAX_OPS {
+                    return Err(fail("Too many opcodes"));
+                }
+                let pubkeys = pop_many(stack, key_count as usize)?;
+                let sig_count = pop_num(stack)?;
+                if !(0..=key_count).contains(&sig_count) {
+                    return Err(fail("Bad signature count"));
+                }
+                let sigs = pop_many(stack, sig_count as usize)?;
+                // The original off-by-one: one more element is consumed
+                pop(stack)?;
+
+                // Signatures must match keys in order; like Bitcoin Core, walk from the last
+                let script_code = checker.script_code(&script[code_start..], &sigs);
+                let mut keys = pubkeys.iter().rev();
+                let valid = sigs.iter().rev().all(|sig| keys.any(|pubkey| checker.check_sig(sig, pubkey, &script_code)));
+                push_bool(stack, valid);
+                if opcode == 0xaf {
+                    verify(stack, "OP_CHECKMULTISIGVERIFY")?;
+                }
+            }
+
+            // Timelocks leave their argument on the stack
+            0xb1 => {
+                let locktime = decode_num(peek(stack, 0)?, 5)?;
+                if locktime < 0 || !checker.check_locktime(locktime) {
+                    return Err(fail("OP_CHECKLOCKTIMEVERIFY failed"));
+                }
+            }
+            0xb2 => {
+                let sequence = decode_num(peek(stack, 0)?, 5)?;
+                if sequence < 0 || !checker.check_sequence(sequence) {
+                    return Err(fail("OP_CHECKSEQUENCEVERIFY failed"));
+                }
+            }
+
+            _ => return Err(fail(&format!("Bad opcode 0x{opcode:02x}"))),
+        }
+
+        if stack.len() + alt_stack.len() > MAX_STACK_SIZE {
+            return Err(fail("Stack too large"));
+        }
+    }
+
+    if !conditions.is_empty() {
+        return Err(fail("Unbalanced conditional"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod interpreter_tests {
+    use std::cell::RefCell;
+    use crate::bitcoin::op_code::script::{find_and_delete, push_data};
+    use super::*;
+
+    // Accepts a signature when its first byte equals the key's first byte
+    struct FakeChecker;
+
+    impl Checker for FakeChecker {
+        fn script_code(&self, script_code: &[u8], _sigs: &[Vec<u8>]) -> Vec<u8> {
+            script_code.to_vec()
+        }
+
+        fn check_sig(&self, sig: &[u8], pubkey: &[u8], _script_code: &[u8]) -> bool {
+            !sig.is_empty() && sig.first() == pubkey.first()
+        }
+
+        fn check_locktime(&self, locktime: i64) -> bool {
+            locktime <= 100
+        }
+
+        fn check_sequence(&self, sequence: i64) -> bool {
+            sequence <= 10
+        }
+    }
+
+    fn run(script_hex: &str) -> io::Result<Vec<Vec<u8>>> {
+        let mut stack = Vec::new();
+        evaluate(&hex::decode(script_hex).unwrap(), &mut stack, &FakeChecker)?;
+        Ok(stack)
+    }
+
+    #[test]
+    fn numbers() -> Result<(), Box<dyn std::error::Error>> {
+        for (num, encoded) in [(0, ""), (1, "01"), (-1, "81"), (127, "7f"), (128, "8000"), (-128, "8080"), (255, "ff00"), (-256, "0081")] {
+            assert_eq!(hex::encode(encode_num(num)), encoded);
+            assert_eq!(decode_num(&hex::decode(encoded)?, 4)?, num);
+        }
+        assert!(decode_num(&[1, 2, 3, 4, 5], 4).is_err());
+        assert!(!cast_to_bool(&[0, 0, 0x80]));
+        assert!(cast_to_bool(&[0, 1, 0x80]));
+        Ok(())
+    }
+
+    #[test]
+    fn arithmetic_and_branches() -> Result<(), Box<dyn std::error::Error>> {
+        // 2 3 ADD 5 EQUAL
+        assert_eq!(run("5253935587")?, vec![vec![1]]);
+        // 1 IF 2 ELSE 3 ENDIF, then 0 IF 2 ELSE 3 ENDIF
+        assert_eq!(run("516352675368")?, vec![vec![2]]);
+        assert_eq!(run("006352675368")?, vec![vec![3]]);
+        // 3 1 2 ROT: 1 2 3, then 5 1 10 WITHIN
+        assert_eq!(run("5351527b")?, vec![vec![1], vec![2], vec![3]]);
+        assert_eq!(run("55515aa5")?, vec![vec![1]]);
+        // Empty push SHA1 against the known digest
+        assert_eq!(run("00a714da39a3ee5e6b4b0d3255bfef95601890afd8070987")?, vec![vec![1]]);
+        Ok(())
+    }
+
+    #[test]
+    fn failures() {
+        assert!(run("6a").is_err()); // OP_RETURN
+        assert!(run("5163").is_err()); // unbalanced OP_IF
+        assert!(run("0063517e68").is_err()); // OP_CAT, even unexecuted
+        assert!(run("0065").is_err()); // OP_VERIF
+        assert!(run("0251").is_err()); // truncated push
+        assert!(run("515288").is_err()); // OP_EQUALVERIFY
+        assert!(run("76").is_err()); // underflow
+        // Timelocks compare against the checker and keep their argument
+        assert_eq!(run("0164b1").unwrap(), vec![vec![100]]);
+        assert!(run("0165b1").is_err());
+        assert!(run("4fb2").is_err());
+        // Pushes count towards the stack limit too
+        assert_eq!(run(&"00".repeat(1000)).unwrap().len(), 1000);
+        assert_eq!(run(&"00".repeat(1001)).unwrap_err().to_string(), "Stack too large");
+    }
+
+    #[test]
+    fn multisig() -> Result<(), Box<dyn std::error::Error>> {
+        // dummy, signatures, m, keys a/b/c, n
+        let keys = "01aa01bb01cc53ae";
+        assert_eq!(run(&format!("0001aa01cc52{keys}"))?, vec![vec![1]]);
+        // Out of order
+        assert_eq!(run(&format!("0001cc01aa52{keys}"))?, vec![vec![]]);
+        // Missing dummy element
+        assert!(run(&format!("01aa01cc52{keys}")).is_err());
+        assert_eq!(run("000000ae")?, vec![vec![1]]);
+        Ok(())
+    }
+
+    // Records the script code each signature is checked against
+    struct RecordingChecker(RefCell<Vec<Vec<u8>>>);
+
+    impl Checker for RecordingChecker {
+        fn script_code(&self, script_code: &[u8], sigs: &[Vec<u8>]) -> Vec<u8> {
+            sigs.iter().fold(script_code.to_vec(), |code, sig| find_and_delete(&code, &push_data(sig)))
+        }
+
+        fn check_sig(&self, _sig: &[u8], _pubkey: &[u8], script_code: &[u8]) -> bool {
+            self.0.borrow_mut().push(script_code.to_vec());
+            true
+        }
+
+        fn check_locktime(&self, _locktime: i64) -> bool {
+            false
+        }
+
+        fn check_sequence(&self, _sequence: i64) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn multisig_script_code() -> Result<(), Box<dyn std::error::Error>> {
+        // Both signatures are removed before either is checked
+        let script = hex::decode("0001aa01cc52010a010b52ae")?;
+        let checker = RecordingChecker(RefCell::new(Vec::new()));
+        evaluate(&script, &mut Vec::new(), &checker)?;
+        assert_eq!(checker.0.into_inner(), vec![hex::decode("0052010a010b52ae")?; 2]);
+        Ok(())
+    }
+}
@@ -1,2 +1,3 @@
 pub mod op_code;
-pub mod script;
\ No newline at end of file
+pub mod script;
+pub mod interpreter;
\ No newline at end of file
@@ -1,7 +1,7 @@
 use std::io::{self, Read};
 use crypto_bigint::U256;
 
-use crate::{algorithms::endian::little_endian_to_u64, bitcoin::{utils::read_bytes, varint::{encode_varint, read_varint}}};
+use crate::{algorithms::endian::little_endian_to_u64, bitcoin::{op_code::interpreter::next_op, utils::read_bytes, varint::{encode_varint, read_varint}}};
 
 #[derive(Debug)]
 pub struct Script{
@@ -108,6 +108,95 @@ impl Script {
 
 }
 
+const OP_CODESEPARATOR: u8 = 0xab;
+
+// Legacy sighash scriptCode: drops OP_CODESEPARATOR opcodes but not 0xab bytes inside pushes.
+// A truncated trailing push is kept as is.
+pub fn strip_code_separators(raw: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(raw.len());
+    let mut pc = 0;
+    while pc < raw.len() {
+        let start = pc;
+        match next_op(raw, &mut pc) {
+            Ok((OP_CODESEPARATOR, _)) => {}
+            Ok(_) => result.extend_from_slice(&raw[start..pc]),
+            Err(_) => {
+                result.extend_from_slice(&raw[start..]);
+                break;
+            }
+        }
+    }
+    result
+}
+
+// Smallest push opcode for data
+pub fn push_data(data: &[u8]) -> Vec<u8> {
+    let mut result = match data.len() {
+        len @ 0..=75 => vec![len as u8],
+        len @ 76..=0xff => vec![0x4c, len as u8],
+        len @ 0x100..=0xffff => vec![0x4d, len as u8, (len >> 8) as u8],
+        len => {
+            let mut prefix = vec![0x4e];
+            prefix.extend_from_slice(&(len as u32).to_le_bytes());
+            prefix
+        }
+    };
+    result.extend_from_slice(data);
+    result
+}
+
+// Legacy signature checks drop the signature itself from the scriptCode, wherever its push
+// starts at an opcode boundary
+pub fn find_and_delete(raw: &[u8], pattern: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(raw.len());
+    let mut pc = 0;
+    while pc < raw.len() {
+        if raw[pc..].starts_with(pattern) && !pattern.is_empty() {
+            pc += pattern.len();
+            continue;
+        }
+        let start = pc;
+        if next_op(raw, &mut pc).is_err() {
+            pc = raw.len();
+        }
+        result.extend_from_slice(&raw[start..pc]);
+    }
+    result
+}
+
+// OP_DUP OP_HASH160 <20 bytes> OP_EQUALVERIFY OP_CHECKSIG
+pub fn p2pkh_script(h160: &[u8]) -> Vec<u8> {
+    let mut script = vec![0x76, 0xa9, 0x14];
+    script.extend_from_slice(h160);
+    script.extend_from_slice(&[0x88, 0xac]);
+    script
+}
+
+// OP_HASH160 <20 bytes> OP_EQUAL
+pub fn p2sh_script(h160: &[u8]) -> Vec<u8> {
+    let mut script = vec![0xa9, 0x14];
+    script.extend_from_slice(h160);
+    script.push(0x87);
+    script
+}
+
+// BIP16: OP_HASH160 <20 bytes> OP_EQUAL
+pub fn is_p2sh(script_pubkey: &[u8]) -> bool {
+    script_pubkey.len() == 23 && script_pubkey[..2] == [0xa9, 0x14] && script_pubkey[22] == 0x87
+}
+
+// BIP141: a version opcode (OP_0, OP_1..OP_16) and a single 2 to 40 byte push
+pub fn witness_program(script_pubkey: &[u8]) -> Option<(u8, &[u8])> {
+    let (&version_op, rest) = script_pubkey.split_first()?;
+    let (&len, program) = rest.split_first()?;
+    let version = match version_op {
+        0x00 => 0,
+        0x51..=0x60 => version_op - 0x50,
+        _ => return None,
+    };
+    ((2..=40).contains(&len) && program.len() == len as usize).then_some((version, program))
+}
+
 #[cfg(test)]
 mod script_tests {
     use std::io::Cursor;
@@ -140,6 +229,35 @@ mod script_tests {
         assert_eq!(hex, "43410411db93e1dcdb8a016b49840f8c53bc1eb68a382e97b1482ecad7b148a6909a5cb2e0eaddfb84ccf9744464f82e160bfa9b8b64f9d4c03f999b8643f656b412a3ac");
         Ok(())
     }
+
+    #[test]
+    fn strip_code_separators_test() -> Result<(), Box<dyn std::error::Error>> {
+        // Only the opcodes go: 0xab as push data and PUSHDATA1 payload stays
+        let stripped = strip_code_separators(&hex::decode("ab5102abab4c01abab")?);
+        assert_eq!(hex::encode(stripped), "5102abab4c01ab");
+        assert_eq!(strip_code_separators(&hex::decode("ab4d0500ab")?), hex::decode("4d0500ab")?);
+        // Truncated length bytes are kept too
+        assert_eq!(strip_code_separators(&hex::decode("51ab4d05")?), hex::decode("514d05")?);
+        Ok(())
+    }
+
+    #[test]
+    fn templates() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(push_data(&[0xab; 3]), hex::decode("03ababab")?);
+        assert_eq!(push_data(&[0; 80])[..2], [0x4c, 80]);
+        assert_eq!(push_data(&[0; 300])[..3], [0x4d, 0x2c, 0x01]);
+
+        // Only at opcode boundaries: the second 0xab is push data
+        assert_eq!(find_and_delete(&hex::decode("01ab5101ab")?, &[0x01, 0xab]), vec![0x51]);
+        assert_eq!(find_and_delete(&hex::decode("0201ab")?, &[0x01, 0xab]), hex::decode("0201ab")?);
+
+        assert!(is_p2sh(&hex::decode("a91474d691da1574e6b3c192ecfb52cc8984ee7b6c5687")?));
+        assert!(is_p2sh(&p2sh_script(&[0; 20])));
+        let p2wpkh = hex::decode("0014751e76e8199196d454941c45d1b3a323f1433bd6")?;
+        assert_eq!(witness_program(&p2wpkh), Some((0, &p2wpkh[2..])));
+        assert!(witness_program(&hex::decode("76a914751e76e8199196d454941c45d1b3a323f1433bd688ac")?).is_none());
+        Ok(())
+    }
 }
 
 
@@ -0,0 +1,248 @@
+use std::io::{self, Error, ErrorKind};
+use crypto_bigint::U256;
+use sha2::{Digest, Sha256};
+use crate::{algorithms::{base58::{base58_check, decode_base58_check}, hash160::hash160, hmac_sha512::hmac_sha512}, bitcoin::{op_code::{interpreter::next_op, script::p2pkh_script}, tx::{tx::{Network, Tx}, tx_in::{OutPoint, TxIn}, tx_out::TxOut}}, s256::{private_key::PrivateKey, s256_point::S256Point, scalr::Scalar}, wallet::bip32::{ExtendedPrivKey, ExtendedPubKey}};
+
+// BIP47 reusable payment codes. A payment code is the public key and chain code of
+// m/47'/0'/account'. The sender announces its own code to the recipient once, blinded in a
+// notification transaction, after which both sides derive fresh addresses from ECDH secrets
+// between their child keys.
+
+const VERSION_PREFIX: u8 = 0x47;
+const PAYLOAD_LEN: usize = 80;
+// Dust-level payment that makes the notification show up at the recipient's address
+pub const NOTIFICATION_AMOUNT: u64 = 546;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaymentCode {
+    pub point: S256Point,
+    pub chain_code: [u8; 32],
+}
+
+impl PaymentCode {
+    // account is the m/47'/0'/account' key
+    pub fn from_account(account: &ExtendedPrivKey) -> Self {
+        Self { point: account.point, chain_code: account.chain_code }
+    }
+
+    // Version 1: 0x01 || features (0) || sec(A) || chain code || 13 zero bytes
+    pub fn payload(&self) -> [u8; PAYLOAD_LEN] {
+        let mut payload = [0u8; PAYLOAD_LEN];
+        payload[0] = 0x01;
+        payload[2..35].copy_from_slice(&self.point.sec(true));
+        payload[35..67].copy_from_slice(&self.chain_code);
+        payload
+    }
+
+    pub fn from_payload(payload: &[u8]) -> io::Result<Self> {
+        if payload.len() != PAYLOAD_LEN || payload[0] != 0x01 {
+            return Err(Error::new(ErrorKind::InvalidData, "Not a version 1 payment code"));
+        }
+        Ok(Self { point: S256Point::parse(&payload[2..35])?, chain_code: payload[35..67].try_into().unwrap() })
+    }
+
+    // "PM8T..."
+    pub fn encode(&self) -> String {
+        let mut data = vec![VERSION_PREFIX];
+        data.extend_from_slice(&self.payload());
+        base58_check(&data)
+    }
+
+    pub fn parse(s: &str) -> io::Result<Self> {
+        let data = decode_base58_check(s)?;
+        if data.first() != Some(&VERSION_PREFIX) {
+            return Err(Error::new(ErrorKind::InvalidData, "Not a payment code"));
+        }
+        Self::from_payload(&data[1..])
+    }
+
+    // Non-hardened child i of the code, as if it were an xpub
+    pub fn pubkey(&self, index: u32) -> S256Point {
+        let xpub = ExtendedPubKey { network: Network::MAINNET, depth: 0, parent_fingerprint: [0u8; 4], child_number: 0, chain_code: self.chain_code, point: self.point };
+        xpub.derive_child(index).expect("payment code indexes must be non-hardened").point
+    }
+
+    pub fn notification_address(&self) -> String {
+        self.pubkey(0).address(true, false)
+    }
+}
+
+// Sender's code with x and the chain code masked by HMAC-SHA512(outpoint, x(a·B)), where a
+// is the key of the designated input spending outpoint and B the recipient's notification key
+pub fn blinded_payload(sender: &PaymentCode, designated_secret: U256, outpoint: &OutPoint, recipient: &PaymentCode) -> [u8; PAYLOAD_LEN] {
+    let shared = recipient.pubkey(0).rmul(designated_secret);
+    let mut payload = sender.payload();
+    apply_mask(&mut payload, &notification_mask(shared, outpoint));
+    payload
+}
+
+// Unsigned notification transaction. The first input is the designated input and must be
+// signed with designated_secret; extra_outputs carry change.
+pub fn notification_tx(sender: &PaymentCode, designated_secret: U256, tx_ins: Vec<TxIn>, recipient: &PaymentCode, extra_outputs: Vec<TxOut>) -> io::Result<Tx> {
+    let designated = tx_ins.first().ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Notification transaction needs a designated input"))?;
+    let payload = blinded_payload(sender, designated_secret, &designated.previous_output, recipient);
+
+    // OP_RETURN OP_PUSHDATA1 80 <payload>
+    let mut op_return = vec![0x6a, 0x4c, PAYLOAD_LEN as u8];
+    op_return.extend_from_slice(&payload);
+
+    let mut tx_outs = vec![
+        TxOut::new(NOTIFICATION_AMOUNT, p2pkh_script(&hash160(&recipient.pubkey(0).sec(true)))),
+        TxOut::new(0, op_return),
+    ];
+    tx_outs.extend(extra_outputs);
+    Ok(Tx::new(1, tx_ins, tx_outs, 0, Network::MAINNET))
+}
+
+// Recipient side: the sender's payment code if tx notifies the owner of account. The
+// designated input is the first one that exposes a public key.
+pub fn read_notification(tx: &Tx, account: &ExtendedPrivKey) -> Option<PaymentCode> {
+    let payload = tx.tx_outs.iter().find_map(|tx_out| {
+        let script = &tx_out.script_pubkey;
+        (script.len() == 3 + PAYLOAD_LEN && script[..3] == [0x6a, 0x4c, PAYLOAD_LEN as u8]).then(|| script[3..].to_vec())
+    })?;
+    let (designated, pubkey) = tx.tx_ins.iter().find_map(|tx_in| exposed_pubkey(tx_in).map(|pubkey| (tx_in, pubkey)))?;
+    let shared = pubkey.rmul(account.derive_child(0).ok()?.secret);
+
+    let mut payload: [u8; PAYLOAD_LEN] = payload.try_into().unwrap();
+    apply_mask(&mut payload, &notification_mask(shared, &designated.previous_output));
+    PaymentCode::from_payload(&payload).ok()
+}
+
+// The key of a P2WPKH witness (native or behind P2SH), else the last push of the scriptSig
+// as in P2PKH
+fn exposed_pubkey(tx_in: &TxIn) -> Option<S256Point> {
+    if let [_, pubkey] = tx_in.witness.as_slice() {
+        return S256Point::parse(pubkey).ok();
+    }
+    let script_sig = &tx_in.script_sig;
+    let mut last_push = None;
+    let mut pc = 0;
+    while pc < script_sig.len() {
+        last_push = next_op(script_sig, &mut pc).ok()?.1;
+    }
+    S256Point::parse(last_push?).ok()
+}
+
+// Sender side: address of the index-th payment to recipient, B_i + sha256(x(a_0·B_i))G
+pub fn send_address(sender_account: &ExtendedPrivKey, recipient: &PaymentCode, index: u32) -> io::Result<String> {
+    let b_i = recipient.pubkey(index);
+    let s = shared_secret(b_i.rmul(sender_account.derive_child(0)?.secret))?;
+    Ok((b_i + S256Point::g().rmul(s)).address(true, false))
+}
+
+// Recipient side: key for the index-th payment from sender, b_i + sha256(x(b_i·A_0))
+pub fn receive_key(recipient_account: &ExtendedPrivKey, sender: &PaymentCode, index: u32) -> io::Result<PrivateKey> {
+    let b_i = recipient_account.derive_child(index)?.secret;
+    let s = shared_secret(sender.pubkey(0).rmul(b_i))?;
+    Ok(PrivateKey::new((Scalar::new(b_i) + Scalar::new(s)).num))
+}
+
+fn shared_secret(shared: S256Point) -> io::Result<U256> {
+    let s = U256::from_be_slice(&Sha256::digest(shared.x().unwrap().num.to_be_bytes()));
+    // BIP47 says to move to the next index; odds are about 2^-128
+    if s >= S256Point::n() {
+        return Err(Error::new(ErrorKind::InvalidInput, "Shared secret out of range, use the next index"));
+    }
+    Ok(s)
+}
+
+fn notification_mask(shared: S256Point, outpoint: &OutPoint) -> [u8; 64] {
+    let mut serialized = outpoint.tx_id.to_vec();
+    serialized.extend_from_slice(&outpoint.vout.to_le_bytes());
+    hmac_sha512(&serialized, &shared.x().unwrap().num.to_be_bytes())
+}
+
+// x sits at bytes 3..35 and the chain code at 35..67
+fn apply_mask(payload: &mut [u8; PAYLOAD_LEN], mask: &[u8; 64]) {
+    payload[3..67].iter_mut().zip(mask).for_each(|(b, m)| *b ^= m);
+}
+
+#[cfg(test)]
+mod payment_code_tests {
+    use crate::wallet::bip39::mnemonic_to_seed;
+    use super::*;
+
+    // Account keys m/47'/0'/0' of Alice and Bob from the BIP47 reference data
+    fn account(mnemonic: &str) -> ExtendedPrivKey {
+        let seed = mnemonic_to_seed(mnemonic, "");
+        ExtendedPrivKey::new_master(&seed, Network::MAINNET).derive_path("m/47'/0'/0'").unwrap()
+    }
+
+    fn alice() -> ExtendedPrivKey {
+        account("response seminar brave tip suit recall often sound stick owner lottery motion")
+    }
+
+    fn bob() -> ExtendedPrivKey {
+        account("reward upper indicate eight swift arch injury crystal super wrestle already dentist")
+    }
+
+    #[test]
+    fn payment_codes() -> Result<(), Box<dyn std::error::Error>> {
+        let alice = PaymentCode::from_account(&alice());
+        assert_eq!(alice.encode(), "PM8TJTLJbPRGxSbc8EJi42Wrr6QbNSaSSVJ5Y3E4pbCYiTHUskHg13935Ubb7q8tx9GVbh2UuRnBc3WSyJHhUrw8KhprKnn9eDznYGieTzFcwQRya4GA");
+        assert_eq!(alice.notification_address(), "1JDdmqFLhpzcUwPeinhJbUPw4Co3aWLyzW");
+
+        let bob = PaymentCode::from_account(&bob());
+        assert_eq!(bob.encode(), "PM8TJS2JxQ5ztXUpBBRnpTbcUXbUHy2T1abfrb3KkAAtMEGNbey4oumH7Hc578WgQJhPjBxteQ5GHHToTYHE3A1w6p7tU6KSoFmWBVbFGjKPisZDbP97");
+        assert_eq!(PaymentCode::parse(&bob.encode())?, bob);
+        assert_eq!(bob.notification_address(), "1ChvUUvht2hUQufHBXF8NgLhW8SwE2ecGV");
+        Ok(())
+    }
+
+    #[test]
+    fn notification_transaction() -> Result<(), Box<dyn std::error::Error>> {
+        let alice = PaymentCode::from_account(&alice());
+        let bob_account = bob();
+        let bob = PaymentCode::from_account(&bob_account);
+
+        // Designated input of the reference notification transaction
+        let designated_secret = U256::from_be_hex("1b7a10f45118e2519a8dd46ef81591c1ae501d082b6610fdda3de7a3c932880d");
+        let mut script_sig = vec![0x01, 0x00, 0x21];
+        script_sig.extend_from_slice(&S256Point::g().rmul(designated_secret).sec(true));
+        assert_eq!(hex::encode(&script_sig[3..]), "0272d83d8a1fa323feab1c085157a0791b46eba34afb8bfbfaeb3a3fcc3f2c9ad8");
+        let tx_id = hex::decode("86f411ab1c8e70ae8a0795ab7a6757aea6e4d5ae1826fc7b8f00c597d500609c")?.try_into().unwrap();
+        let tx_in = TxIn::new(tx_id, 1, script_sig, 0xffffffff);
+
+        assert_eq!(notification_tx(&alice, designated_secret, vec![], &bob, vec![]).err().unwrap().kind(), ErrorKind::InvalidInput);
+        let tx = notification_tx(&alice, designated_secret, vec![tx_in], &bob, vec![])?;
+        assert_eq!(hex::encode(&tx.tx_outs[0].script_pubkey), "76a9148066a8e7ee82e5c5b9b7dc1765038340dc5420a988ac");
+        assert_eq!(hex::encode(&tx.tx_outs[1].script_pubkey), "6a4c50010002063e4eb95e62791b06c50e1a3a942e1ecaaa9afbbeb324d16ae6821e091611fa96c0cf048f607fe51a0327f5e2528979311c78cb2de0d682c61e1180fc3d543b00000000000000000000000000");
+        assert_eq!(read_notification(&tx, &bob_account), Some(alice));
+
+        // An earlier input without a key (taproot key path) isn't the designated one
+        let mut taproot = TxIn::new([7u8; 32], 0, Vec::new(), 0xffffffff);
+        taproot.witness = vec![vec![0x01; 64]];
+        let mut tx = tx;
+        tx.tx_ins.insert(0, taproot);
+        assert_eq!(read_notification(&tx, &bob_account), Some(alice));
+
+        // A P2WPKH designated input exposes its key in the witness
+        let mut p2wpkh = TxIn::new(tx_id, 1, Vec::new(), 0xffffffff);
+        p2wpkh.witness = vec![vec![0x30; 71], S256Point::g().rmul(designated_secret).sec(true)];
+        let tx = notification_tx(&alice, designated_secret, vec![p2wpkh], &bob, vec![])?;
+        assert_eq!(read_notification(&tx, &bob_account), Some(alice));
+        Ok(())
+    }
+
+    #[test]
+    fn payment_addresses() -> Result<(), Box<dyn std::error::Error>> {
+        let (alice, bob) = (alice(), bob());
+        let alice_code = PaymentCode::from_account(&alice);
+        let bob_code = PaymentCode::from_account(&bob);
+
+        // First Alice -> Bob addresses from the reference data; Bob derives the same keys
+        let expected = ["141fi7TY3h936vRUKh1qfUZr8rSBuYbVBK", "12u3Uued2fuko2nY4SoSFGCoGLCBUGPkk6", "1FsBVhT5dQutGwaPePTYMe5qvYqqjxyftc"];
+        for (index, address) in (0..).zip(expected) {
+            assert_eq!(send_address(&alice, &bob_code, index)?, address);
+            assert_eq!(receive_key(&bob, &alice_code, index)?.point.address(true, false), address);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_codes() {
+        assert!(PaymentCode::parse("1ChvUUvht2hUQufHBXF8NgLhW8SwE2ecGV").is_err());
+        assert!(PaymentCode::from_payload(&[0u8; 80]).is_err());
+    }
+}
@@ -0,0 +1,372 @@
+use std::collections::HashMap;
+use std::io::{self, Error, ErrorKind};
+use crypto_bigint::U256;
+use crate::{algorithms::{bech32::{convert_bits, decode, encode, Variant}, tagged_hash::tagged_hash}, bitcoin::tx::{tx::{Network, Tx}, tx_in::TxIn}, s256::{s256_field::S256Field, s256_point::S256Point, scalr::Scalar}};
+
+// BIP352 silent payments. The sender tweaks the recipient's spend key with an ECDH
+// secret between the sum of its input keys and the recipient's scan key, so every
+// payment lands on a fresh taproot output only the recipient can find and spend.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SilentPaymentAddress {
+    pub scan: S256Point,
+    pub spend: S256Point,
+    pub network: Network,
+}
+
+impl SilentPaymentAddress {
+    pub fn new(scan: S256Point, spend: S256Point, network: Network) -> Self {
+        Self { scan, spend, network }
+    }
+
+    // Address for label m: B_spend + hash(b_scan || m) G. m = 0 is reserved for change.
+    pub fn labeled(&self, b_scan: U256, m: u32) -> Self {
+        let spend = self.spend + S256Point::g().rmul(label_tweak(b_scan, m));
+        Self { spend, ..*self }
+    }
+
+    // bech32m with hrp sp / tsp, version 0, then ser(B_scan) || ser(B_spend)
+    pub fn encode(&self) -> String {
+        let mut payload = self.scan.sec(true);
+        payload.extend_from_slice(&self.spend.sec(true));
+
+        let mut data = vec![0u8];
+        data.extend(convert_bits(&payload, 8, 5, true).expect("bytes always regroup"));
+        encode(hrp(self.network), &data, Variant::Bech32m)
+    }
+
+    pub fn parse(address: &str) -> io::Result<Self> {
+        let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, msg.to_string());
+
+        let (hrp, data, variant) = decode(address, 1023)?;
+        let network = match hrp.as_str() {
+            "sp" => Network::MAINNET,
+            "tsp" => Network::TESTNET,
+            _ => return Err(invalid("Not a silent payment address")),
+        };
+        if variant != Variant::Bech32m || data.is_empty() {
+            return Err(invalid("Silent payment addresses use bech32m"));
+        }
+
+        let version = data[0];
+        let payload = convert_bits(&data[1..], 5, 8, false)?;
+
+        // Later versions must keep the v0 keys up front, so read those and ignore the rest
+        match version {
+            0 if payload.len() == 66 => {}
+            1..=30 if payload.len() >= 66 => {}
+            _ => return Err(invalid("Unsupported silent payment version or length")),
+        }
+
+        Ok(Self { scan: S256Point::parse(&payload[..33])?, spend: S256Point::parse(&payload[33..66])?, network })
+    }
+}
+
+// A spendable input of the transaction being built. Taproot keys are negated when
+// their point has an odd y, to match the x-only key the receiver sees.
+pub struct InputKey {
+    pub secret: U256,
+    pub taproot: bool,
+}
+
+// P2TR script_pubkeys, one per recipient in order. tx_ins are all of the transaction's
+// inputs (the smallest outpoint feeds the hash); keys are those of the eligible inputs.
+pub fn sender_outputs(tx_ins: &[TxIn], keys: &[InputKey], recipients: &[SilentPaymentAddress]) -> io::Result<Vec<Vec<u8>>> {
+    let invalid = |msg: &str| Error::new(ErrorKind::InvalidInput, msg.to_string());
+
+    let n = S256Point::n();
+    let mut a = U256::ZERO;
+    for key in keys {
+        let mut secret = key.secret;
+        if secret == U256::ZERO || secret >= n {
+            return Err(invalid("Input key out of range"));
+        }
+        if key.taproot && is_odd(S256Point::g().rmul(secret)) {
+            secret = n - secret;
+        }
+        a = a.add_mod(&secret, &n);
+    }
+    if a == U256::ZERO {
+        return Err(invalid("Input keys sum to zero"));
+    }
+
+    let big_a = S256Point::g().rmul(a);
+    let input_hash = input_tweak(tx_ins, big_a).ok_or_else(|| invalid("Transaction has no inputs"))?;
+    let tweak = (Scalar::new(input_hash) * Scalar::new(a)).num;
+
+    // k counts outputs per scan key; the ECDH secret is shared by every output to that key
+    let mut counters: HashMap<Vec<u8>, u32> = HashMap::new();
+    let mut secrets: HashMap<Vec<u8>, S256Point> = HashMap::new();
+    Ok(recipients.iter().map(|recipient| {
+        let scan = recipient.scan.sec(true);
+        let shared = *secrets.entry(scan.clone()).or_insert_with(|| recipient.scan.rmul(tweak));
+        let k = counters.entry(scan).or_insert(0);
+        let output = recipient.spend + S256Point::g().rmul(shared_secret_tweak(shared, *k));
+        *k += 1;
+        p2tr_script(output)
+    }).collect())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundOutput {
+    pub vout: usize,
+    // Spend with b_spend + tweak (negated if the output key has an odd y)
+    pub tweak: U256,
+    pub label: Option<u32>,
+}
+
+// Receiver side. input_pubkeys are the public keys of the eligible inputs, taproot ones
+// as their even-y point; input_pubkey() extracts them from P2PKH inputs.
+pub fn scan(tx: &Tx, input_pubkeys: &[S256Point], b_scan: U256, spend: S256Point, labels: &[u32]) -> Vec<FoundOutput> {
+    let big_a = input_pubkeys.iter().fold(S256Point::new_inf(), |acc, &p| acc + p);
+    if big_a.is_inf() {
+        return Vec::new();
+    }
+
+    let Some(input_hash) = input_tweak(&tx.tx_ins, big_a) else {
+        return Vec::new();
+    };
+    let n = S256Point::n();
+    let shared = big_a.rmul((Scalar::new(input_hash) * Scalar::new(b_scan)).num);
+
+    let label_points: Vec<(u32, U256, S256Point)> = labels.iter().map(|&m| {
+        let t = label_tweak(b_scan, m);
+        (m, t, S256Point::g().rmul(t))
+    }).collect();
+
+    let mut candidates: Vec<(usize, S256Point)> = tx.tx_outs.iter().enumerate()
+        .filter_map(|(vout, out)| p2tr_output_key(&out.script_pubkey).map(|key| (vout, key)))
+        .collect();
+
+    let mut found = Vec::new();
+    let mut k = 0u32;
+    loop {
+        let t_k = shared_secret_tweak(shared, k);
+        let p_k = spend + S256Point::g().rmul(t_k);
+
+        let hit = candidates.iter().enumerate().find_map(|(i, &(vout, output))| {
+            if output.x() == p_k.x() {
+                return Some((i, FoundOutput { vout, tweak: t_k, label: None }));
+            }
+            // output = P_k + label G, with the sign of the output lost to x-only encoding
+            label_points.iter()
+                .find(|(_, _, point)| *point == output - p_k || *point == -output - p_k)
+                .map(|&(m, t, _)| (i, FoundOutput { vout, tweak: t_k.add_mod(&t, &n), label: Some(m) }))
+        });
+
+        match hit {
+            Some((i, output)) => {
+                candidates.remove(i);
+                found.push(output);
+                k += 1;
+            }
+            None => return found,
+        }
+    }
+}
+
+// Compressed pubkey pushed last by a P2PKH script_sig
+pub fn input_pubkey(tx_in: &TxIn) -> Option<S256Point> {
+    let script_sig = &tx_in.script_sig;
+    if script_sig.len() < 34 || script_sig[script_sig.len() - 34] != 33 {
+        return None;
+    }
+    S256Point::parse(&script_sig[script_sig.len() - 33..]).ok()
+}
+
+// hash_BIP0352/Inputs(outpoint_L || A) with outpoint_L the smallest serialized outpoint,
+// None without inputs
+fn input_tweak(tx_ins: &[TxIn], big_a: S256Point) -> Option<U256> {
+    let outpoint_l = tx_ins.iter().map(|tx_in| {
+        let mut outpoint = tx_in.previous_output.tx_id.to_vec();
+        outpoint.extend_from_slice(&tx_in.previous_output.vout.to_le_bytes());
+        outpoint
+    }).min()?;
+
+    let mut preimage = outpoint_l;
+    preimage.extend_from_slice(&big_a.sec(true));
+    Some(hash_to_scalar("BIP0352/Inputs", &preimage))
+}
+
+fn shared_secret_tweak(shared: S256Point, k: u32) -> U256 {
+    let mut preimage = shared.sec(true);
+    preimage.extend_from_slice(&k.to_be_bytes());
+    hash_to_scalar("BIP0352/SharedSecret", &preimage)
+}
+
+fn label_tweak(b_scan: U256, m: u32) -> U256 {
+    let mut preimage = b_scan.to_be_bytes().to_vec();
+    preimage.extend_from_slice(&m.to_be_bytes());
+    hash_to_scalar("BIP0352/Label", &preimage)
+}
+
+// BIP352 treats a hash >= n as a failure; at probability ~2^-128 we just reduce
+fn hash_to_scalar(tag: &str, data: &[u8]) -> U256 {
+    U256::from_be_slice(&tagged_hash(tag, data)) % S256Point::n()
+}
+
+fn p2tr_script(output: S256Point) -> Vec<u8> {
+    let mut script = vec![0x51, 0x20];
+    script.extend_from_slice(&output.x().unwrap().num.to_be_bytes());
+    script
+}
+
+fn p2tr_output_key(script_pubkey: &[u8]) -> Option<S256Point> {
+    if script_pubkey.len() != 34 || script_pubkey[0] != 0x51 || script_pubkey[1] != 0x20 {
+        return None;
+    }
+    let x = U256::from_be_slice(&script_pubkey[2..]);
+    if x >= S256Field::new_zero().get_prime() {
+        return None;
+    }
+    S256Point::lift_x(S256Field::new(x))
+}
+
+fn is_odd(point: S256Point) -> bool {
+    point.y().unwrap().num.bit(0).into()
+}
+
+fn hrp(network: Network) -> &'static str {
+    match network {
+        Network::MAINNET => "sp",
+        Network::TESTNET => "tsp",
+    }
+}
+
+#[cfg(test)]
+mod silent_payments_tests {
+    use crate::bitcoin::tx::tx_out::TxOut;
+    use super::*;
+
+    // Keys, addresses and outputs from the BIP352 test vectors
+    const ADDRESS: &str = "sp1qqgste7k9hx0qftg6qmwlkqtwuy6cycyavzmzj85c6qdfhjdpdjtdgqjuexzk6murw56suy3e0rd2cgqvycxttddwsvgxe2usfpxumr70xc9pkqwv";
+    const LABELED_ADDRESSES: [&str; 3] = [
+        "sp1qqgste7k9hx0qftg6qmwlkqtwuy6cycyavzmzj85c6qdfhjdpdjtdgqaxww2fnhrx05cghth75n0qcj59e3e2anscr0q9wyknjxtxycg07y3pevyj",
+        "sp1qqgste7k9hx0qftg6qmwlkqtwuy6cycyavzmzj85c6qdfhjdpdjtdgqjex54dmqmmv6rw353tsuqhs99ydvadxzrsy9nuvk74epvee55drs734pqq",
+        "sp1qqgste7k9hx0qftg6qmwlkqtwuy6cycyavzmzj85c6qdfhjdpdjtdgqsg59z2rppn4qlkx0yz9sdltmjv3j8zgcqadjn4ug98m3t6plujsq9qvu5n",
+    ];
+    const TX_IDS: [&str; 2] = [
+        "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16",
+        "a1075db55d416d3ca199f55b6084e2115b9345e16c5cf302fc80e9d5fbf5d48d",
+    ];
+    const KEY_A: &str = "eadc78165ff1f8ea94ad7cfdc54990738a4c53f6e0507b42154201b8e5dff3b1";
+    const KEY_B: &str = "fc8716a97a48ba9a05a98ae47b5cd201a25a7fd5d8b73c203c5f7b6b6b3b6ad7";
+    const KEY_C: &str = "8d4751f6e8a3586880fb66c19ae277969bd5aa06f61c4ee2f1e2486efdf666d3";
+
+    fn b_scan() -> U256 {
+        U256::from_be_hex("0f694e068028a717f8af6b9411f9a133dd3565258714cc226594b34db90c1f2c")
+    }
+
+    fn receiver() -> SilentPaymentAddress {
+        let b_spend = U256::from_be_hex("9d6ad855ce3417ef84e836892e5a56392bfba05fa5d97ccea30e266f540e08b3");
+        SilentPaymentAddress::new(S256Point::g().rmul(b_scan()), S256Point::g().rmul(b_spend), Network::MAINNET)
+    }
+
+    // Vector txids are in display order
+    fn tx_ins() -> Vec<TxIn> {
+        TX_IDS.iter().map(|id| {
+            let mut tx_id: [u8; 32] = hex::decode(id).unwrap().try_into().unwrap();
+            tx_id.reverse();
+            TxIn::new(tx_id, 0, Vec::new(), 0xffffffff)
+        }).collect()
+    }
+
+    fn key(secret: &str, taproot: bool) -> InputKey {
+        InputKey { secret: U256::from_be_hex(secret), taproot }
+    }
+
+    #[test]
+    fn address_encoding() -> Result<(), Box<dyn std::error::Error>> {
+        let address = receiver();
+        assert_eq!(address.encode(), ADDRESS);
+        assert_eq!(SilentPaymentAddress::parse(ADDRESS)?, address);
+
+        for (m, labeled) in (1..).zip(LABELED_ADDRESSES) {
+            assert_eq!(address.labeled(b_scan(), m).encode(), labeled);
+        }
+
+        let testnet = SilentPaymentAddress { network: Network::TESTNET, ..address };
+        assert_eq!(SilentPaymentAddress::parse(&testnet.encode())?, testnet);
+
+        assert!(SilentPaymentAddress::parse("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn sender_vectors() -> Result<(), Box<dyn std::error::Error>> {
+        let address = receiver();
+        let cases = [
+            // Multiple UTXOs from the same public key
+            ([key(KEY_A, false), key(KEY_A, false)], "548ae55c8eec1e736e8d3e520f011f1f42a56d166116ad210b3937599f87f566"),
+            // Taproot only inputs with even y-values
+            ([key(KEY_A, true), key(KEY_B, true)], "de88bea8e7ffc9ce1af30d1132f910323c505185aec8eae361670421e749a1fb"),
+            // Taproot input with even y-value and non-taproot input
+            ([key(KEY_A, true), key(KEY_C, false)], "30523cca96b2a9ae3c98beb5e60f7d190ec5bc79b2d11a0b2d4d09a608c448f0"),
+        ];
+        for (keys, output) in cases {
+            assert_eq!(sender_outputs(&tx_ins(), &keys, &[address])?, vec![hex::decode(format!("5120{}", output))?]);
+        }
+
+        // Keys outside [1, n), keys that cancel out, and a transaction without inputs
+        let errors = [
+            sender_outputs(&tx_ins(), &[InputKey { secret: U256::ZERO, taproot: false }], &[address]),
+            sender_outputs(&tx_ins(), &[InputKey { secret: S256Point::n(), taproot: true }], &[address]),
+            sender_outputs(&tx_ins(), &[key(KEY_A, false), InputKey { secret: S256Point::n() - U256::from_be_hex(KEY_A), taproot: false }], &[address]),
+            sender_outputs(&[], &[key(KEY_A, false)], &[address]),
+        ];
+        for error in errors {
+            assert_eq!(error.unwrap_err().kind(), ErrorKind::InvalidInput);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn scan_simple_send() -> Result<(), Box<dyn std::error::Error>> {
+        // Simple send: two P2PKH inputs
+        let input_pubkeys = [
+            S256Point::parse(&hex::decode("025a1e61f898173040e20616d43e9f496fba90338a39faa1ed98fcbaeee4dd9be5")?)?,
+            S256Point::parse(&hex::decode("03bd85685d03d111699b15d046319febe77f8de5286e9e512703cdee1bf3be3792")?)?,
+        ];
+        let tx_outs = vec![TxOut::new(1000, hex::decode("51203e9fce73d4e77a4809908e3c3a2e54ee147b9312dc5044a193d1fc85de46e3c1")?)];
+        let tx = Tx::new(2, tx_ins(), tx_outs, 0, Network::MAINNET);
+
+        let found = scan(&tx, &input_pubkeys, b_scan(), receiver().spend, &[]);
+        assert_eq!(found, vec![
+            FoundOutput { vout: 0, tweak: U256::from_be_hex("f438b40179a3c4262de12986c0e6cce0634007cdc79c1dcd3e20b9ebc2e7eef6"), label: None },
+        ]);
+
+        // Nothing to find without inputs
+        let no_inputs = Tx::new(2, Vec::new(), tx.tx_outs, 0, Network::MAINNET);
+        assert!(scan(&no_inputs, &input_pubkeys, b_scan(), receiver().spend, &[]).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn send_and_scan() -> Result<(), Box<dyn std::error::Error>> {
+        let address = receiver();
+        let labeled = SilentPaymentAddress::parse(LABELED_ADDRESSES[1])?;
+
+        // One P2PKH input and one taproot input (whose key has an odd y and gets negated)
+        let tx_ins = vec![TxIn::new([0x11; 32], 1, Vec::new(), 0xffffffff), TxIn::new([0x22; 32], 0, Vec::new(), 0xffffffff)];
+        let keys = [InputKey { secret: U256::from_u64(0x1234), taproot: false }, InputKey { secret: U256::from_u64(0x5678), taproot: true }];
+        let scripts = sender_outputs(&tx_ins, &keys, &[address, address, labeled])?;
+
+        // The receiver sees the outputs shuffled, next to an unrelated one
+        let mut tx_outs: Vec<TxOut> = scripts.iter().rev().map(|script| TxOut::new(1000, script.clone())).collect();
+        tx_outs.insert(1, TxOut::new(5000, hex::decode("0014751e76e8199196d454941c45d1b3a323f1433bd6")?));
+        let tx = Tx::new(2, tx_ins, tx_outs, 0, Network::MAINNET);
+
+        let input_pubkeys = [S256Point::g().rmul(U256::from_u64(0x1234)), S256Point::g().rmul(U256::from_u64(0x5678))];
+        let input_pubkeys = [input_pubkeys[0], S256Point::lift_x(input_pubkeys[1].x().unwrap()).unwrap()];
+
+        let found = scan(&tx, &input_pubkeys, b_scan(), address.spend, &[1, 2]);
+        assert_eq!(found.iter().map(|f| (f.vout, f.label)).collect::<Vec<_>>(), vec![(3, None), (2, None), (0, Some(2))]);
+
+        // Each tweak spends its output: (b_spend + tweak) G has the output's x
+        for (output, script) in found.iter().zip(&scripts) {
+            let key = address.spend + S256Point::g().rmul(output.tweak);
+            assert_eq!(p2tr_script(key), *script);
+        }
+        Ok(())
+    }
+}
\ No newline at end of file
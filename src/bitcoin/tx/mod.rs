@@ -1,3 +1,4 @@
 pub mod tx_in;
 pub mod tx_out;
-pub mod tx;
\ No newline at end of file
+pub mod tx;
+pub mod sighash;
\ No newline at end of file
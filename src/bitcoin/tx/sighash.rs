@@ -0,0 +1,105 @@
+use std::io::{self, Error, ErrorKind};
+
+// The flag byte appended to a signature. NONE and SINGLE leave other inputs' sequences and
+// (some) outputs unsigned; ANYONECANPAY signs only the spending input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SighashType {
+    // BIP341 only: ALL, signalled by a 64-byte signature without a flag byte
+    Default,
+    All,
+    None,
+    Single,
+    AllAnyoneCanPay,
+    NoneAnyoneCanPay,
+    SingleAnyoneCanPay,
+}
+
+impl SighashType {
+    // ECDSA (legacy and BIP143): anything outside the six defined bytes is non-standard
+    pub fn from_ecdsa_byte(byte: u8) -> io::Result<Self> {
+        match Self::from_byte(byte) {
+            Some(SighashType::Default) | None => Err(invalid_byte(byte)),
+            Some(sighash_type) => Ok(sighash_type),
+        }
+    }
+
+    // BIP341 rejects undefined bytes by consensus
+    pub fn from_schnorr_byte(byte: u8) -> io::Result<Self> {
+        Self::from_byte(byte).ok_or_else(|| invalid_byte(byte))
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        Some(match byte {
+            0x00 => SighashType::Default,
+            0x01 => SighashType::All,
+            0x02 => SighashType::None,
+            0x03 => SighashType::Single,
+            0x81 => SighashType::AllAnyoneCanPay,
+            0x82 => SighashType::NoneAnyoneCanPay,
+            0x83 => SighashType::SingleAnyoneCanPay,
+            _ => return None,
+        })
+    }
+
+    pub fn to_byte(self) -> u8 {
+        match self {
+            SighashType::Default => 0x00,
+            SighashType::All => 0x01,
+            SighashType::None => 0x02,
+            SighashType::Single => 0x03,
+            SighashType::AllAnyoneCanPay => 0x81,
+            SighashType::NoneAnyoneCanPay => 0x82,
+            SighashType::SingleAnyoneCanPay => 0x83,
+        }
+    }
+
+    pub fn anyone_can_pay(self) -> bool {
+        self.to_byte() & 0x80 != 0
+    }
+
+    // No outputs are signed
+    pub fn is_none(self) -> bool {
+        self.to_byte() & 0x1f == 0x02
+    }
+
+    // Only the output at the input's index is signed
+    pub fn is_single(self) -> bool {
+        self.to_byte() & 0x1f == 0x03
+    }
+}
+
+// BIP143 hashes of the prevouts, sequences and outputs, shared by every input's sighash.
+// Tx::verify computes them once for all inputs, unlike the legacy sighash which rehashes the
+// whole transaction per input.
+pub struct SegwitV0Hashes {
+    pub prevouts: [u8; 32],
+    pub sequences: [u8; 32],
+    pub outputs: [u8; 32],
+}
+
+fn invalid_byte(byte: u8) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("Invalid sighash type 0x{byte:02x}"))
+}
+
+#[cfg(test)]
+mod sighash_tests {
+    use super::*;
+
+    #[test]
+    fn flag_bytes() -> Result<(), Box<dyn std::error::Error>> {
+        for byte in [0x01, 0x02, 0x03, 0x81, 0x82, 0x83] {
+            assert_eq!(SighashType::from_ecdsa_byte(byte)?.to_byte(), byte);
+            assert_eq!(SighashType::from_schnorr_byte(byte)?.to_byte(), byte);
+        }
+        assert_eq!(SighashType::from_schnorr_byte(0x00)?, SighashType::Default);
+        assert!(SighashType::from_ecdsa_byte(0x00).is_err());
+        assert!(SighashType::from_ecdsa_byte(0x04).is_err());
+        assert!(SighashType::from_schnorr_byte(0x80).is_err());
+        assert!(SighashType::from_schnorr_byte(0x21).is_err());
+
+        let single_acp = SighashType::SingleAnyoneCanPay;
+        assert!(single_acp.anyone_can_pay() && single_acp.is_single() && !single_acp.is_none());
+        assert!(!SighashType::Default.anyone_can_pay() && !SighashType::Default.is_none());
+        Ok(())
+    }
+}
@@ -1,7 +1,8 @@
-use std::io::{self, Read};
+use std::io::{self, Error, ErrorKind, Read};
 use crypto_bigint::U256;
+use sha2::{Digest, Sha256};
 
-use crate::{algorithms::{endian::int_to_little_endian, hash256::hash256}, bitcoin::{tx::{tx_in::TxIn, tx_out::TxOut}, tx_fetcher::TxFetcher, utils::read_u32_le, varint::{encode_varint, read_varint}}};
+use crate::{algorithms::{der::is_strict_der, endian::int_to_little_endian, hash160::hash160, hash256::hash256, tagged_hash::tagged_hash}, bitcoin::{op_code::{interpreter::{cast_to_bool, evaluate, is_push_only, next_op, Checker}, script::{find_and_delete, is_p2sh, p2pkh_script, p2sh_script, push_data, strip_code_separators, witness_program}}, tx::{sighash::{SegwitV0Hashes, SighashType}, tx_in::TxIn, tx_out::TxOut}, tx_fetcher::{PrevoutSource, TxFetcher}, utils::{read_bytes, read_u32_le}, varint::{encode_varint, read_varint}}, s256::{private_key::PrivateKey, s256_point::S256Point, signature::Signature}};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Network {
@@ -9,6 +10,7 @@ pub enum Network {
     TESTNET
 }
 
+
 pub struct Tx {
     pub id: String,
     pub version: u32,
@@ -25,8 +27,9 @@ impl Tx {
         instance
     }
 
+    // txid: witnesses are left out, so signing an input doesn't change the id of a segwit spend
     pub fn hash(&self) -> String {
-        let serialized = self.serialize();
+        let serialized = self.serialize_legacy();
 
         let hash = hash256(&serialized);
 
@@ -36,10 +39,30 @@ impl Tx {
         hex::encode(txid)
     }
 
+    // BIP141 wtxid, equal to the txid when no input has a witness
+    pub fn wtxid(&self) -> String {
+        let mut wtxid = hash256(&self.serialize()).to_vec();
+        wtxid.reverse();
+        hex::encode(wtxid)
+    }
+
+    pub fn has_witness(&self) -> bool {
+        self.tx_ins.iter().any(|tx_in| !tx_in.witness.is_empty())
+    }
+
     pub fn parse<R: Read>(stream: &mut R) -> io::Result<Tx> {
         let version = read_u32_le(stream)?;
 
-        let tx_in_count = read_varint(stream)?;
+        // BIP144: a zero input count is the segwit marker, followed by the flag
+        let mut tx_in_count = read_varint(stream)?;
+        let segwit = tx_in_count == 0;
+        if segwit {
+            let flag = read_bytes(stream, 1)?[0];
+            if flag != 0x01 {
+                return Err(Error::new(ErrorKind::InvalidData, format!("Unknown segwit flag 0x{flag:02x}")));
+            }
+            tx_in_count = read_varint(stream)?;
+        }
         let mut tx_ins: Vec<TxIn> = Vec::new();
         for _i in 0..tx_in_count {
             tx_ins.push(TxIn::parse(stream)?);
@@ -51,13 +74,42 @@ impl Tx {
             tx_outs.push(TxOut::parse(stream)?);
         }
 
+        if segwit {
+            for tx_in in &mut tx_ins {
+                tx_in.parse_witness(stream)?;
+            }
+            // Otherwise the same transaction would have a second encoding
+            if tx_ins.iter().all(|tx_in| tx_in.witness.is_empty()) {
+                return Err(Error::new(ErrorKind::InvalidData, "Segwit marker without witness data"));
+            }
+        }
+
         let locktime = read_u32_le(stream)?;
 
         Ok(Tx::new(version, tx_ins, tx_outs, locktime, Network::MAINNET))
 
     }
 
+    // BIP144 encoding when any input has a witness, the legacy one otherwise
     pub fn serialize(&self) -> Vec<u8> {
+        if !self.has_witness() {
+            return self.serialize_legacy();
+        }
+
+        let legacy = self.serialize_legacy();
+        let (body, locktime) = legacy[4..].split_at(legacy.len() - 8);
+        let mut result = legacy[..4].to_vec();
+        result.extend_from_slice(&[0x00, 0x01]);
+        result.extend_from_slice(body);
+        for tx_in in &self.tx_ins {
+            result.extend_from_slice(&tx_in.serialize_witness());
+        }
+        result.extend_from_slice(locktime);
+        result
+    }
+
+    // Without marker, flag and witnesses, as hashed for the txid
+    pub fn serialize_legacy(&self) -> Vec<u8> {
         let mut result: Vec<u8> = Vec::new();
         result.extend_from_slice(&int_to_little_endian(U256::from_u32(self.version), 4));
 
@@ -96,11 +148,465 @@ impl Tx {
         input_sats - out_sats
     }
 
+    // Runs the scriptSig and then the previous output's script_pubkey, plus the redeem script
+    // for P2SH and the witness for segwit v0. Later witness versions are refused.
+    pub fn verify_input(&self, input_index: usize, prevouts: &mut impl PrevoutSource) -> io::Result<()> {
+        self.verify_input_with(input_index, prevouts, &self.segwit_v0_hashes())
+    }
+
+    fn verify_input_with(&self, input_index: usize, prevouts: &mut impl PrevoutSource, hashes: &SegwitV0Hashes) -> io::Result<()> {
+        let tx_in = self.tx_ins.get(input_index).ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Input index out of range"))?;
+        let prevout = prevouts.prev_output(&tx_in.previous_output, self.network)?;
+        let script_pubkey = prevout.script_pubkey;
+        let checker = TxChecker { tx: self, input_index, segwit_v0: None };
+        if let Some((version, program)) = witness_program(&script_pubkey) {
+            if !tx_in.script_sig.is_empty() {
+                return Err(Error::new(ErrorKind::InvalidData, "Native segwit scriptSig must be empty"));
+            }
+            return self.verify_witness(input_index, version, program, prevout.amount, hashes);
+        }
+
+        let mut stack = Vec::new();
+        evaluate(&tx_in.script_sig, &mut stack, &checker)?;
+        let mut p2sh_stack = stack.clone();
+        evaluate(&script_pubkey, &mut stack, &checker)?;
+        check_result(&stack)?;
+
+        // BIP16: the script_pubkey only checked the hash of the last push, which must run as well
+        if is_p2sh(&script_pubkey) {
+            if !is_push_only(&tx_in.script_sig) {
+                return Err(Error::new(ErrorKind::InvalidData, "P2SH scriptSig must be push-only"));
+            }
+            let redeem_script = p2sh_stack.pop().expect("OP_HASH160 consumed an element");
+            // P2SH-wrapped segwit: the scriptSig is exactly the push of the witness program
+            if let Some((version, program)) = witness_program(&redeem_script) {
+                if tx_in.script_sig != push_data(&redeem_script) {
+                    return Err(Error::new(ErrorKind::InvalidData, "P2SH segwit scriptSig must only push the program"));
+                }
+                return self.verify_witness(input_index, version, program, prevout.amount, hashes);
+            }
+            evaluate(&redeem_script, &mut p2sh_stack, &checker)?;
+            check_result(&p2sh_stack)?;
+        }
+
+        if !tx_in.witness.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidData, "Witness on a non-segwit input"));
+        }
+        Ok(())
+    }
+
+    // BIP141: P2WPKH runs the P2PKH script of the key hash, P2WSH the last witness item, on the
+    // rest of the witness. Either must leave exactly one true element.
+    fn verify_witness(&self, input_index: usize, version: u8, program: &[u8], amount: u64, hashes: &SegwitV0Hashes) -> io::Result<()> {
+        let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, msg.to_string());
+        if version != 0 {
+            return Err(Error::new(ErrorKind::Unsupported, format!("Verifying witness version {version} is not supported")));
+        }
+
+        let witness = &self.tx_ins[input_index].witness;
+        let (script, mut stack) = match program.len() {
+            20 if witness.len() == 2 => (p2pkh_script(program), witness.clone()),
+            20 => return Err(invalid("P2WPKH witness must be a signature and a public key")),
+            32 => {
+                let (witness_script, items) = witness.split_last().ok_or_else(|| invalid("Empty P2WSH witness"))?;
+                if Sha256::digest(witness_script)[..] != *program {
+                    return Err(invalid("Witness script does not match the program"));
+                }
+                (witness_script.clone(), items.to_vec())
+            }
+            _ => return Err(invalid("Witness v0 program must be 20 or 32 bytes")),
+        };
+        if stack.iter().any(|item| item.len() > 520) {
+            return Err(invalid("Witness item larger than 520 bytes"));
+        }
+
+        let checker = TxChecker { tx: self, input_index, segwit_v0: Some((amount, hashes)) };
+        evaluate(&script, &mut stack, &checker)?;
+        if stack.len() != 1 {
+            return Err(invalid("Witness script must leave exactly one element"));
+        }
+        check_result(&stack)
+    }
+
+    // Every input, after checking that the inputs cover the outputs
+    pub fn verify(&self, prevouts: &mut impl PrevoutSource) -> io::Result<()> {
+        let mut input_sats = 0u64;
+        for tx_in in &self.tx_ins {
+            let amount = prevouts.prev_output(&tx_in.previous_output, self.network)?.amount;
+            input_sats = input_sats.checked_add(amount).ok_or_else(|| Error::new(ErrorKind::InvalidData, "Input amounts overflow"))?;
+        }
+        let mut output_sats = 0u64;
+        for tx_out in &self.tx_outs {
+            output_sats = output_sats.checked_add(tx_out.amount).ok_or_else(|| Error::new(ErrorKind::InvalidData, "Output amounts overflow"))?;
+        }
+        if output_sats > input_sats {
+            return Err(Error::new(ErrorKind::InvalidData, "Outputs exceed inputs"));
+        }
+
+        let hashes = self.segwit_v0_hashes();
+        for input_index in 0..self.tx_ins.len() {
+            self.verify_input_with(input_index, prevouts, &hashes)?;
+        }
+        Ok(())
+    }
+
+    // Signs a P2PKH input and writes <DER + hash type> <SEC> into its scriptSig. The SEC format
+    // (compressed or not) is whichever one the previous output pays to.
+    pub fn sign_input(&mut self, input_index: usize, private_key: &PrivateKey, sighash_type: SighashType, prevouts: &mut impl PrevoutSource) -> io::Result<()> {
+        check_ecdsa_sighash(sighash_type)?;
+        let tx_in = self.tx_ins.get(input_index).ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Input index out of range"))?;
+        let script_pubkey = prevouts.prev_output(&tx_in.previous_output, self.network)?.script_pubkey;
+        let sec = [true, false].into_iter()
+            .map(|compressed| private_key.point.sec(compressed))
+            .find(|sec| p2pkh_script(&hash160(sec)) == script_pubkey)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Previous output is not P2PKH to this key"))?;
+
+        let z = self.sig_hash(input_index, &script_pubkey, sighash_type)?;
+        let mut sig = private_key.sign(z).der();
+        sig.push(sighash_type.to_byte());
+
+        let mut script_sig = push_data(&sig);
+        script_sig.extend_from_slice(&push_data(&sec));
+        self.tx_ins[input_index].script_sig = script_sig;
+        // The legacy txid covers the scriptSig
+        self.id = self.hash();
+
+        self.verify_input(input_index, prevouts)
+    }
+
+    // Signs a P2WPKH input, native or P2SH-wrapped depending on the previous output, with the
+    // witness <DER + hash type> <compressed SEC>
+    pub fn sign_p2wpkh(&mut self, input_index: usize, private_key: &PrivateKey, sighash_type: SighashType, prevouts: &mut impl PrevoutSource) -> io::Result<()> {
+        check_ecdsa_sighash(sighash_type)?;
+        let sec = private_key.point.sec(true);
+        let key_hash = hash160(&sec);
+        let mut program = vec![0x00, 0x14];
+        program.extend_from_slice(&key_hash);
+        let prevout = self.segwit_prevout(input_index, &program, prevouts)?;
+
+        let hashes = self.segwit_v0_hashes();
+        let z = self.segwit_v0_sig_hash(input_index, &p2pkh_script(&key_hash), prevout.amount, sighash_type, &hashes)?;
+        let mut sig = private_key.sign(z).der();
+        sig.push(sighash_type.to_byte());
+        self.tx_ins[input_index].witness = vec![sig, sec];
+
+        self.verify_input(input_index, prevouts)
+    }
+
+    // Signs a P2WSH input, native or P2SH-wrapped, with one signature per key in the given order
+    // followed by the witness script. Meant for single-key and multisig scripts: a script ending
+    // in OP_CHECKMULTISIG also gets the dummy element that opcode pops.
+    pub fn sign_p2wsh(&mut self, input_index: usize, private_keys: &[&PrivateKey], witness_script: &[u8], sighash_type: SighashType, prevouts: &mut impl PrevoutSource) -> io::Result<()> {
+        check_ecdsa_sighash(sighash_type)?;
+        let mut program = vec![0x00, 0x20];
+        program.extend_from_slice(&Sha256::digest(witness_script));
+        let prevout = self.segwit_prevout(input_index, &program, prevouts)?;
+
+        let hashes = self.segwit_v0_hashes();
+        let z = self.segwit_v0_sig_hash(input_index, witness_script, prevout.amount, sighash_type, &hashes)?;
+        // A script ending in OP_CHECKMULTISIG pops one extra, unused item
+        let mut last_op = None;
+        let mut pc = 0;
+        while pc < witness_script.len() {
+            last_op = Some(next_op(witness_script, &mut pc)?.0);
+        }
+        let mut witness = Vec::new();
+        if last_op == Some(0xae) {
+            witness.push(Vec::new());
+        }
+        for private_key in private_keys {
+            let mut sig = private_key.sign(z).der();
+            sig.push(sighash_type.to_byte());
+            witness.push(sig);
+        }
+        witness.push(witness_script.to_vec());
+        self.tx_ins[input_index].witness = witness;
+
+        self.verify_input(input_index, prevouts)
+    }
+
+    // Looks up the output an input spends and sets the scriptSig for a witness program: empty
+    // when the output is the program itself, a push of it when the output is its P2SH.
+    fn segwit_prevout(&mut self, input_index: usize, program: &[u8], prevouts: &mut impl PrevoutSource) -> io::Result<TxOut> {
+        let tx_in = self.tx_ins.get(input_index).ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Input index out of range"))?;
+        let prevout = prevouts.prev_output(&tx_in.previous_output, self.network)?;
+        let script_sig = if prevout.script_pubkey == program {
+            Vec::new()
+        } else if prevout.script_pubkey == p2sh_script(&hash160(program)) {
+            push_data(program)
+        } else {
+            return Err(Error::new(ErrorKind::InvalidInput, "Previous output does not pay to this witness program"));
+        };
+        self.tx_ins[input_index].script_sig = script_sig;
+        self.id = self.hash();
+        Ok(prevout)
+    }
+
+    // Legacy (pre-segwit) signature hash, as the big-endian z that sign() and verify() take.
+    // script_code is the raw script being satisfied, e.g. the previous output's script_pubkey or
+    // a P2SH redeem script.
+    pub fn sig_hash(&self, input_index: usize, script_code: &[u8], sighash_type: SighashType) -> io::Result<U256> {
+        check_ecdsa_sighash(sighash_type)?;
+        self.sig_hash_raw(input_index, script_code, sighash_type.to_byte() as u32)
+    }
+
+    // Consensus accepts any hash type here: the value is hashed as given and only its low five
+    // bits (NONE = 2, SINGLE = 3, anything else ALL) and 0x80 (ANYONECANPAY) are interpreted
+    pub fn sig_hash_raw(&self, input_index: usize, script_code: &[u8], hash_type: u32) -> io::Result<U256> {
+        if input_index >= self.tx_ins.len() {
+            return Err(Error::new(ErrorKind::InvalidInput, "Input index out of range"));
+        }
+        let none = hash_type & 0x1f == 0x02;
+        let single = hash_type & 0x1f == 0x03;
+        let anyone_can_pay = hash_type & 0x80 != 0;
+
+        // Consensus bug: SIGHASH_SINGLE without a matching output signs the number 1
+        if single && input_index >= self.tx_outs.len() {
+            let mut one = [0u8; 32];
+            one[0] = 1;
+            return Ok(U256::from_be_slice(&one));
+        }
+
+        let script_code = strip_code_separators(script_code);
+        let mut result: Vec<u8> = Vec::new();
+        result.extend_from_slice(&int_to_little_endian(U256::from_u32(self.version), 4));
+
+        let signed_ins: Vec<usize> = if anyone_can_pay { vec![input_index] } else { (0..self.tx_ins.len()).collect() };
+        result.extend_from_slice(&encode_varint(U256::from_u64(signed_ins.len() as u64)));
+        for i in signed_ins {
+            let tx_in = &self.tx_ins[i];
+            let script_sig = if i == input_index { script_code.clone() } else { Vec::new() };
+            // NONE and SINGLE let the other inputs be replaced
+            let sequence = if i != input_index && (none || single) { 0 } else { tx_in.sequence };
+            let blanked = TxIn::new(tx_in.previous_output.tx_id, tx_in.previous_output.vout, script_sig, sequence);
+            result.extend_from_slice(&blanked.serialize());
+        }
+
+        if none {
+            result.extend_from_slice(&encode_varint(U256::ZERO));
+        } else if single {
+            // Outputs before ours become empty with an amount of -1
+            result.extend_from_slice(&encode_varint(U256::from_u64(input_index as u64 + 1)));
+            for _ in 0..input_index {
+                result.extend_from_slice(&TxOut::new(u64::MAX, Vec::new()).serialize());
+            }
+            result.extend_from_slice(&self.tx_outs[input_index].serialize());
+        } else {
+            result.extend_from_slice(&encode_varint(U256::from_u64(self.tx_outs.len() as u64)));
+            for tx_out in &self.tx_outs {
+                result.extend_from_slice(&tx_out.serialize());
+            }
+        }
+
+        result.extend_from_slice(&int_to_little_endian(U256::from_u32(self.locktime), 4));
+        result.extend_from_slice(&hash_type.to_le_bytes());
+        Ok(U256::from_be_slice(&hash256(&result)))
+    }
+
+    pub fn segwit_v0_hashes(&self) -> SegwitV0Hashes {
+        let double_sha256 = |items: Vec<Vec<u8>>| -> [u8; 32] { Sha256::digest(sha256_concat(items.into_iter())).into() };
+        SegwitV0Hashes {
+            prevouts: double_sha256(self.tx_ins.iter().map(outpoint_bytes).collect()),
+            sequences: double_sha256(self.tx_ins.iter().map(|tx_in| tx_in.sequence.to_le_bytes().to_vec()).collect()),
+            outputs: double_sha256(self.tx_outs.iter().map(|tx_out| tx_out.serialize()).collect()),
+        }
+    }
+
+    // BIP143 signature hash for witness v0 inputs, which commits to the amount being spent.
+    // script_code is the witness script for P2WSH and the P2PKH script of the key hash for P2WPKH.
+    pub fn segwit_v0_sig_hash(&self, input_index: usize, script_code: &[u8], amount: u64, sighash_type: SighashType, hashes: &SegwitV0Hashes) -> io::Result<U256> {
+        check_ecdsa_sighash(sighash_type)?;
+        self.segwit_v0_sig_hash_raw(input_index, script_code, amount, sighash_type.to_byte() as u32, hashes)
+    }
+
+    // Same reading of the hash type as sig_hash_raw, without the SIGHASH_SINGLE bug
+    pub fn segwit_v0_sig_hash_raw(&self, input_index: usize, script_code: &[u8], amount: u64, hash_type: u32, hashes: &SegwitV0Hashes) -> io::Result<U256> {
+        let tx_in = self.tx_ins.get(input_index).ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Input index out of range"))?;
+        let none = hash_type & 0x1f == 0x02;
+        let single = hash_type & 0x1f == 0x03;
+        let anyone_can_pay = hash_type & 0x80 != 0;
+
+        let mut result: Vec<u8> = Vec::new();
+        result.extend_from_slice(&self.version.to_le_bytes());
+        result.extend_from_slice(if anyone_can_pay { &[0; 32] } else { &hashes.prevouts });
+        result.extend_from_slice(if anyone_can_pay || none || single { &[0; 32] } else { &hashes.sequences });
+        result.extend_from_slice(&outpoint_bytes(tx_in));
+        result.extend_from_slice(&encode_varint(U256::from_u64(script_code.len() as u64)));
+        result.extend_from_slice(script_code);
+        result.extend_from_slice(&amount.to_le_bytes());
+        result.extend_from_slice(&tx_in.sequence.to_le_bytes());
+        if !none && !single {
+            result.extend_from_slice(&hashes.outputs);
+        } else if single && input_index < self.tx_outs.len() {
+            result.extend_from_slice(&hash256(&self.tx_outs[input_index].serialize()));
+        } else {
+            result.extend_from_slice(&[0; 32]);
+        }
+        result.extend_from_slice(&self.locktime.to_le_bytes());
+        result.extend_from_slice(&hash_type.to_le_bytes());
+        Ok(U256::from_be_slice(&hash256(&result)))
+    }
+
+    // BIP341 signature hash for taproot inputs. prevouts are the outputs spent by every input, in
+    // order. annex is the witness annex (starting with 0x50), if any; leaf is (tapleaf hash,
+    // position of the last executed OP_CODESEPARATOR or 0xffffffff) for script path spends.
+    pub fn taproot_sig_hash(&self, input_index: usize, prevouts: &[TxOut], sighash_type: SighashType, annex: Option<&[u8]>, leaf: Option<([u8; 32], u32)>) -> io::Result<U256> {
+        let invalid = |msg: &str| Error::new(ErrorKind::InvalidInput, msg.to_string());
+        if input_index >= self.tx_ins.len() {
+            return Err(invalid("Input index out of range"));
+        }
+        if prevouts.len() != self.tx_ins.len() {
+            return Err(invalid("Need one previous output per input"));
+        }
+        // Unlike legacy, a missing output is an error rather than a signature over 1
+        if sighash_type.is_single() && input_index >= self.tx_outs.len() {
+            return Err(invalid("SIGHASH_SINGLE without a matching output"));
+        }
+
+        // Epoch 0, then SigMsg
+        let mut msg = vec![0x00, sighash_type.to_byte()];
+        msg.extend_from_slice(&self.version.to_le_bytes());
+        msg.extend_from_slice(&self.locktime.to_le_bytes());
+
+        if !sighash_type.anyone_can_pay() {
+            msg.extend_from_slice(&sha256_concat(self.tx_ins.iter().map(outpoint_bytes)));
+            msg.extend_from_slice(&sha256_concat(prevouts.iter().map(|prevout| prevout.amount.to_le_bytes().to_vec())));
+            msg.extend_from_slice(&sha256_concat(prevouts.iter().map(|prevout| prevout.serialize()[8..].to_vec())));
+            msg.extend_from_slice(&sha256_concat(self.tx_ins.iter().map(|tx_in| tx_in.sequence.to_le_bytes().to_vec())));
+        }
+        if !sighash_type.is_none() && !sighash_type.is_single() {
+            msg.extend_from_slice(&sha256_concat(self.tx_outs.iter().map(|tx_out| tx_out.serialize())));
+        }
+
+        msg.push(if leaf.is_some() { 2 } else { 0 } + if annex.is_some() { 1 } else { 0 });
+        if sighash_type.anyone_can_pay() {
+            let tx_in = &self.tx_ins[input_index];
+            msg.extend_from_slice(&outpoint_bytes(tx_in));
+            msg.extend_from_slice(&prevouts[input_index].serialize());
+            msg.extend_from_slice(&tx_in.sequence.to_le_bytes());
+        } else {
+            msg.extend_from_slice(&(input_index as u32).to_le_bytes());
+        }
+
+        if let Some(annex) = annex {
+            if annex.first() != Some(&0x50) {
+                return Err(invalid("Annex must start with 0x50"));
+            }
+            let mut serialized = encode_varint(U256::from_u64(annex.len() as u64));
+            serialized.extend_from_slice(annex);
+            msg.extend_from_slice(&Sha256::digest(&serialized));
+        }
+        if sighash_type.is_single() {
+            msg.extend_from_slice(&Sha256::digest(self.tx_outs[input_index].serialize()));
+        }
+        // BIP342 extension, key version 0
+        if let Some((leaf_hash, code_separator_position)) = leaf {
+            msg.extend_from_slice(&leaf_hash);
+            msg.push(0x00);
+            msg.extend_from_slice(&code_separator_position.to_le_bytes());
+        }
+
+        Ok(U256::from_be_slice(&tagged_hash("TapSighash", &msg)))
+    }
+
+}
+
+// The ECDSA signing paths append the flag byte, which SIGHASH_DEFAULT doesn't have
+fn check_ecdsa_sighash(sighash_type: SighashType) -> io::Result<()> {
+    if sighash_type == SighashType::Default {
+        return Err(Error::new(ErrorKind::InvalidInput, "SIGHASH_DEFAULT is taproot only"));
+    }
+    Ok(())
+}
+
+fn check_result(stack: &[Vec<u8>]) -> io::Result<()> {
+    match stack.last() {
+        Some(top) if cast_to_bool(top) => Ok(()),
+        _ => Err(Error::new(ErrorKind::InvalidData, "Script evaluated to false")),
+    }
+}
+
+// Signature and timelock checks for an input. segwit_v0 holds the spent amount and the BIP143
+// hashes when the script runs from a witness.
+struct TxChecker<'a> {
+    tx: &'a Tx,
+    input_index: usize,
+    segwit_v0: Option<(u64, &'a SegwitV0Hashes)>,
+}
+
+impl Checker for TxChecker<'_> {
+    fn script_code(&self, script_code: &[u8], sigs: &[Vec<u8>]) -> Vec<u8> {
+        match self.segwit_v0 {
+            // BIP143 keeps the signatures in the script code
+            Some(_) => script_code.to_vec(),
+            None => sigs.iter().fold(script_code.to_vec(), |code, sig| find_and_delete(&code, &push_data(sig))),
+        }
+    }
+
+    fn check_sig(&self, sig: &[u8], pubkey: &[u8], script_code: &[u8]) -> bool {
+        let Some((&hash_type, der)) = sig.split_last() else {
+            return false;
+        };
+        // BIP66: Signature::parse alone would take non-minimal encodings
+        if !is_strict_der(der) {
+            return false;
+        }
+        let (Ok(signature), Ok(point)) = (Signature::parse(der), S256Point::parse(pubkey)) else {
+            return false;
+        };
+        let z = match self.segwit_v0 {
+            Some((amount, hashes)) => self.tx.segwit_v0_sig_hash_raw(self.input_index, script_code, amount, hash_type as u32, hashes),
+            None => self.tx.sig_hash_raw(self.input_index, script_code, hash_type as u32),
+        };
+        let Ok(z) = z else {
+            return false;
+        };
+        point.verify_ecdsa(z, signature.r.num, signature.s.num)
+    }
+
+    fn check_locktime(&self, locktime: i64) -> bool {
+        const THRESHOLD: i64 = 500_000_000;
+        let tx_locktime = self.tx.locktime as i64;
+        // Heights and timestamps don't compare, and a final input disables the locktime
+        (locktime < THRESHOLD) == (tx_locktime < THRESHOLD)
+            && locktime <= tx_locktime
+            && self.tx.tx_ins[self.input_index].sequence != 0xffffffff
+    }
+
+    fn check_sequence(&self, sequence: i64) -> bool {
+        const DISABLE: i64 = 1 << 31;
+        const TYPE: i64 = 1 << 22;
+        const MASK: i64 = TYPE | 0xffff;
+        if sequence & DISABLE != 0 {
+            return true;
+        }
+        let tx_sequence = self.tx.tx_ins[self.input_index].sequence as i64;
+        if self.tx.version < 2 || tx_sequence & DISABLE != 0 {
+            return false;
+        }
+        // Blocks against blocks, 512 second units against units
+        (sequence & TYPE) == (tx_sequence & TYPE) && (sequence & MASK) <= (tx_sequence & MASK)
+    }
+}
+
+fn outpoint_bytes(tx_in: &TxIn) -> Vec<u8> {
+    let mut result = tx_in.previous_output.tx_id.to_vec();
+    result.extend_from_slice(&tx_in.previous_output.vout.to_le_bytes());
+    result
+}
+
+fn sha256_concat(items: impl Iterator<Item = Vec<u8>>) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for item in items {
+        hasher.update(item);
+    }
+    hasher.finalize().into()
 }
 
 #[cfg(test)]
 mod tx_tests {
-    use std::io::Cursor;
+    use std::{collections::HashMap, io::Cursor};
+    use crate::{bitcoin::tx::tx_in::OutPoint, s256::s256_field::S256Field};
     use super::*;
 
     /*
@@ -281,6 +787,48 @@ mod tx_tests {
     }
 
 
+    #[test]
+    fn segwit_parse_serialize() -> Result<(), Box<dyn std::error::Error>> {
+        // One P2WPKH input (rust-bitcoin's test_segwit_transaction)
+        let raw = hex::decode("02000000000101595895ea20179de87052b4046dfe6fd515860505d6511a9004cf12a1f93cac7c0100000000ffffffff01deb807000000000017a9140f3444e271620c736808aa7b33e370bd87cb5a078702483045022100fb60dad8df4af2841adc0346638c16d0b8035f5e3f3753b88db122e70c79f9370220756e6633b17fd2710e626347d28d60b0a2d6cbb41de51740644b9fb3ba7751040121028fa937ca8cba2197a37c007176ed8941055d3bcb8627d085e94553e62f057dcc00000000")?;
+        let tx = Tx::parse(&mut Cursor::new(&raw))?;
+
+        assert!(tx.has_witness());
+        assert_eq!(tx.tx_ins[0].previous_output.vout, 1);
+        assert_eq!(tx.tx_ins[0].witness.len(), 2);
+        assert_eq!(hex::encode(&tx.tx_ins[0].witness[1]), "028fa937ca8cba2197a37c007176ed8941055d3bcb8627d085e94553e62f057dcc");
+        assert_eq!(tx.tx_outs[0].amount, 506_078);
+        assert_eq!(tx.serialize(), raw);
+        assert_eq!(tx.id, "f5864806e3565c34d1b41e716f72609d00b55ea5eac5b924c9719a842ef42206");
+        assert_eq!(tx.wtxid(), "80b7d8a82d5d5bf92905b06f2014dd699e03837ca172e3a59d51426ebbe3e7f5");
+
+        // Stripping the witness keeps the txid
+        let legacy = tx.serialize_legacy();
+        let stripped = Tx::parse(&mut Cursor::new(&legacy))?;
+        assert!(!stripped.has_witness());
+        assert_eq!(stripped.serialize(), legacy);
+        assert_eq!(stripped.wtxid(), tx.id);
+
+        // Oversized witness counts and lengths are rejected before allocating
+        let witness_start = legacy.len() - 4 + 2;
+        let mut many_items = raw[..witness_start].to_vec();
+        many_items.extend_from_slice(&[0xfe, 0x01, 0x09, 0x3d, 0x00]);
+        assert_eq!(Tx::parse(&mut Cursor::new(many_items)).err().unwrap().to_string(), "Too many witness items");
+        let mut long_item = raw[..witness_start].to_vec();
+        long_item.extend_from_slice(&[0x01, 0xfe, 0x01, 0x09, 0x3d, 0x00]);
+        assert_eq!(Tx::parse(&mut Cursor::new(long_item)).err().unwrap().to_string(), "Witness item too large");
+
+        // Unknown flag, and a marker with only empty witnesses
+        let mut bad_flag = raw.clone();
+        bad_flag[5] = 0x02;
+        assert!(Tx::parse(&mut Cursor::new(bad_flag)).is_err());
+        let mut empty = legacy[..legacy.len() - 4].to_vec();
+        empty.splice(4..4, [0x00, 0x01]);
+        empty.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x00]);
+        assert!(Tx::parse(&mut Cursor::new(empty)).is_err_and(|e| e.kind() == ErrorKind::InvalidData));
+        Ok(())
+    }
+
     #[test]
     fn fee() -> Result<(), Box<dyn std::error::Error>> {
         let mut fetcher = TxFetcher::new();
@@ -291,4 +839,411 @@ mod tx_tests {
 
         Ok(())
     }
+
+    #[test]
+    fn sig_hash_p2pkh() -> Result<(), Box<dyn std::error::Error>> {
+        // Programming Bitcoin, chapter 7
+        let tx = Tx::parse(&mut Cursor::new(hex::decode("0100000001813f79011acb80925dfe69b3def355fe914bd1d96a3f5f71bf8303c6a989c7d1000000006b483045022100ed81ff192e75a3fd2304004dcadb746fa5e24c5031ccfcf21320b0277457c98f02207a986d955c6e0cb35d446a89d3f56100f4d7f67801c31967743a9c8e10615bed01210349fc4e631e3624a545de3f89f5d8684c7b8138bd94bdd531d2e213bf016b278afeffffff02a135ef01000000001976a914bc3b654dca7e56b04dca18f2566cdaf02e8d9ada88ac99c39800000000001976a9141c4bc762dd5423e332166702cb75f40df79fea1288ac19430600")?))?;
+        let script_pubkey = hex::decode("76a914a802fc56c704ce87c42d7c92eb75e7896bdc41ae88ac")?;
+        assert_eq!(tx.sig_hash(0, &script_pubkey, SighashType::All)?, U256::from_be_hex("27e0c5994dec7824e56dec6b2fcb342eb7cdb0d0957c2fce9882f715e85d81a6"));
+        // Taproot's type and a missing input are errors
+        assert_eq!(tx.sig_hash(0, &script_pubkey, SighashType::Default).unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(tx.sig_hash(1, &script_pubkey, SighashType::All).unwrap_err().kind(), ErrorKind::InvalidInput);
+        Ok(())
+    }
+
+    #[test]
+    fn sig_hash_core_vectors() -> Result<(), Box<dyn std::error::Error>> {
+        // Bitcoin Core sighash.json (tx, script, input, hash type, reversed hash), one per
+        // combination of base type, ANYONECANPAY, negative and non-standard hash types
+        let vectors = [
+            ("cf7bdc250249e22cbe23baf6b648328d31773ea0e771b3b76a48b4748d7fbd390e88a004d30000000003ac536a4ab8cce0e097136c90b2037f231b7fde2063017facd40ed4e5896da7ad00e9c71dd70ae600000000096a0063516352525365ffffffff01b71e3e00000000000300536a00000000", "", 1, 546970113, "6a815ba155270af102322c882f26d22da11c5330a751f520807936b320b9af5d"),
+            ("0728c606014c1fd6005ccf878196ba71a54e86cc8c53d6db500c3cc0ac369a26fac6fcbc210000000005ab53ac5365ba9668290182d7870100000000066a000053655100000000", "65", 0, 1789961588, "ab6baa6da3b2bc853868d166f8996ad31d63ef981179f9104f49968fd61c8427"),
+            ("b7877f82019c832707a60cf14fba44cfa254d787501fdd676bd58c744f6e951dbba0b3b77f0200000009ac515263ac53525300a5a36e500148f89c0500000000085265ac6a6a65acab00000000", "6563", 0, -1785108415, "cb6e4322955af12eb29613c70e1a00ddbb559c887ba844df0bcdebed736dffbd"),
+            ("f2b539a401e4e8402869d5e1502dbc3156dbce93583f516a4947b333260d5af1a34810c6a00200000003525363ffffffff01d305e2000000000005acab535200a265fe77", "", 0, -1435650456, "41617b27321a830c712638dbb156dae23d4ef181c7a06728ccbf3153ec53d7dd"),
+            ("df0a32ae01c4672fd1abd0b2623aae0a1a8256028df57e532f9a472d1a9ceb194267b6ee190200000009536a6a51516a525251b545f9e803469a2302000000000465526500810631040000000000441f5b050000000006530051006aaceb183c76", "536a635252ac6a", 0, 1601138113, "9a0435996cc58bdba09643927fe48c1fc908d491a050abbef8daec87f323c58f"),
+            ("eabc0aa701fe489c0e4e6222d72b52f083166b49d63ad1410fb98caed027b6a71c02ab830c03000000075253ab63530065ffffffff01a5dc0b05000000000253533e820177", "", 0, 954499283, "1d849b92eedb9bf26bd4ced52ce9cb0595164295b0526842ab1096001fcd31b1"),
+            ("e3cdbfb4014d90ae6a4401e85f7ac717adc2c035858bf6ff48979dd399d155bce1f150daea0300000002ac51a67a0d39017f6c71040000000005535200535200000000", "", 0, -1899950911, "c1c7df8206e661d593f6455db1d61a364a249407f88e99ecad05346e495b38d7"),
+            ("c33028b301d5093e1e8397270d75a0b009b2a6509a01861061ab022ca122a6ba935b8513320200000000ffffffff013bcf5a0500000000015200000000", "", 0, -513413204, "6b1459536f51482f5dbf42d7e561896557461e1e3b6bf67871e2b51faae2832c"),
+            ("97be4f7702dc20b087a1fdd533c7de762a3f2867a8f439bddf0dcec9a374dfd0276f9c55cc0300000000cdfb1dbe6582499569127bda6ca4aaff02c132dc73e15dcd91d73da77e92a32a13d1a0ba0200000002ab51ffffffff048cfbe202000000000900516351515363ac535128ce0100000000076aac5365ab6aabc84e8302000000000863536a53ab6a6552f051230500000000066aac535153510848d813", "ac51", 0, 229541474, "e5da9a416ea883be1f8b8b2d178463633f19de3fa82ae25d44ffb531e35bdbc8"),
+            ("2f7353dd02e395b0a4d16da0f7472db618857cd3de5b9e2789232952a9b154d249102245fd030000000151617fd88f103280b85b0a198198e438e7cab1a4c92ba58409709997cc7a65a619eb9eec3c0200000003636aabffffffff0397481c0200000000045300636a0dc97803000000000009d389030000000003ac6a53134007bb", "0000536552526a", 0, -1912746174, "30c4cd4bd6b291f7e9489cc4b4440a083f93a7664ea1f93e77a9597dab8ded9c"),
+            ("b3cad3a7041c2c17d90a2cd994f6c37307753fa3635e9ef05ab8b1ff121ca11239a0902e700300000009ab635300006aac5163ffffffffcec91722c7468156dce4664f3c783afef147f0e6f80739c83b5f09d5a09a57040200000004516a6552ffffffff969d1c6daf8ef53a70b7cdf1b4102fb3240055a8eaeaed2489617cd84cfd56cf020000000352ab53ffffffff46598b6579494a77b593681c33422a99559b9993d77ca2fa97833508b0c169f80200000009655300655365516351ffffffff04d7ddf800000000000853536a65ac6351ab09f3420300000000056aab65abac33589d04000000000952656a65655151acac944d6f0400000000006a8004ba", "005165", 1, 1035865506, "fe1dc9e8554deecf8f50c417c670b839cc9d650722ebaaf36572418756075d58"),
+            ("32fa0b0804e6ea101e137665a041cc2350b794e59bf42d9b09088b01cde806ec1bbea077df0200000008515153650000006506a11c55904258fa418e57b88b12724b81153260d3f4c9f080439789a391ab147aabb0fa0000000007000052ac51ab510986f2a15c0d5e05d20dc876dd2dafa435276d53da7b47c393f20900e55f163b97ce0b800000000008ab526a520065636a8087df7d4d9c985fb42308fb09dce704650719140aa6050e8955fa5d2ea46b464a333f870000000009636300636a6565006affffffff01994a0d040000000002536500000000", "516563530065", 2, -163068286, "f58637277d2bc42e18358dc55f7e87e7043f5e33f4ce1fc974e715ef0d3d1c2a"),
+            ("6f62138301436f33a00b84a26a0457ccbfc0f82403288b9cbae39986b34357cb2ff9b889b302000000045253655335a7ff6701bac9960400000000086552ab656352635200000000", "6aac51", 0, 1444414211, "502a2435fd02898d2ff3ab08a3c19078414b32ec9b73d64a944834efc9dae10c"),
+            ("ceecfa6c02b7e3345445b82226b15b7a097563fa7d15f3b0c979232b138124b62c0be007890200000009abac51536a63525253ffffffffbae481ccb4f15d94db5ec0d8854c24c1cc8642bd0c6300ede98a91ca13a4539a0200000001ac50b0813d023110f5020000000006acabac526563e2b0d0040000000009656aac0063516a536300000000", "0063526500", 0, -1862053821, "e1600e6df8a6160a79ac32aa40bb4644daa88b5f76c0d7d13bf003327223f70c"),
+            ("9ff618e60136f8e6bb7eabaaac7d6e2535f5fba95854be6d2726f986eaa9537cb283c701ff02000000026a65ffffffff012d1c0905000000000865ab00ac6a516a652f9ad240", "51515253635351ac", 0, 1571304387, "659cd3203095d4a8672646add7d77831a1926fc5b66128801979939383695a79"),
+            ("d3b7421e011f4de0f1cea9ba7458bf3486bee722519efab711a963fa8c100970cf7488b7bb0200000003525352dcd61b300148be5d05000000000000000000", "535251536aac536a", 0, -1960128125, "29aa6d2d752d3310eba20442770ad345b7f6a35f96161ede5f07b33e92053e2a"),
+        ];
+        for (tx_hex, script, input_index, hash_type, expected) in vectors {
+            let tx = Tx::parse(&mut Cursor::new(hex::decode(tx_hex)?))?;
+            let mut hash = tx.sig_hash_raw(input_index, &hex::decode(script)?, hash_type as u32)?.to_be_bytes();
+            hash.reverse();
+            assert_eq!(hex::encode(hash), expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn sig_hash_single_bug() -> Result<(), Box<dyn std::error::Error>> {
+        let tx_ins = vec![TxIn::new([1u8; 32], 0, vec![], 0xffffffff), TxIn::new([2u8; 32], 0, vec![], 0xffffffff)];
+        let tx = Tx::new(1, tx_ins, vec![TxOut::new(1000, vec![0x51])], 0, Network::MAINNET);
+        let mut one = [0u8; 32];
+        one[0] = 1;
+        assert_eq!(tx.sig_hash(1, &[0x51], SighashType::Single)?, U256::from_be_slice(&one));
+        assert_eq!(tx.sig_hash(1, &[0x51], SighashType::SingleAnyoneCanPay)?, U256::from_be_slice(&one));
+        assert_ne!(tx.sig_hash(0, &[0x51], SighashType::Single)?, U256::from_be_slice(&one));
+        Ok(())
+    }
+
+    #[test]
+    fn sig_hash_code_separator() -> Result<(), Box<dyn std::error::Error>> {
+        let tx_ins = vec![TxIn::new([1u8; 32], 0, vec![], 0xffffffff)];
+        let tx = Tx::new(1, tx_ins, vec![TxOut::new(1000, vec![0x51])], 0, Network::MAINNET);
+        assert_eq!(tx.sig_hash(0, &[0xab, 0x51, 0xab], SighashType::All)?, tx.sig_hash(0, &[0x51], SighashType::All)?);
+        // A pushed 0xab byte is data, not a separator
+        assert_ne!(tx.sig_hash(0, &[0x01, 0xab], SighashType::All)?, tx.sig_hash(0, &[0x01], SighashType::All)?);
+        Ok(())
+    }
+
+    #[test]
+    fn taproot_sig_hash_bip341() -> Result<(), Box<dyn std::error::Error>> {
+        // BIP341 keyPathSpending vector: one input per sighash type
+        let tx = Tx::parse(&mut Cursor::new(hex::decode("02000000097de20cbff686da83a54981d2b9bab3586f4ca7e48f57f5b55963115f3b334e9c010000000000000000d7b7cab57b1393ace2d064f4d4a2cb8af6def61273e127517d44759b6dafdd990000000000fffffffff8e1f583384333689228c5d28eac13366be082dc57441760d957275419a418420000000000fffffffff0689180aa63b30cb162a73c6d2a38b7eeda2a83ece74310fda0843ad604853b0100000000feffffffaa5202bdf6d8ccd2ee0f0202afbbb7461d9264a25e5bfd3c5a52ee1239e0ba6c0000000000feffffff956149bdc66faa968eb2be2d2faa29718acbfe3941215893a2a3446d32acd050000000000000000000e664b9773b88c09c32cb70a2a3e4da0ced63b7ba3b22f848531bbb1d5d5f4c94010000000000000000e9aa6b8e6c9de67619e6a3924ae25696bb7b694bb677a632a74ef7eadfd4eabf0000000000ffffffffa778eb6a263dc090464cd125c466b5a99667720b1c110468831d058aa1b82af10100000000ffffffff0200ca9a3b000000001976a91406afd46bcdfd22ef94ac122aa11f241244a37ecc88ac807840cb0000000020ac9a87f5594be208f8532db38cff670c450ed2fea8fcdefcc9a663f78bab962b0065cd1d")?))?;
+        let prevouts = [
+            ("512053a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343", 420000000),
+            ("5120147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3", 462000000),
+            ("76a914751e76e8199196d454941c45d1b3a323f1433bd688ac", 294000000),
+            ("5120e4d810fd50586274face62b8a807eb9719cef49c04177cc6b76a9a4251d5450e", 504000000),
+            ("512091b64d5324723a985170e4dc5a0f84c041804f2cd12660fa5dec09fc21783605", 630000000),
+            ("00147dd65592d0ab2fe0d0257d571abf032cd9db93dc", 378000000),
+            ("512075169f4001aa68f15bbed28b218df1d0a62cbbcf1188c6665110c293c907b831", 672000000),
+            ("5120712447206d7a5238acc7ff53fbe94a3b64539ad291c7cdbc490b7577e4b17df5", 546000000),
+            ("512077e30a5522dd9f894c3f8b8bd4c4b2cf82ca7da8a3ea6a239655c39c050ab220", 588000000),
+        ];
+        let inputs = [
+            (0, 0x03, "2514a6272f85cfa0f45eb907fcb0d121b808ed37c6ea160a5a9046ed5526d555"),
+            (1, 0x83, "325a644af47e8a5a2591cda0ab0723978537318f10e6a63d4eed783b96a71a4d"),
+            (3, 0x01, "bf013ea93474aa67815b1b6cc441d23b64fa310911d991e713cd34c7f5d46669"),
+            (4, 0x00, "4f900a0bae3f1446fd48490c2958b5a023228f01661cda3496a11da502a7f7ef"),
+            (6, 0x02, "15f25c298eb5cdc7eb1d638dd2d45c97c4c59dcaec6679cfc16ad84f30876b85"),
+            (7, 0x82, "cd292de50313804dabe4685e83f923d2969577191a3e1d2882220dca88cbeb10"),
+            (8, 0x81, "cccb739eca6c13a8a89e6e5cd317ffe55669bbda23f2fd37b0f18755e008edd2"),
+        ];
+        let prevouts: Vec<TxOut> = prevouts.iter().map(|(script, amount)| TxOut::new(*amount, hex::decode(script).unwrap())).collect();
+        for (input_index, byte, expected) in inputs {
+            let sighash_type = SighashType::from_schnorr_byte(byte)?;
+            assert_eq!(tx.taproot_sig_hash(input_index, &prevouts, sighash_type, None, None)?, U256::from_be_hex(expected));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn taproot_sig_hash_annex_and_leaf() -> Result<(), Box<dyn std::error::Error>> {
+        // From rust-bitcoin's taproot sighash tests
+        let tx = Tx::parse(&mut Cursor::new(hex::decode("0200000001df8123752e8f37d132c4e9f1ff7e4f9b986ade9211267e9ebd5fd22a5e718dec6d01000000ce4023b903cb7b23000000000017a914a18b36ea7a094db2f4940fc09edf154e86de7bd787580200000000000017a914afd0d512a2c5c2b40e25669e9cc460303c325b8b87580200000000000017a914a18b36ea7a094db2f4940fc09edf154e86de7bd787f6020000")?))?;
+        let prevouts = [TxOut::new(2509290, hex::decode("5120ab5e9800806bf18cb246edcf5fe63441208fe955a4b5a35bbff65f5db622a010")?)];
+        let annex = hex::decode("507b979802e62d397acb29f56743a791894b99372872fc5af06a4f6e8d242d0615cda53062bb20e6ec79756fe39183f0c128adfe85559a8fa042b042c018aa8010143799e44f0893c40e1e")?;
+        let hash = tx.taproot_sig_hash(0, &prevouts, SighashType::SingleAnyoneCanPay, Some(&annex), None)?;
+        assert_eq!(hash, U256::from_be_hex("3b003000add359a364a156e73e02846782a59d0d95ca8c4638aaad99f2ef915c"));
+        assert!(tx.taproot_sig_hash(0, &prevouts, SighashType::All, Some(&annex[1..]), None).is_err());
+
+        let tx = Tx::parse(&mut Cursor::new(hex::decode("020000000189fc651483f9296b906455dd939813bf086b1bbe7c77635e157c8e14ae29062195010000004445b5c7044561320000000000160014331414dbdada7fb578f700f38fb69995fc9b5ab958020000000000001976a914268db0a8104cc6d8afd91233cc8b3d1ace8ac3ef88ac580200000000000017a914ec00dcb368d6a693e11986d265f659d2f59e8be2875802000000000000160014c715799a49a0bae3956df9c17cb4440a673ac0df6f010000")?))?;
+        let prevouts = [TxOut::new(3468315, hex::decode("512028055142ea437db73382e991861446040b61dd2185c4891d7daf6893d79f7182")?)];
+        let leaf_hash: [u8; 32] = hex::decode("15a2530514e399f8b5cf0b3d3112cf5b289eaa3e308ba2071b58392fdc6da68a")?.try_into().unwrap();
+        let hash = tx.taproot_sig_hash(0, &prevouts, SighashType::All, None, Some((leaf_hash, 0xffffffff)))?;
+        assert_eq!(hash, U256::from_be_hex("d66de5274a60400c7b08c86ba6b7f198f40660079edf53aca89d2a9501317f2e"));
+        Ok(())
+    }
+
+    #[test]
+    fn taproot_sig_hash_errors() {
+        let tx_ins = vec![TxIn::new([1u8; 32], 0, vec![], 0xffffffff), TxIn::new([2u8; 32], 0, vec![], 0xffffffff)];
+        let tx = Tx::new(2, tx_ins, vec![TxOut::new(1000, vec![0x51])], 0, Network::MAINNET);
+        let prevouts = [TxOut::new(2000, vec![0x51]), TxOut::new(2000, vec![0x51])];
+        assert!(tx.taproot_sig_hash(0, &prevouts[..1], SighashType::Default, None, None).is_err());
+        assert!(tx.taproot_sig_hash(2, &prevouts, SighashType::Default, None, None).is_err());
+        // No legacy SIGHASH_SINGLE bug
+        assert!(tx.taproot_sig_hash(1, &prevouts, SighashType::Single, None, None).is_err());
+        assert!(tx.taproot_sig_hash(0, &prevouts, SighashType::Single, None, None).is_ok());
+    }
+
+    fn prevouts_for(tx: &Tx, prevouts: &[(u64, &str)]) -> HashMap<OutPoint, TxOut> {
+        tx.tx_ins.iter().zip(prevouts)
+            .map(|(tx_in, (amount, spk))| (tx_in.previous_output, TxOut::new(*amount, hex::decode(spk).unwrap())))
+            .collect()
+    }
+
+    #[test]
+    fn verify_p2pkh() -> Result<(), Box<dyn std::error::Error>> {
+        // Programming Bitcoin, chapter 7
+        let raw = "0100000001813f79011acb80925dfe69b3def355fe914bd1d96a3f5f71bf8303c6a989c7d1000000006b483045022100ed81ff192e75a3fd2304004dcadb746fa5e24c5031ccfcf21320b0277457c98f02207a986d955c6e0cb35d446a89d3f56100f4d7f67801c31967743a9c8e10615bed01210349fc4e631e3624a545de3f89f5d8684c7b8138bd94bdd531d2e213bf016b278afeffffff02a135ef01000000001976a914bc3b654dca7e56b04dca18f2566cdaf02e8d9ada88ac99c39800000000001976a9141c4bc762dd5423e332166702cb75f40df79fea1288ac19430600";
+        let tx = Tx::parse(&mut Cursor::new(hex::decode(raw)?))?;
+        let spk = "76a914a802fc56c704ce87c42d7c92eb75e7896bdc41ae88ac";
+        tx.verify(&mut prevouts_for(&tx, &[(42_505_594, spk)]))?;
+        // The same signature with s padded by a zero byte breaks BIP66
+        let padded = raw.replace("6b483045022100ed81", "6c493046022100ed81").replace("02207a986d95", "0221007a986d95");
+        let lax = Tx::parse(&mut Cursor::new(hex::decode(padded)?))?;
+        assert_eq!(Signature::parse(&lax.tx_ins[0].script_sig[1..0x49])?, Signature::parse(&tx.tx_ins[0].script_sig[1..0x48])?);
+        assert!(lax.verify_input(0, &mut prevouts_for(&lax, &[(42_505_594, spk)])).is_err());
+
+        // Checked before any signature
+        let err = tx.verify(&mut prevouts_for(&tx, &[(42_000_000, spk)])).unwrap_err();
+        assert_eq!(err.to_string(), "Outputs exceed inputs");
+        let mut overflow = Tx::parse(&mut Cursor::new(tx.serialize()))?;
+        overflow.tx_outs.iter_mut().for_each(|tx_out| tx_out.amount = u64::MAX);
+        let err = overflow.verify(&mut prevouts_for(&tx, &[(42_505_594, spk)])).unwrap_err();
+        assert_eq!(err.to_string(), "Output amounts overflow");
+        // Another key's hash fails at OP_EQUALVERIFY
+        let wrong = "76a9141c4bc762dd5423e332166702cb75f40df79fea1288ac";
+        assert_eq!(tx.verify_input(0, &mut prevouts_for(&tx, &[(42_505_594, wrong)])).unwrap_err().kind(), ErrorKind::InvalidData);
+        // Missing previous output, and a scriptSig spending native segwit
+        assert_eq!(tx.verify_input(0, &mut HashMap::new()).unwrap_err().kind(), ErrorKind::NotFound);
+        let p2wpkh = "0014a802fc56c704ce87c42d7c92eb75e7896bdc41ae";
+        assert_eq!(tx.verify_input(0, &mut prevouts_for(&tx, &[(42_505_594, p2wpkh)])).unwrap_err().kind(), ErrorKind::InvalidData);
+        Ok(())
+    }
+
+    #[test]
+    fn verify_p2sh() -> Result<(), Box<dyn std::error::Error>> {
+        // Programming Bitcoin, chapter 8: 2-of-2 multisig redeem script
+        let tx = Tx::parse(&mut Cursor::new(hex::decode("0100000001868278ed6ddfb6c1ed3ad5f8181eb0c7a385aa0836f01d5e4789e6bd304d87221a000000db00483045022100dc92655fe37036f47756db8102e0d7d5e28b3beb83a8fef4f5dc0559bddfb94e02205a36d4e4e6c7fcd16658c50783e00c341609977aed3ad00937bf4ee942a8993701483045022100da6bee3c93766232079a01639d07fa869598749729ae323eab8eef53577d611b02207bef15429dcadce2121ea07f233115c6f09034c0be68db99980b9a6c5e75402201475221022626e955ea6ea6d98850c994f9107b036b1334f18ca8830bfff1295d21cfdb702103b287eaf122eea69030a0e9feed096bed8045c8b98bec453e1ffac7fbdbd4bb7152aeffffffff04d3b11400000000001976a914904a49878c0adfc3aa05de7afad2cc15f483a56a88ac7f400900000000001976a914418327e3f3dda4cf5b9089325a4b95abdfa0334088ac722c0c00000000001976a914ba35042cfe9fc66fd35ac2224eebdafd1028ad2788acdc4ace020000000017a91474d691da1574e6b3c192ecfb52cc8984ee7b6c568700000000")?))?;
+        let spk = "a91474d691da1574e6b3c192ecfb52cc8984ee7b6c5687";
+        tx.verify_input(0, &mut prevouts_for(&tx, &[(0, spk)]))?;
+        Ok(())
+    }
+
+    #[test]
+    fn sign_input_p2pkh() -> Result<(), Box<dyn std::error::Error>> {
+        // Programming Bitcoin, chapter 7: secret 8675309 spends to its own change address
+        let prev_tx: [u8; 32] = hex::decode("99a24308080ab26e6fb65c4eccfadf76749bb5bfa8cb08f291320b3c21e56f0d")?.try_into().unwrap();
+        let tx_outs = vec![
+            TxOut::new(33_000_000, hex::decode("76a914d52ad7ca9b3d096a38e752c2018e6fbc40cdf26f88ac")?),
+            TxOut::new(10_000_000, hex::decode("76a914507b27411ccf7f16f10297de6cef3f291623eddf88ac")?),
+        ];
+        let mut tx = Tx::new(1, vec![TxIn::new(prev_tx, 13, vec![], 0xffffffff)], tx_outs, 0, Network::TESTNET);
+        let mut prevouts = prevouts_for(&tx, &[(44_000_000, "76a914d52ad7ca9b3d096a38e752c2018e6fbc40cdf26f88ac")]);
+        let unsigned_id = tx.id.clone();
+
+        // Wrong key and wrong index fail before signing
+        let other_key = PrivateKey::new(U256::from_u64(1));
+        assert_eq!(tx.sign_input(0, &other_key, SighashType::All, &mut prevouts).unwrap_err().kind(), ErrorKind::InvalidInput);
+        let key = PrivateKey::new(U256::from_u64(8675309));
+        assert_eq!(tx.sign_input(1, &key, SighashType::All, &mut prevouts).unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(tx.sign_input(0, &key, SighashType::Default, &mut prevouts).unwrap_err().kind(), ErrorKind::InvalidInput);
+
+        tx.sign_input(0, &key, SighashType::All, &mut prevouts)?;
+        assert_eq!(hex::encode(tx.serialize()), "010000000199a24308080ab26e6fb65c4eccfadf76749bb5bfa8cb08f291320b3c21e56f0d0d0000006b4830450221008ed46aa2cf12d6d81065bfabe903670165b538f65ee9a3385e6327d80c66d3b502203124f804410527497329ec4715e18558082d489b218677bd029e7fa306a72236012103935581e52c354cd2f484fe8ed83af7a3097005b2f9c60bff71d35bd795f54b67ffffffff02408af701000000001976a914d52ad7ca9b3d096a38e752c2018e6fbc40cdf26f88ac80969800000000001976a914507b27411ccf7f16f10297de6cef3f291623eddf88ac00000000");
+        assert_ne!(tx.id, unsigned_id);
+        Ok(())
+    }
+
+    #[test]
+    fn segwit_v0_sig_hash_bip143() -> Result<(), Box<dyn std::error::Error>> {
+        // BIP143 native P2WPKH example, second input
+        let tx = Tx::parse(&mut Cursor::new(hex::decode("0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000")?))?;
+        let hashes = tx.segwit_v0_hashes();
+        assert_eq!(hex::encode(hashes.prevouts), "96b827c8483d4e9b96712b6713a7b68d6e8003a781feba36c31143470b4efd37");
+        assert_eq!(hex::encode(hashes.sequences), "52b0a642eea2fb7ae638c36f6252b6750293dbe574a806984b8e4d8548339a3b");
+        assert_eq!(hex::encode(hashes.outputs), "863ef3e1a92afbfdb97f31ad0fc7683ee943e9abcf2501590ff8f6551f47e5e5");
+        let script_code = hex::decode("76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac")?;
+        assert_eq!(tx.segwit_v0_sig_hash(1, &script_code, 600_000_000, SighashType::All, &hashes)?, U256::from_be_hex("c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670"));
+        assert_eq!(tx.segwit_v0_sig_hash(1, &script_code, 600_000_000, SighashType::Default, &hashes).unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(tx.segwit_v0_sig_hash(2, &script_code, 600_000_000, SighashType::All, &hashes).unwrap_err().kind(), ErrorKind::InvalidInput);
+
+        // BIP143 P2SH-P2WSH example: 6-of-6 multisig witness script
+        let tx = Tx::parse(&mut Cursor::new(hex::decode("010000000136641869ca081e70f394c6948e8af409e18b619df2ed74aa106c1ca29787b96e0100000000ffffffff0200e9a435000000001976a914389ffce9cd9ae88dcc0631e88a821ffdbe9bfe2688acc0832f05000000001976a9147480a33f950689af511e6e84c138dbbd3c3ee41588ac00000000")?))?;
+        let witness_script = hex::decode("56210307b8ae49ac90a048e9b53357a2354b3334e9c8bee813ecb98e99a7e07e8c3ba32103b28f0c28bfab54554ae8c658ac5c3e0ce6e79ad336331f78c428dd43eea8449b21034b8113d703413d57761b8b9781957b8c0ac1dfe69f492580ca4195f50376ba4a21033400f6afecb833092a9a21cfdf1ed1376e58c5d1f47de74683123987e967a8f42103a6d48b1131e94ba04d9737d61acdaa1322008af9602b3b14862c07a1789aac162102d8b661b0b3302ee2f162b09e07a55ad5dfbe673a9f01d9f0c19617681024306b56ae")?;
+        let hashes = tx.segwit_v0_hashes();
+        for (sighash_type, expected) in [
+            (SighashType::All, "185c0be5263dce5b4bb50a047973c1b6272bfbd0103a89444597dc40b248ee7c"),
+            (SighashType::None, "e9733bc60ea13c95c6527066bb975a2ff29a925e80aa14c213f686cbae5d2f36"),
+            (SighashType::Single, "1e1f1c303dc025bd664acb72e583e933fae4cff9148bf78c157d1e8f78530aea"),
+            (SighashType::AllAnyoneCanPay, "2a67f03e63a6a422125878b40b82da593be8d4efaafe88ee528af6e5a9955c6e"),
+            (SighashType::NoneAnyoneCanPay, "781ba15f3779d5542ce8ecb5c18716733a5ee42a6f51488ec96154934e2c890a"),
+            (SighashType::SingleAnyoneCanPay, "511e8e52ed574121fc1b654970395502128263f62662e076dc6baf05c2e6a99b"),
+        ] {
+            assert_eq!(tx.segwit_v0_sig_hash(0, &witness_script, 987_654_321, sighash_type, &hashes)?, U256::from_be_hex(expected));
+        }
+
+        // rust-bitcoin's flag vectors (hashes reversed for display), SINGLE with its output
+        let tx = Tx::parse(&mut Cursor::new(hex::decode("0200000001cf309ee0839b8aaa3fbc84f8bd32e9c6357e99b49bf6a3af90308c68e762f1d70100000000feffffff0288528c61000000001600146e8d9e07c543a309dcdeba8b50a14a991a658c5be0aebb0000000000160014698d8419804a5d5994704d47947889ff7620c004db000000")?))?;
+        let script_code = hex::decode("76a91462744660c6b5133ddeaacbc57d2dc2d7b14d0b0688ac")?;
+        let hashes = tx.segwit_v0_hashes();
+        for (sighash_type, expected) in [
+            (SighashType::All, "0a1bc2758dbb5b3a56646f8cafbf63f410cc62b77a482f8b87552683300a7711"),
+            (SighashType::None, "3e275ac8b084f79f756dcd535bffb615cc94a685eefa244d9031eaf22e4cec12"),
+            (SighashType::Single, "191a08165ffacc3ea55753b225f323c35fd00d9cc0268081a4a501921fc6ec14"),
+            (SighashType::AllAnyoneCanPay, "4b6b612530f94470bbbdef18f57f2990d56b239f41b8728b9a49dc8121de4559"),
+            (SighashType::NoneAnyoneCanPay, "a7e916d3acd4bb97a21e6793828279aeab02162adf8099ea4f309af81f3d5adb"),
+            (SighashType::SingleAnyoneCanPay, "d9276e2a48648ddb53a4aaa58314fc2b8067c13013e1913ffb67e0988ce82c78"),
+        ] {
+            let mut expected = hex::decode(expected)?;
+            expected.reverse();
+            assert_eq!(tx.segwit_v0_sig_hash(0, &script_code, 1_648_888_940, sighash_type, &hashes)?, U256::from_be_slice(&expected));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn segwit_v0_sig_hash_code_separator() -> Result<(), Box<dyn std::error::Error>> {
+        // BIP143 second native P2WSH example: <pubkey> CHECKSIG behind IF CODESEPARATOR ENDIF
+        let tx = Tx::parse(&mut Cursor::new(hex::decode("0100000002e9b542c5176808107ff1df906f46bb1f2583b16112b95ee5380665ba7fcfc0010000000000ffffffff80e68831516392fcd100d186b3c2c7b95c80b29b6b0b0b67b9c4c9a5e4b5bf2e0000000000ffffffff0280969800000000001976a914de4b231626ef508c9a74a8517e6783c0546d6b2888ac80969800000000001976a9146648a8cd4531e1ec47f35916de8e259237294d1e88ac00000000")?))?;
+        let hashes = tx.segwit_v0_hashes();
+        let skipped = hex::decode("0063ab68210392972e2eb617b2388771abe27235fd5ac44af8e61693261550447a4c3e39da98ac")?;
+        let executed = hex::decode("5163ab68210392972e2eb617b2388771abe27235fd5ac44af8e61693261550447a4c3e39da98ac")?;
+        assert_eq!(hex::encode(Sha256::digest(&skipped)), "ba468eea561b26301e4cf69fa34bde4ad60c81e70f059f045ca9a79931004a4d");
+        assert_eq!(hex::encode(Sha256::digest(&executed)), "d9bbfbe56af7c4b7f960a70d7ea107156913d9e5a26b0a71429df5e097ca6537");
+
+        // OP_0 skips the CODESEPARATOR, so the whole script is signed
+        let z = tx.segwit_v0_sig_hash(0, &skipped, 16_777_215, SighashType::SingleAnyoneCanPay, &hashes)?;
+        assert_eq!(z, U256::from_be_hex("e9071e75e25b8a1e298a72f0d2e9f4f95a0f5cdf86a533cda597eb402ed13b3a"));
+        // OP_1 executes it, so only what follows is signed
+        let z = tx.segwit_v0_sig_hash(1, &executed[3..], 16_777_215, SighashType::SingleAnyoneCanPay, &hashes)?;
+        assert_eq!(z, U256::from_be_hex("2cb6525715bceadf890630000acfd54652805e1a5ddc7e08e51972ce551e0aa1"));
+        assert_ne!(z, tx.segwit_v0_sig_hash(1, &executed, 16_777_215, SighashType::SingleAnyoneCanPay, &hashes)?);
+        Ok(())
+    }
+
+    #[test]
+    fn verify_p2sh_p2wsh_bip143() -> Result<(), Box<dyn std::error::Error>> {
+        // BIP143 P2SH-P2WSH example: six keys, each signing with a different sighash type
+        let mut tx = Tx::parse(&mut Cursor::new(hex::decode("010000000136641869ca081e70f394c6948e8af409e18b619df2ed74aa106c1ca29787b96e0100000000ffffffff0200e9a435000000001976a914389ffce9cd9ae88dcc0631e88a821ffdbe9bfe2688acc0832f05000000001976a9147480a33f950689af511e6e84c138dbbd3c3ee41588ac00000000")?))?;
+        let witness_script = hex::decode("56210307b8ae49ac90a048e9b53357a2354b3334e9c8bee813ecb98e99a7e07e8c3ba32103b28f0c28bfab54554ae8c658ac5c3e0ce6e79ad336331f78c428dd43eea8449b21034b8113d703413d57761b8b9781957b8c0ac1dfe69f492580ca4195f50376ba4a21033400f6afecb833092a9a21cfdf1ed1376e58c5d1f47de74683123987e967a8f42103a6d48b1131e94ba04d9737d61acdaa1322008af9602b3b14862c07a1789aac162102d8b661b0b3302ee2f162b09e07a55ad5dfbe673a9f01d9f0c19617681024306b56ae")?;
+        let mut prevouts = prevouts_for(&tx, &[(987_654_321, "a9149993a429037b5d912407a71c252019287b8d27a587")]);
+        let hashes = tx.segwit_v0_hashes();
+
+        let mut witness = vec![Vec::new()];
+        for (secret, sighash_type, der) in [
+            ("730fff80e1413068a05b57d6a58261f07551163369787f349438ea38ca80fac6", SighashType::All, "304402206ac44d672dac41f9b00e28f4df20c52eeb087207e8d758d76d92c6fab3b73e2b0220367750dbbe19290069cba53d096f44530e4f98acaa594810388cf7409a1870ce"),
+            ("11fa3d25a17cbc22b29c44a484ba552b5a53149d106d3d853e22fdd05a2d8bb3", SighashType::None, "3044022068c7946a43232757cbdf9176f009a928e1cd9a1a8c212f15c1e11ac9f2925d9002205b75f937ff2f9f3c1246e547e54f62e027f64eefa2695578cc6432cdabce2715"),
+            ("77bf4141a87d55bdd7f3cd0bdccf6e9e642935fec45f2f30047be7b799120661", SighashType::Single, "3044022059ebf56d98010a932cf8ecfec54c48e6139ed6adb0728c09cbe1e4fa0915302e022007cd986c8fa870ff5d2b3a89139c9fe7e499259875357e20fcbb15571c767954"),
+            ("14af36970f5025ea3e8b5542c0f8ebe7763e674838d08808896b63c3351ffe49", SighashType::AllAnyoneCanPay, "3045022100fbefd94bd0a488d50b79102b5dad4ab6ced30c4069f1eaa69a4b5a763414067e02203156c6a5c9cf88f91265f5a942e96213afae16d83321c8b31bb342142a14d163"),
+            ("fe9a95c19eef81dde2b95c1284ef39be497d128e2aa46916fb02d552485e0323", SighashType::NoneAnyoneCanPay, "3045022100a5263ea0553ba89221984bd7f0b13613db16e7a70c549a86de0cc0444141a407022005c360ef0ae5a5d4f9f2f87a56c1546cc8268cab08c73501d6b3be2e1e1a8a08"),
+            ("428a7aee9f0c2af0cd19af3cf1c78149951ea528726989b2e83e4778d2c3f890", SighashType::SingleAnyoneCanPay, "30440220525406a1482936d5a21888260dc165497a90a15669636d8edca6b9fe490d309c022032af0c646a34a44d1f4576bf6a4a74b67940f8faa84c7df9abe12a01a11e2b47"),
+        ] {
+            // RFC 6979 reproduces the published signature, which checks against the key
+            let key = PrivateKey::new(U256::from_be_hex(secret));
+            let z = tx.segwit_v0_sig_hash(0, &witness_script, 987_654_321, sighash_type, &hashes)?;
+            assert_eq!(hex::encode(key.sign(z).der()), der);
+            assert!(key.point.verify(S256Field::new(z), Signature::parse(&hex::decode(der)?)?));
+            let mut sig = hex::decode(der)?;
+            sig.push(sighash_type.to_byte());
+            witness.push(sig);
+        }
+        witness.push(witness_script.clone());
+        let mut program = vec![0x00, 0x20];
+        program.extend_from_slice(&Sha256::digest(&witness_script));
+        tx.tx_ins[0].script_sig = push_data(&program);
+        tx.tx_ins[0].witness = witness;
+        tx.verify_input(0, &mut prevouts)?;
+
+        // The sighash byte decides what a signature commits to
+        tx.tx_ins[0].witness[1].pop();
+        tx.tx_ins[0].witness[1].push(SighashType::None.to_byte());
+        assert!(tx.verify_input(0, &mut prevouts).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn sign_p2wpkh_native() -> Result<(), Box<dyn std::error::Error>> {
+        // BIP143 native P2WPKH example: the second input, signed with RFC 6979 as in the BIP
+        let mut tx = Tx::parse(&mut Cursor::new(hex::decode("0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000")?))?;
+        let mut prevouts = prevouts_for(&tx, &[(625_000_000, "2103c9f4836b9a4f77fc0d81f7bcb01b7f1b35916864b9476c241ce9fc198bd25432ac"), (600_000_000, "00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1")]);
+        let txid = tx.id.clone();
+        let key = PrivateKey::new(U256::from_be_hex("619c335025c7f4012e556c2a58b2506e30b8511b53ade95ea316fd8c3286feb9"));
+
+        // Not this key's program
+        assert_eq!(tx.sign_p2wpkh(0, &key, SighashType::All, &mut prevouts).unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(tx.sign_p2wpkh(1, &key, SighashType::Default, &mut prevouts).unwrap_err().kind(), ErrorKind::InvalidInput);
+
+        tx.sign_p2wpkh(1, &key, SighashType::All, &mut prevouts)?;
+        assert!(tx.tx_ins[1].script_sig.is_empty());
+        assert_eq!(hex::encode(&tx.tx_ins[1].witness[0]), "304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee01");
+        assert_eq!(hex::encode(&tx.tx_ins[1].witness[1]), "025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee6357");
+        assert_eq!(tx.id, txid);
+        assert!(tx.has_witness() && tx.wtxid() != txid);
+        Ok(())
+    }
+
+    #[test]
+    fn sign_p2sh_p2wpkh() -> Result<(), Box<dyn std::error::Error>> {
+        // BIP143 P2SH-P2WPKH example
+        let mut tx = Tx::parse(&mut Cursor::new(hex::decode("0100000001db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a54770100000000feffffff02b8b4eb0b000000001976a914a457b684d7f0d539a46a45bbc043f35b59d0d96388ac0008af2f000000001976a914fd270b1ee6abcaea97fea7ad0402e8bd8ad6d77c88ac92040000")?))?;
+        let mut prevouts = prevouts_for(&tx, &[(1_000_000_000, "a9144733f37cf4db86fbc2efed2500b4f4e49f31202387")]);
+        let key = PrivateKey::new(U256::from_be_hex("eb696a065ef48a2192da5b28b694f87544b30fae8327c4510137a922f32c6dcf"));
+
+        tx.sign_p2wpkh(0, &key, SighashType::All, &mut prevouts)?;
+        assert_eq!(hex::encode(&tx.tx_ins[0].script_sig), "16001479091972186c449eb1ded22b78e40d009bdf0089");
+        assert_eq!(hex::encode(&tx.tx_ins[0].witness[0]), "3044022047ac8e878352d3ebbde1c94ce3a10d057c24175747116f8288e5d794d12d482f0220217f36a485cae903c713331d877c1f64677e3622ad4010726870540656fe9dcb01");
+
+        // The witness is checked, not just present
+        tx.tx_ins[0].witness[0][10] ^= 1;
+        assert!(tx.verify_input(0, &mut prevouts).is_err());
+        tx.tx_ins[0].witness.pop();
+        assert_eq!(tx.verify_input(0, &mut prevouts).unwrap_err().to_string(), "P2WPKH witness must be a signature and a public key");
+        Ok(())
+    }
+
+    #[test]
+    fn sign_p2wsh_multisig() -> Result<(), Box<dyn std::error::Error>> {
+        // 1-of-1 CHECKMULTISIG behind P2SH-P2WSH, so the dummy element is needed
+        let key = PrivateKey::new(U256::from_u64(8675309));
+        let mut witness_script = vec![0x51];
+        witness_script.extend_from_slice(&push_data(&key.point.sec(true)));
+        witness_script.extend_from_slice(&[0x51, 0xae]);
+        let mut program = vec![0x00, 0x20];
+        program.extend_from_slice(&Sha256::digest(&witness_script));
+        let spk = hex::encode(p2sh_script(&hash160(&program)));
+
+        let tx_ins = vec![TxIn::new([7u8; 32], 0, vec![], 0xffffffff)];
+        let mut tx = Tx::new(2, tx_ins, vec![TxOut::new(90_000, hex::decode("0014d52ad7ca9b3d096a38e752c2018e6fbc40cdf26f")?)], 0, Network::MAINNET);
+        let mut prevouts = prevouts_for(&tx, &[(100_000, &spk)]);
+        assert!(tx.sign_p2wsh(0, &[&key], &witness_script[1..], SighashType::All, &mut prevouts).is_err());
+        assert_eq!(tx.sign_p2wsh(0, &[&key], &witness_script, SighashType::Default, &mut prevouts).unwrap_err().kind(), ErrorKind::InvalidInput);
+
+        tx.sign_p2wsh(0, &[&key], &witness_script, SighashType::All, &mut prevouts)?;
+        assert_eq!(tx.tx_ins[0].script_sig, push_data(&program));
+        assert_eq!(tx.tx_ins[0].witness.len(), 3);
+        assert!(tx.tx_ins[0].witness[0].is_empty());
+        let z = tx.segwit_v0_sig_hash(0, &witness_script, 100_000, SighashType::All, &tx.segwit_v0_hashes())?;
+        let (sighash_byte, der) = tx.tx_ins[0].witness[1].split_last().unwrap();
+        assert_eq!(*sighash_byte, SighashType::All.to_byte());
+        assert_eq!(der, key.sign(z).der());
+        assert!(key.point.verify(S256Field::new(z), Signature::parse(der)?));
+
+        // Parsing the BIP144 encoding gives back the same spend
+        let parsed = Tx::parse(&mut Cursor::new(tx.serialize()))?;
+        assert_eq!(parsed.tx_ins[0].witness, tx.tx_ins[0].witness);
+        assert_eq!(parsed.id, tx.id);
+
+        // A trailing 0xae inside a push isn't OP_CHECKMULTISIG, so no dummy element
+        let mut witness_script = push_data(&key.point.sec(true));
+        witness_script.extend_from_slice(&[0xad, 0x01, 0xae]);
+        let mut spk = vec![0x00, 0x20];
+        spk.extend_from_slice(&Sha256::digest(&witness_script));
+        let mut prevouts = prevouts_for(&tx, &[(100_000, &hex::encode(spk))]);
+        tx.sign_p2wsh(0, &[&key], &witness_script, SighashType::All, &mut prevouts)?;
+        assert!(tx.tx_ins[0].script_sig.is_empty());
+        assert_eq!(tx.tx_ins[0].witness.len(), 2);
+
+        // BIP143's 6-of-6 P2SH-P2WSH spend, all signing ALL: the first key gives the published signature
+        let mut tx = Tx::parse(&mut Cursor::new(hex::decode("010000000136641869ca081e70f394c6948e8af409e18b619df2ed74aa106c1ca29787b96e0100000000ffffffff0200e9a435000000001976a914389ffce9cd9ae88dcc0631e88a821ffdbe9bfe2688acc0832f05000000001976a9147480a33f950689af511e6e84c138dbbd3c3ee41588ac00000000")?))?;
+        let witness_script = hex::decode("56210307b8ae49ac90a048e9b53357a2354b3334e9c8bee813ecb98e99a7e07e8c3ba32103b28f0c28bfab54554ae8c658ac5c3e0ce6e79ad336331f78c428dd43eea8449b21034b8113d703413d57761b8b9781957b8c0ac1dfe69f492580ca4195f50376ba4a21033400f6afecb833092a9a21cfdf1ed1376e58c5d1f47de74683123987e967a8f42103a6d48b1131e94ba04d9737d61acdaa1322008af9602b3b14862c07a1789aac162102d8b661b0b3302ee2f162b09e07a55ad5dfbe673a9f01d9f0c19617681024306b56ae")?;
+        let mut prevouts = prevouts_for(&tx, &[(987_654_321, "a9149993a429037b5d912407a71c252019287b8d27a587")]);
+        let keys: Vec<PrivateKey> = [
+            "730fff80e1413068a05b57d6a58261f07551163369787f349438ea38ca80fac6",
+            "11fa3d25a17cbc22b29c44a484ba552b5a53149d106d3d853e22fdd05a2d8bb3",
+            "77bf4141a87d55bdd7f3cd0bdccf6e9e642935fec45f2f30047be7b799120661",
+            "14af36970f5025ea3e8b5542c0f8ebe7763e674838d08808896b63c3351ffe49",
+            "fe9a95c19eef81dde2b95c1284ef39be497d128e2aa46916fb02d552485e0323",
+            "428a7aee9f0c2af0cd19af3cf1c78149951ea528726989b2e83e4778d2c3f890",
+        ].iter().map(|secret| PrivateKey::new(U256::from_be_hex(secret))).collect();
+        tx.sign_p2wsh(0, &keys.iter().collect::<Vec<_>>(), &witness_script, SighashType::All, &mut prevouts)?;
+        assert_eq!(tx.tx_ins[0].witness.len(), 8);
+        assert_eq!(hex::encode(&tx.tx_ins[0].witness[1]), "304402206ac44d672dac41f9b00e28f4df20c52eeb087207e8d758d76d92c6fab3b73e2b0220367750dbbe19290069cba53d096f44530e4f98acaa594810388cf7409a1870ce01");
+        // Signatures out of key order fail CHECKMULTISIG
+        tx.tx_ins[0].witness.swap(1, 2);
+        assert!(tx.verify_input(0, &mut prevouts).is_err());
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -1,9 +1,12 @@
-use std::io::{self, Read};
+use std::io::{self, Error, ErrorKind, Read};
 use crypto_bigint::U256;
 
 use crate::{algorithms::endian::int_to_little_endian, bitcoin::{tx::tx::{Network, Tx}, tx_fetcher::TxFetcher, utils::{read_bytes, read_u32_le}, varint::{encode_varint, read_varint}}};
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+// A witness can't outgrow a block's weight limit
+const MAX_WITNESS_SIZE: u64 = 4_000_000;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct OutPoint {
     pub tx_id: [u8; 32], // little-endian as found on the wire
     pub vout: u32,
@@ -13,11 +16,13 @@ pub struct TxIn {
     pub previous_output: OutPoint,
     pub script_sig: Vec<u8>,
     pub sequence: u32,
+    // BIP141 witness stack, empty for legacy inputs. Carried after the outputs, not in serialize().
+    pub witness: Vec<Vec<u8>>,
 }
 
 impl TxIn {
     pub fn new(tx_id: [u8; 32], vout: u32, script_sig: Vec<u8>, sequence: u32) -> Self {
-        Self { previous_output: OutPoint { tx_id, vout }, script_sig, sequence }
+        Self { previous_output: OutPoint { tx_id, vout }, script_sig, sequence, witness: Vec::new() }
     }
 
     pub fn parse<R: Read>(stream: &mut R) -> io::Result<TxIn> {
@@ -56,6 +61,31 @@ impl TxIn {
         result
     }
 
+    pub fn parse_witness<R: Read>(&mut self, stream: &mut R) -> io::Result<()> {
+        let items = read_varint(stream)?;
+        if items > MAX_WITNESS_SIZE {
+            return Err(Error::new(ErrorKind::InvalidData, "Too many witness items"));
+        }
+        self.witness = Vec::new();
+        for _i in 0..items {
+            let len = read_varint(stream)?;
+            if len > MAX_WITNESS_SIZE {
+                return Err(Error::new(ErrorKind::InvalidData, "Witness item too large"));
+            }
+            self.witness.push(read_bytes(stream, len as usize)?);
+        }
+        Ok(())
+    }
+
+    pub fn serialize_witness(&self) -> Vec<u8> {
+        let mut result = encode_varint(U256::from_u64(self.witness.len() as u64));
+        for item in &self.witness {
+            result.extend_from_slice(&encode_varint(U256::from_u64(item.len() as u64)));
+            result.extend_from_slice(item);
+        }
+        result
+    }
+
     pub fn fetch_tx<'a>(
         &self, 
         fetcher: &'a mut TxFetcher, 
@@ -3,6 +3,7 @@ use crypto_bigint::U256;
 
 use crate::{algorithms::endian::int_to_little_endian, bitcoin::{utils::{read_bytes, read_u64_le}, varint::{encode_varint, read_varint}}};
 
+#[derive(Debug, Clone)]
 pub struct TxOut {
     pub amount: u64,
     pub script_pubkey: Vec<u8>,
@@ -1,7 +1,12 @@
 use std::collections::HashMap;
-use std::io::Cursor;
+use std::io::{self, Cursor, Error, ErrorKind};
 use reqwest::blocking::get;
-use crate::bitcoin::tx::tx::{Network, Tx};
+use crate::bitcoin::tx::{tx::{Network, Tx}, tx_in::OutPoint, tx_out::TxOut};
+
+// Where verification looks up the outputs a transaction spends
+pub trait PrevoutSource {
+    fn prev_output(&mut self, outpoint: &OutPoint, network: Network) -> io::Result<TxOut>;
+}
 
 pub struct TxFetcher {
     cache: HashMap<String, Tx>,
@@ -38,6 +43,22 @@ impl TxFetcher {
     }
 }
 
+impl PrevoutSource for TxFetcher {
+    fn prev_output(&mut self, outpoint: &OutPoint, network: Network) -> io::Result<TxOut> {
+        let mut tx_id = outpoint.tx_id;
+        tx_id.reverse();
+        let tx = self.fetch(&hex::encode(tx_id), network).map_err(|e| Error::other(e.to_string()))?;
+        tx.tx_outs.get(outpoint.vout as usize).cloned().ok_or_else(|| Error::new(ErrorKind::NotFound, "Previous output index out of range"))
+    }
+}
+
+// Outputs known up front, e.g. for transactions built locally
+impl PrevoutSource for HashMap<OutPoint, TxOut> {
+    fn prev_output(&mut self, outpoint: &OutPoint, _network: Network) -> io::Result<TxOut> {
+        self.get(outpoint).cloned().ok_or_else(|| Error::new(ErrorKind::NotFound, "Unknown previous output"))
+    }
+}
+
 #[cfg(test)]
 mod tx_fetcher_tests {
     use crate::bitcoin::tx_fetcher::TxFetcher;
@@ -1,7 +1,10 @@
 pub mod primitives;
 pub mod s256;
+pub mod p256;
 pub mod algorithms;
 pub mod bitcoin;
+pub mod attacks;
+pub mod wallet;
 
 #[macro_export]
 macro_rules! fe32 {
@@ -28,4 +31,23 @@ macro_rules! fe {
     ($n:expr, $p:expr) => {
         $crate::primitives::field_element::FieldElement::new($n, $p)
     };
-}
\ No newline at end of file
+}
+
+#[macro_export]
+macro_rules! curve {
+    ($name:ident, $field:ty, $a:expr, $b:expr) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct $name;
+        impl $crate::primitives::point::Curve for $name {
+            type Field = $field;
+
+            fn a() -> $field {
+                $a
+            }
+
+            fn b() -> $field {
+                $b
+            }
+        }
+    };
+}
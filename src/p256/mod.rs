@@ -0,0 +1,4 @@
+pub mod p256_field;
+pub mod p256_point;
+pub mod scalar;
+pub mod signature;
@@ -0,0 +1,13 @@
+use crypto_bigint::U256;
+use crate::primitives::field_element::{FieldElement, Modulus};
+
+#[derive(Debug)]
+pub struct P256Prime;
+impl Modulus for P256Prime {
+    fn modulus() -> U256 {
+        U256::from_be_hex(
+            "FFFFFFFF00000001000000000000000000000000FFFFFFFFFFFFFFFFFFFFFFFF"
+        )
+    }
+}
+pub type P256Field = FieldElement<P256Prime>;
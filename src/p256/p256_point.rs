@@ -0,0 +1,104 @@
+use crypto_bigint::U256;
+use crate::{p256::{p256_field::{P256Field, P256Prime}, scalar::P256Order, signature::Signature}, primitives::{ecdsa::EcdsaCurve, field_element::Modulus, point::{Curve, Point}}};
+
+// secp256r1 / NIST P-256: y^2 = x^3 - 3x + b
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Secp256r1;
+impl Curve for Secp256r1 {
+    type Field = P256Field;
+
+    fn a() -> P256Field {
+        P256Field::new(P256Prime::modulus() - U256::from(3u8))
+    }
+
+    fn b() -> P256Field {
+        P256Field::new(U256::from_be_hex("5ac635d8aa3a93e7b3ebbd55769886bc651d06b0cc53b0f63bce3c3e27d2604b"))
+    }
+}
+
+impl EcdsaCurve for Secp256r1 {
+    type Order = P256Order;
+
+    fn g() -> P256Point {
+        P256Point::new_concrete(
+            P256Field::new(U256::from_be_hex("6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296")),
+            P256Field::new(U256::from_be_hex("4fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5"))
+        )
+    }
+}
+
+pub type P256Point = Point<Secp256r1>;
+
+impl Point<Secp256r1> {
+    pub fn rmul(self, coefficeint: U256) -> Self {
+        self.smul(coefficeint % Self::n())
+    }
+
+    pub fn g() -> Self {
+        Secp256r1::g()
+    }
+
+    pub fn n() -> U256 {
+        Secp256r1::n()
+    }
+
+    // z is a plain integer because a SHA-256 digest can exceed p here
+    pub fn verify(self, z: U256, sig: Signature) -> bool {
+        self.verify_ecdsa(z, sig.r.num, sig.s.num)
+    }
+}
+
+#[cfg(test)]
+mod p256_point_tests {
+    use sha2::{Digest, Sha256};
+    use super::*;
+
+    fn public_key() -> P256Point {
+        // RFC 6979 A.2.5 key, x = c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721
+        P256Point::parse(&hex::decode("0460fed4ba255a9d31c961eb74c6356d68c049b8923b61fa6ce669622e60f29fb67903fe1008b8bc99a41ae9e95628bc64f2f1b20c2d7e9f5177a3c294d4462299").unwrap()).unwrap()
+    }
+
+    #[test]
+    fn g_order() {
+        assert!(P256Point::g().smul(P256Point::n()).is_inf());
+    }
+
+    #[test]
+    fn public_key_from_secret() {
+        let secret = U256::from_be_hex("c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721");
+        assert_eq!(P256Point::g().rmul(secret), public_key());
+    }
+
+    #[test]
+    fn sec() {
+        let point = public_key();
+        let compressed = point.sec(true);
+        assert_eq!(hex::encode(&compressed), "0360fed4ba255a9d31c961eb74c6356d68c049b8923b61fa6ce669622e60f29fb6");
+        assert_eq!(P256Point::parse(&compressed).unwrap(), point);
+    }
+
+    #[test]
+    fn verify_rfc6979_sample() {
+        let z = U256::from_be_slice(&Sha256::digest(b"sample"));
+        let sig = Signature {
+            r: P256Field::new(U256::from_be_hex("efd48b2aacb6a8fd1140dd9cd45e81d69d2c877b56aaf991c34d0ea84eaf3716")),
+            s: P256Field::new(U256::from_be_hex("f7cb1c942d657c41d436c7a1b6e29f65f3e900dbb9aff4064dc4ab2f843acda8")),
+        };
+        assert!(public_key().verify(z, sig));
+
+        let wrong_z = U256::from_be_slice(&Sha256::digest(b"test"));
+        assert!(!public_key().verify(wrong_z, sig));
+    }
+
+    #[test]
+    fn verify_der() -> Result<(), Box<dyn std::error::Error>> {
+        // Produced by OpenSSL with a random nonce
+        let der = hex::decode("304402207e8d5343601bee8a8513f197fd48f10a729948702e3c9aa939cb178b5f9ca9f702207f0dafab39e11519f83db7c5ad0c964e3535e8a2c4b12f5aa581885c736816ef")?;
+        let sig = Signature::parse(&der)?;
+        assert_eq!(sig.der(), der);
+
+        let z = U256::from_be_slice(&Sha256::digest(b"sample"));
+        assert!(public_key().verify(z, sig));
+        Ok(())
+    }
+}
@@ -0,0 +1,13 @@
+use crypto_bigint::U256;
+use crate::primitives::field_element::{FieldElement, Modulus};
+
+#[derive(Debug)]
+pub struct P256Order;
+impl Modulus for P256Order {
+    fn modulus() -> U256 {
+        U256::from_be_hex(
+            "FFFFFFFF00000000FFFFFFFFFFFFFFFFBCE6FAADA7179E84F3B9CAC2FC632551"
+        )
+    }
+}
+pub type P256Scalar = FieldElement<P256Order>;
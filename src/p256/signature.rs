@@ -0,0 +1,26 @@
+use std::io;
+
+use crate::{algorithms::der::{encode_signature, parse_signature}, p256::p256_field::{P256Field, P256Prime}, primitives::field_element::Modulus};
+
+// Same shape as s256::signature::Signature; r and s are below n, which is below p
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub struct Signature {
+    pub r: P256Field,
+    pub s: P256Field,
+}
+
+impl Signature {
+
+    pub fn der(&self) -> Vec<u8> {
+        encode_signature(self.r.num, self.s.num)
+    }
+
+    pub fn parse(der: &[u8]) -> io::Result<Signature> {
+        let (r, s) = parse_signature(der)?;
+        let p = P256Prime::modulus();
+        if r >= p || s >= p {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Signature value out of range"));
+        }
+        Ok(Signature { r: P256Field::new(r), s: P256Field::new(s) })
+    }
+}
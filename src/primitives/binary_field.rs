@@ -0,0 +1,157 @@
+use std::ops;
+use crypto_bigint::U256;
+use crate::primitives::field::Field;
+
+// GF(2^BITS) with elements as polynomials over GF(2) reduced by POLY (degree BITS).
+// Addition is xor, so every element is its own negative.
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub struct BinaryFieldElement<const BITS: u32, const POLY: u16> {
+    pub num: u8,
+}
+
+// SLIP-39: the AES field, x^8 + x^4 + x^3 + x + 1
+pub type GF256 = BinaryFieldElement<8, 0x11b>;
+
+// BIP93 codex32: x^5 + x^3 + 1, elements indexed by the bech32 alphabet
+pub type GF32 = BinaryFieldElement<5, 0x29>;
+
+impl<const BITS: u32, const POLY: u16> BinaryFieldElement<BITS, POLY> {
+    pub fn new(num: u8) -> Self {
+        assert!((num as u16) < 1 << BITS, "Num {} not in GF(2^{})", num, BITS);
+        Self { num }
+    }
+
+    pub fn zero() -> Self {
+        Self { num: 0 }
+    }
+
+    pub fn one() -> Self {
+        Self { num: 1 }
+    }
+
+    pub fn pow(self, mut exp: u32) -> Self {
+        let mut result = Self::one();
+        let mut base = self;
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            exp >>= 1;
+        }
+        result
+    }
+
+    // The multiplicative group has order 2^BITS - 1
+    pub fn inv(self) -> Self {
+        assert!(self.num != 0, "Cannot invert zero");
+        self.pow((1 << BITS) - 2)
+    }
+}
+
+impl<const BITS: u32, const POLY: u16> ops::Add for BinaryFieldElement<BITS, POLY> {
+    type Output = Self;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn add(self, other: Self) -> Self {
+        Self { num: self.num ^ other.num }
+    }
+}
+
+impl<const BITS: u32, const POLY: u16> ops::Sub for BinaryFieldElement<BITS, POLY> {
+    type Output = Self;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn sub(self, other: Self) -> Self {
+        Self { num: self.num ^ other.num }
+    }
+}
+
+impl<const BITS: u32, const POLY: u16> ops::Mul for BinaryFieldElement<BITS, POLY> {
+    type Output = Self;
+
+    // Carry-less multiply, then reduce from the top bit down
+    fn mul(self, other: Self) -> Self {
+        let mut product = 0u16;
+        for i in 0..BITS {
+            if (other.num >> i) & 1 == 1 {
+                product ^= (self.num as u16) << i;
+            }
+        }
+        for i in (BITS..2 * BITS - 1).rev() {
+            if (product >> i) & 1 == 1 {
+                product ^= POLY << (i - BITS);
+            }
+        }
+        Self { num: product as u8 }
+    }
+}
+
+impl<const BITS: u32, const POLY: u16> ops::Div for BinaryFieldElement<BITS, POLY> {
+    type Output = Self;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, other: Self) -> Self {
+        self * other.inv()
+    }
+}
+
+impl<const BITS: u32, const POLY: u16> ops::Neg for BinaryFieldElement<BITS, POLY> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        self
+    }
+}
+
+impl<const BITS: u32, const POLY: u16> Field for BinaryFieldElement<BITS, POLY> {
+    fn is_zero(&self) -> bool {
+        self.num == 0
+    }
+
+    fn inv(self) -> Self {
+        BinaryFieldElement::inv(self)
+    }
+
+    fn as_u256(&self) -> U256 {
+        U256::from(self.num)
+    }
+}
+
+#[cfg(test)]
+mod binary_field_tests {
+    use super::*;
+
+    #[test]
+    fn gf256() {
+        // FIPS-197 examples
+        assert_eq!(GF256::new(0x57) + GF256::new(0x83), GF256::new(0xd4));
+        assert_eq!(GF256::new(0x57) * GF256::new(0x83), GF256::new(0xc1));
+        assert_eq!(GF256::new(0x53).inv(), GF256::new(0xca));
+
+        for n in 1..=255 {
+            let a = GF256::new(n);
+            assert_eq!(a * a.inv(), GF256::one());
+            assert_eq!(a / a, GF256::one());
+        }
+    }
+
+    #[test]
+    fn gf32() {
+        // x^4 * x = x^5 = x^3 + 1
+        assert_eq!(GF32::new(16) * GF32::new(2), GF32::new(9));
+        for n in 1..32 {
+            let a = GF32::new(n);
+            assert_eq!(a * a.inv(), GF32::one());
+            assert_eq!(a.pow(31), GF32::one());
+            assert_eq!(a - a, GF32::zero());
+            assert_eq!(-a, a);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "not in GF(2^5)")]
+    fn out_of_range() {
+        GF32::new(32);
+    }
+}
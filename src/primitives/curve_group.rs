@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+use crate::primitives::{field_element_u32::FieldElementU32, point_fe_u32::PointFEU32};
+
+// Brute-force tools for exploring the group on a small curve y^2 = x^3 + ax + b.
+// Everything here is O(p) or worse, so only use it on the chapter-sized fields.
+
+// Every point on the curve, point at infinity first, then ordered by (x, y)
+pub fn points(a: FieldElementU32, b: FieldElementU32) -> Vec<PointFEU32> {
+    assert_eq!(a.prime, b.prime, "Cannot operate between different fields");
+    let p = a.prime;
+    let disc = fe(4, p) * a.pow(3) + fe(27, p) * b.pow(2);
+    assert!(disc.num != 0, "Curve is singular");
+
+    // y values for each square, so each x needs a single lookup
+    let mut roots = vec![Vec::new(); p as usize];
+    for y in 0..p {
+        roots[(y * y % p) as usize].push(y);
+    }
+
+    let mut res = vec![PointFEU32::new_inf(a, b)];
+    for x in 0..p {
+        let x = fe(x, p);
+        let alpha = x.pow(3) + a * x + b;
+        for &y in &roots[alpha.num as usize] {
+            res.push(PointFEU32::new_concrete(x, fe(y, p), a, b));
+        }
+    }
+    res
+}
+
+pub fn group_order(a: FieldElementU32, b: FieldElementU32) -> u32 {
+    points(a, b).len() as u32
+}
+
+// The order of a point divides the group order, so only the divisors need checking
+pub fn point_order(point: PointFEU32) -> u32 {
+    order_dividing(point, group_order(point.a, point.b))
+}
+
+// The cyclic subgroup generated by point: O, P, 2P, ...
+pub fn subgroup(point: PointFEU32) -> Vec<PointFEU32> {
+    let inf = PointFEU32::new_inf(point.a, point.b);
+    let mut res = vec![inf];
+    let mut curr = point;
+    while curr != inf {
+        res.push(curr);
+        curr = curr + point;
+    }
+    res
+}
+
+// Points grouped by their order. Each point in the entry for d generates a cyclic subgroup of order d.
+pub fn generators_by_order(a: FieldElementU32, b: FieldElementU32) -> BTreeMap<u32, Vec<PointFEU32>> {
+    let all = points(a, b);
+    let n = all.len() as u32;
+    let mut res: BTreeMap<u32, Vec<PointFEU32>> = BTreeMap::new();
+    for point in all {
+        res.entry(order_dividing(point, n)).or_default().push(point);
+    }
+    res
+}
+
+// Points of maximal order, empty when the group is not cyclic
+pub fn generators(a: FieldElementU32, b: FieldElementU32) -> Vec<PointFEU32> {
+    let n = group_order(a, b);
+    generators_by_order(a, b).remove(&n).unwrap_or_default()
+}
+
+pub fn label(point: &PointFEU32) -> String {
+    match (point.x, point.y) {
+        (Some(x), Some(y)) => format!("({},{})", x.num, y.num),
+        _ => "O".to_string(),
+    }
+}
+
+// Cayley table of the given points, one row per left operand
+pub fn addition_table(points: &[PointFEU32]) -> String {
+    let labels: Vec<String> = points.iter().map(label).collect();
+    let mut rows = vec![std::iter::once(String::from("+")).chain(labels.iter().cloned()).collect::<Vec<_>>()];
+    for (p1, l1) in points.iter().zip(&labels) {
+        let mut row = vec![l1.clone()];
+        row.extend(points.iter().map(|&p2| label(&(*p1 + p2))));
+        rows.push(row);
+    }
+
+    let width = rows.iter().flatten().map(|cell| cell.len()).max().unwrap_or(0);
+    let mut res = String::new();
+    for row in rows {
+        let cells: Vec<String> = row.iter().map(|cell| format!("{:>width$}", cell)).collect();
+        res.push_str(cells.join(" ").trim_end());
+        res.push('\n');
+    }
+    res
+}
+
+fn order_dividing(point: PointFEU32, n: u32) -> u32 {
+    (1..=n)
+        .filter(|&d| n.is_multiple_of(d))
+        .find(|&d| point.smul(d as i32).x.is_none())
+        .expect("group order always annihilates a point")
+}
+
+fn fe(num: u32, prime: u32) -> FieldElementU32 {
+    FieldElementU32::new(num % prime, prime)
+}
+
+#[cfg(test)]
+mod curve_group_tests {
+    use crate::{fe32, p_fe32};
+    use super::*;
+
+    #[test]
+    fn points_small_curve() {
+        let all = points(fe32!(0, 13), fe32!(7, 13));
+        let labels: Vec<String> = all.iter().map(label).collect();
+        assert_eq!(labels, ["O", "(7,5)", "(7,8)", "(8,5)", "(8,8)", "(11,5)", "(11,8)"]);
+    }
+
+    #[test]
+    fn orders_chapter_curve() {
+        let a = fe32!(0, 223);
+        let b = fe32!(7, 223);
+        assert_eq!(group_order(a, b), 252);
+        assert_eq!(point_order(p_fe32!(fe32!(47, 223), fe32!(71, 223), a, b)), 21);
+        assert_eq!(point_order(p_fe32!(fe32!(15, 223), fe32!(86, 223), a, b)), 7);
+
+        let by_order = generators_by_order(a, b);
+        assert_eq!(by_order.values().map(|v| v.len() as u32).sum::<u32>(), 252);
+        assert!(by_order.keys().all(|&d| 252u32.is_multiple_of(d)));
+        assert_eq!(by_order[&1], vec![PointFEU32::new_inf(a, b)]);
+    }
+
+    #[test]
+    fn subgroup_and_generators() {
+        let a = fe32!(2, 17);
+        let b = fe32!(2, 17);
+        // Prime order 19, so every point but O generates the whole group
+        assert_eq!(group_order(a, b), 19);
+        assert_eq!(generators(a, b).len(), 18);
+
+        let g = p_fe32!(fe32!(5, 17), fe32!(1, 17), a, b);
+        let sub = subgroup(g);
+        assert_eq!(sub.len(), 19);
+        assert_eq!(sub[2], g.smul(2));
+    }
+
+    #[test]
+    fn table() {
+        let a = fe32!(0, 13);
+        let b = fe32!(7, 13);
+        let g = p_fe32!(fe32!(7, 13), fe32!(5, 13), a, b);
+        let sub = subgroup(g);
+        let table = addition_table(&sub[..3]);
+        let expected = "     +      O  (7,5)  (8,5)\n     O      O  (7,5)  (8,5)\n (7,5)  (7,5)  (8,5) (11,8)\n (8,5)  (8,5) (11,8) (11,5)\n";
+        assert_eq!(table, expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "Curve is singular")]
+    fn singular_curve() {
+        points(fe32!(0, 13), fe32!(0, 13));
+    }
+}
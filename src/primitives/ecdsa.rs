@@ -0,0 +1,75 @@
+use crypto_bigint::U256;
+use crate::primitives::{field::Field, field_element::{FieldElement, Modulus}, point::{Curve, Point}};
+
+// A curve with a base point G of prime order n, which is all ECDSA needs
+pub trait EcdsaCurve: Curve + Sized {
+    type Order: Modulus + std::fmt::Debug;
+
+    fn g() -> Point<Self>;
+
+    fn n() -> U256 {
+        Self::Order::modulus()
+    }
+}
+
+impl<C: EcdsaCurve> Point<C> {
+    // z is the message hash and gets reduced mod n here
+    pub fn verify_ecdsa(self, z: U256, r: U256, s: U256) -> bool {
+        let n = C::n();
+        if r == U256::ZERO || r >= n || s == U256::ZERO || s >= n {
+            return false;
+        }
+
+        let s_inv = FieldElement::<C::Order>::new(s).inv();
+        let u = FieldElement::<C::Order>::new(z % n) * s_inv;
+        let v = FieldElement::<C::Order>::new(r) * s_inv;
+
+        let total = C::g().smul(u.num) + self.smul(v.num);
+
+        match total.x() {
+            Some(x) => x.as_u256() % n == r,
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod ecdsa_tests {
+    use crypto_bigint::U256;
+    use crate::primitives::{field::Field, field_element::FieldElement, toy_curves::{Toy1051, Toy1051Order}};
+    use super::*;
+
+    type ToyScalar = FieldElement<Toy1051Order>;
+
+    // s = (z + r * e) / k, computed by hand so the test does not depend on any signer
+    fn toy_sign(e: u32, z: u32, k: u32) -> (U256, U256) {
+        let r = Toy1051::g().smul(U256::from(k)).x().unwrap().as_u256() % Toy1051::n();
+        let s = (ToyScalar::new(U256::from(z)) + ToyScalar::new(r) * ToyScalar::new(U256::from(e))) / ToyScalar::new(U256::from(k));
+        (r, s.num)
+    }
+
+    #[test]
+    fn g_order() {
+        assert!(Toy1051::g().smul(Toy1051::n()).is_inf());
+    }
+
+    #[test]
+    fn verify_toy_curve() {
+        let e = 777u32;
+        let point = Toy1051::g().smul(U256::from(e));
+
+        for (z, k) in [(100u32, 5u32), (1000, 321), (42, 1092)] {
+            let (r, s) = toy_sign(e, z, k);
+            assert!(point.verify_ecdsa(U256::from(z), r, s));
+            assert!(!point.verify_ecdsa(U256::from(z + 1), r, s));
+        }
+    }
+
+    #[test]
+    fn verify_out_of_range() {
+        let point = Toy1051::g().smul(U256::from(777u32));
+        let (r, s) = toy_sign(777, 100, 5);
+        assert!(!point.verify_ecdsa(U256::from(100u32), U256::ZERO, s));
+        assert!(!point.verify_ecdsa(U256::from(100u32), r, Toy1051::n()));
+    }
+}
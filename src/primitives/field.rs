@@ -0,0 +1,23 @@
+use std::fmt::Debug;
+use std::ops;
+use crypto_bigint::U256;
+
+// What curve arithmetic needs from a field, so points can be written once for
+// both the chapter 1 FieldElementU32 and the generic FieldElement<M>.
+pub trait Field:
+    Copy
+    + PartialEq
+    + Debug
+    + ops::Add<Output = Self>
+    + ops::Sub<Output = Self>
+    + ops::Mul<Output = Self>
+    + ops::Div<Output = Self>
+    + ops::Neg<Output = Self>
+{
+    fn is_zero(&self) -> bool;
+
+    fn inv(self) -> Self;
+
+    // Integer representative in 0..p, e.g. to reduce an x coordinate mod a group order
+    fn as_u256(&self) -> U256;
+}
@@ -2,6 +2,7 @@ use std::ops;
 use crypto_bigint::U256;
 use crypto_bigint::I256;
 use crypto_bigint::NonZero;
+use crate::primitives::field::Field;
 
 pub trait Modulus {
     fn modulus() -> U256;
@@ -23,12 +24,89 @@ impl<M: Modulus> FieldElement<M> {
         }
     }
 
-    // TODO: Write test for this
-    // (p + 1) / 4
-    pub fn sqrt(self) -> Self {
+    // Legendre symbol via Euler's criterion: a^((p-1)/2) is 1 for squares, p-1 for non-squares
+    pub fn legendre(self) -> i8 {
+        let p = M::modulus();
+        let ls = self.pow_u256((p - U256::ONE) >> 1);
+        if ls.num == U256::ZERO {
+            0
+        } else if ls.num == U256::ONE {
+            1
+        } else {
+            -1
+        }
+    }
+
+    pub fn is_square(self) -> bool {
+        self.legendre() >= 0
+    }
+
+    // Returns None for non-residues. Either root may come back; the other one is p - root.
+    pub fn sqrt(self) -> Option<Self> {
+        if self.num == U256::ZERO {
+            return Some(self);
+        }
+        if !self.is_square() {
+            return None;
+        }
+
+        let p = M::modulus();
+        let root = if p.as_words()[0] & 3 == 3 {
+            // p = 3 (mod 4): a^((p + 1) / 4)
+            self.pow_u256((p + U256::ONE) >> 2)
+        } else {
+            self.tonelli_shanks()
+        };
+
+        if root * root == self { Some(root) } else { None }
+    }
+
+    // Tonelli-Shanks for any odd prime. Assumes self is a non-zero square.
+    fn tonelli_shanks(self) -> Self {
         let p = M::modulus();
-        let exponent = (p + U256::ONE) >> 2;
-        self.pow_u256(exponent)
+        let one = Self::new(U256::ONE);
+
+        // p - 1 = q * 2^s with q odd
+        let mut q = p - U256::ONE;
+        let mut s = 0u32;
+        while !bool::from(q.bit(0)) {
+            q >>= 1;
+            s += 1;
+        }
+
+        // Any non-residue works; half of the field qualifies so this ends quickly
+        let mut z = Self::new(U256::from(2u8));
+        while z.is_square() {
+            z = z + one;
+        }
+
+        let mut m = s;
+        let mut c = z.pow_u256(q);
+        let mut t = self.pow_u256(q);
+        let mut r = self.pow_u256((q + U256::ONE) >> 1);
+
+        while t != one {
+            // Least i with t^(2^i) = 1
+            let mut i = 0u32;
+            let mut t_sq = t;
+            while t_sq != one {
+                t_sq = t_sq * t_sq;
+                i += 1;
+            }
+
+            // b = c^(2^(m - i - 1))
+            let mut b = c;
+            for _ in 0..(m - i - 1) {
+                b = b * b;
+            }
+
+            m = i;
+            c = b * b;
+            t = t * c;
+            r = r * b;
+        }
+
+        r
     }
 
     // TODO: Write test for this
@@ -67,6 +145,34 @@ impl<M: Modulus> FieldElement<M> {
         result
     }
 
+    // Montgomery's trick: inverts every element with a single inv() plus 3(n-1) multiplications.
+    // Panics like inv() if any element is zero.
+    pub fn batch_inv(elements: &[Self]) -> Vec<Self> {
+        if elements.is_empty() {
+            return Vec::new();
+        }
+
+        // prefix[i] = elements[0] * ... * elements[i]
+        let mut prefix = Vec::with_capacity(elements.len());
+        let mut acc = elements[0];
+        prefix.push(acc);
+        for el in &elements[1..] {
+            acc = acc * *el;
+            prefix.push(acc);
+        }
+
+        // Walk backwards peeling one element off the inverted product at a time
+        let mut acc_inv = acc.inv();
+        let mut result = vec![acc_inv; elements.len()];
+        for i in (1..elements.len()).rev() {
+            result[i] = acc_inv * prefix[i - 1];
+            acc_inv = acc_inv * elements[i];
+        }
+        result[0] = acc_inv;
+
+        result
+    }
+
     pub fn get_prime(self) -> U256 {
         return M::modulus()
     }
@@ -146,9 +252,20 @@ impl<M: Modulus> ops::Div for FieldElement<M> {
 
     fn div(self, other: Self) -> Self {
         let p = M::modulus();
-        let exp = p.as_int() - I256::from(2);
-        let divisor = other.pow(exp);
-        let num = (self.num * divisor.num) % p;
+        let prime_nz = NonZero::new(p).unwrap();
+        let num = self.num.mul_mod(&other.inv().num, &prime_nz);
+
+        Self::new(num)
+    }
+
+}
+
+impl<M: Modulus> ops::Neg for FieldElement<M> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        let p = M::modulus();
+        let num = if self.num == U256::ZERO { U256::ZERO } else { p - self.num };
         Self::new(num)
     }
 
@@ -162,6 +279,20 @@ impl<M: Modulus> PartialEq for FieldElement<M> {
 
 impl<M: Modulus> Eq for FieldElement<M> {}
 
+impl<M: Modulus + std::fmt::Debug> Field for FieldElement<M> {
+    fn is_zero(&self) -> bool {
+        self.num == U256::ZERO
+    }
+
+    fn inv(self) -> Self {
+        FieldElement::inv(self)
+    }
+
+    fn as_u256(&self) -> U256 {
+        self.num
+    }
+}
+
 #[derive(Debug)]
 pub struct Prime7;
 impl Modulus for Prime7 {
@@ -296,6 +427,38 @@ mod field_element_ops_tests {
         assert_eq!(res2.num, res2_num);
     }
 
+    #[test]
+    fn batch_inv_valid() {
+        type TestField31 = FieldElement<Prime31>;
+        let elements: Vec<TestField31> = (1u32..31).map(|n| TestField31::new(U256::from(n))).collect();
+
+        let inverses = TestField31::batch_inv(&elements);
+        assert_eq!(inverses.len(), elements.len());
+        for (el, inv) in elements.iter().zip(inverses.iter()) {
+            assert_eq!(*inv, el.inv());
+            assert_eq!((*el * *inv).num, U256::ONE);
+        }
+
+        let x1 = S256Field::new(U256::from_be_hex("79BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798"));
+        let y1 = S256Field::new(U256::from_be_hex("483ADA7726A3C4655DA4FBFC0E1108A8FD17B448A68554199C47D08FFB10D4B8"));
+        let inverses = S256Field::batch_inv(&[x1, y1]);
+        assert_eq!(inverses[0], x1.inv());
+        assert_eq!(inverses[1], y1.inv());
+    }
+
+    #[test]
+    fn batch_inv_empty() {
+        assert!(S256Field::batch_inv(&[]).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot invert zero")]
+    fn batch_inv_zero() {
+        type TestField7 = FieldElement<Prime7>;
+        let elements = [TestField7::new(U256::from(3u32)), TestField7::new(U256::ZERO)];
+        TestField7::batch_inv(&elements);
+    }
+
     // Discrete log problem
     /*
     #[test]
@@ -303,4 +466,73 @@ mod field_element_ops_tests {
     }
     */
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod field_element_sqrt_tests {
+    use crate::s256::scalr::Scalar;
+    use crate::s256::s256_field::S256Field;
+    use super::*;
+
+    // 17 - 1 = 2^4, so every root goes through Tonelli-Shanks
+    #[derive(Debug)]
+    struct Prime17;
+    impl Modulus for Prime17 {
+        fn modulus() -> U256 {
+            U256::from_u32(17u32)
+        }
+    }
+
+    fn check_all<M: Modulus + std::fmt::Debug>(p: u32) {
+        let squares: Vec<u32> = (1..p).map(|n| n * n % p).collect();
+        for n in 1..p {
+            let fe = FieldElement::<M>::new(U256::from(n));
+            let expected_square = squares.contains(&n);
+            assert_eq!(fe.is_square(), expected_square, "{} mod {}", n, p);
+            assert_eq!(fe.legendre(), if expected_square { 1 } else { -1 });
+            match fe.sqrt() {
+                Some(root) => assert_eq!(root * root, fe),
+                None => assert!(!expected_square),
+            }
+        }
+    }
+
+    #[test]
+    fn sqrt_small_primes() {
+        check_all::<Prime7>(7);
+        check_all::<Prime31>(31);
+        check_all::<Prime17>(17);
+    }
+
+    #[test]
+    fn sqrt_zero() {
+        let zero = FieldElement::<Prime17>::new(U256::ZERO);
+        assert_eq!(zero.legendre(), 0);
+        assert!(zero.is_square());
+        assert_eq!(zero.sqrt(), Some(zero));
+    }
+
+    #[test]
+    fn sqrt_non_residue() {
+        assert_eq!(FieldElement::<Prime7>::new(U256::from(3u32)).sqrt(), None);
+        assert_eq!(FieldElement::<Prime17>::new(U256::from(3u32)).sqrt(), None);
+    }
+
+    #[test]
+    fn sqrt_s256_field() {
+        let gy = S256Field::new(U256::from_be_hex("483ADA7726A3C4655DA4FBFC0E1108A8FD17B448A68554199C47D08FFB10D4B8"));
+        let root = (gy * gy).sqrt().unwrap();
+        assert!(root == gy || root == S256Field::new_zero() - gy);
+    }
+
+    #[test]
+    fn sqrt_scalar() {
+        // n = 1 (mod 4) with n - 1 divisible by 2^6
+        let x = Scalar::new(U256::from_be_hex("79BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798"));
+        let root = (x * x).sqrt().unwrap();
+        assert!(root == x || root == Scalar::new(U256::ZERO) - x);
+
+        let non_residue = (1u32..).map(|n| Scalar::new(U256::from(n))).find(|s| !s.is_square()).unwrap();
+        assert_eq!(non_residue.sqrt(), None);
+    }
+}
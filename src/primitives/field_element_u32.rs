@@ -1,4 +1,6 @@
 use std::ops;
+use crypto_bigint::U256;
+use crate::primitives::field::Field;
 
 // Chapter 1
 #[derive(PartialEq, Eq, Debug, Copy, Clone)]
@@ -94,6 +96,32 @@ impl ops::Div for FieldElementU32 {
 
 }
 
+impl ops::Neg for FieldElementU32 {
+    type Output = FieldElementU32;
+
+    fn neg(self) -> FieldElementU32 {
+        FieldElementU32 {
+            num: (self.prime - self.num) % self.prime,
+            prime: self.prime
+        }
+    }
+
+}
+
+impl Field for FieldElementU32 {
+    fn is_zero(&self) -> bool {
+        self.num == 0
+    }
+
+    fn inv(self) -> Self {
+        FieldElementU32::inv(self)
+    }
+
+    fn as_u256(&self) -> U256 {
+        U256::from(self.num)
+    }
+}
+
 #[cfg(test)]
 mod field_element_u32_basic_tests {
     use crate::fe32;
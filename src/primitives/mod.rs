@@ -1,4 +1,12 @@
+pub mod field;
 pub mod field_element_u32;
 pub mod field_element;
+pub mod point;
 pub mod point_i32;
-pub mod point_fe_u32;
\ No newline at end of file
+pub mod point_fe_u32;
+pub mod ecdsa;
+pub mod toy_curves;
+pub mod curve_group;
+pub mod ring_signature;
+pub mod binary_field;
+pub mod shamir;
\ No newline at end of file
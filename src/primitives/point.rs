@@ -0,0 +1,366 @@
+use std::io::{self, Error, ErrorKind};
+use std::ops;
+use crypto_bigint::U256;
+use crate::primitives::{field::Field, field_element::{FieldElement, Modulus}};
+
+// Short Weierstrass curve y^2 = x^3 + ax + b over C::Field
+pub trait Curve {
+    type Field: Field;
+
+    fn a() -> Self::Field;
+
+    fn b() -> Self::Field;
+}
+
+#[derive(Debug)]
+pub struct Point<C: Curve> {
+    x: Option<C::Field>,
+    y: Option<C::Field>,
+    _marker: core::marker::PhantomData<C>,
+}
+
+impl<C: Curve> Point<C> {
+    pub fn new(x: Option<C::Field>, y: Option<C::Field>) -> Self {
+        match (x, y) {
+            (Some(_x), Some(_y)) => {
+                assert_eq!(_y * _y, _x * _x * _x + C::a() * _x + C::b(), "({:?}, {:?}) is not on the curve", _x, _y);
+            }
+            _ => assert_eq!(x, y, "For Point at Infinity, both points should be None"),
+        }
+        Self { x, y, _marker: core::marker::PhantomData }
+    }
+
+    pub fn new_concrete(x: C::Field, y: C::Field) -> Self {
+        Self::new(Some(x), Some(y))
+    }
+
+    pub fn new_inf() -> Self {
+        Self::new(None, None)
+    }
+
+    pub fn x(self) -> Option<C::Field> {
+        self.x
+    }
+
+    pub fn y(self) -> Option<C::Field> {
+        self.y
+    }
+
+    pub fn is_inf(self) -> bool {
+        self.x.is_none()
+    }
+
+    pub fn a() -> C::Field {
+        C::a()
+    }
+
+    pub fn b() -> C::Field {
+        C::b()
+    }
+
+    // Double-and-add, no reduction of k (curves without a known order can use this too)
+    pub fn smul(self, k: U256) -> Self {
+        let mut coef = k;
+        let mut result = Self::new_inf();
+        let mut current = self;
+
+        while coef > U256::ZERO {
+            if coef.bit(0).into() {
+                result = result + current;
+            }
+            current = current + current;
+            coef >>= 1;
+        }
+
+        result
+    }
+
+    fn coords(self) -> Option<(C::Field, C::Field)> {
+        self.x.zip(self.y)
+    }
+}
+
+// SEC serialization, for curves over a 256-bit FieldElement<M>
+impl<M: Modulus + std::fmt::Debug, C: Curve<Field = FieldElement<M>>> Point<C> {
+    pub fn sec(self, compressed: bool) -> Vec<u8> {
+        let x_bytes = self.x().unwrap().num.to_be_bytes();
+        let y_bytes = self.y().unwrap().num.to_be_bytes();
+
+        let mut result;
+        if compressed {
+            result = Vec::with_capacity(33);
+            if bool::from(self.y().unwrap().num.bit(0)) {
+                result.push(0x03);
+            } else {
+                result.push(0x02);
+            }
+            result.extend_from_slice(&x_bytes);
+        } else {
+            result = Vec::with_capacity(65);
+            result.push(0x04);
+            result.extend_from_slice(&x_bytes);
+            result.extend_from_slice(&y_bytes);
+        }
+
+        result
+    }
+
+    pub fn parse(sec: &[u8]) -> io::Result<Self> {
+        let invalid = |msg| Error::new(ErrorKind::InvalidData, msg);
+        let p = M::modulus();
+
+        // Uncompressed: 04 || x || y
+        if sec.first() == Some(&0x04) {
+            if sec.len() != 65 {
+                return Err(invalid("Invalid uncompressed SEC length"));
+            }
+
+            let x = U256::from_be_slice(&sec[1..33]);
+            let y = U256::from_be_slice(&sec[33..65]);
+            if x >= p || y >= p {
+                return Err(invalid("SEC coordinate not in field range"));
+            }
+
+            let (x, y) = (FieldElement::new(x), FieldElement::new(y));
+            if y * y != x * x * x + C::a() * x + C::b() {
+                return Err(invalid("Point is not on the curve"));
+            }
+            return Ok(Self::new(Some(x), Some(y)));
+        }
+
+        // Compressed: 02 || x  OR  03 || x
+        if sec.len() != 33 || (sec[0] != 0x02 && sec[0] != 0x03) {
+            return Err(invalid("Invalid compressed SEC encoding"));
+        }
+
+        let is_even = sec[0] == 0x02;
+
+        let x = U256::from_be_slice(&sec[1..33]);
+        if x >= p {
+            return Err(invalid("SEC coordinate not in field range"));
+        }
+        let x = FieldElement::<M>::new(x);
+
+        // y^2 = x^3 + ax + b
+        let alpha = x * x * x + C::a() * x + C::b();
+
+        let beta = alpha.sqrt().ok_or_else(|| invalid("x is not on the curve"))?;
+
+        let even_beta;
+        let odd_beta;
+
+        if bool::from(beta.num.bit(0)) {
+            even_beta = -beta;
+            odd_beta = beta;
+        } else {
+            even_beta = beta;
+            odd_beta = -beta;
+        }
+
+        if is_even {
+            Ok(Self::new(Some(x), Some(even_beta)))
+        } else {
+            Ok(Self::new(Some(x), Some(odd_beta)))
+        }
+    }
+}
+
+// Bulk affine arithmetic, sharing one field inversion across many points
+impl<M: Modulus, C: Curve<Field = FieldElement<M>>> Point<C> {
+    // Adds q to every point. Pairs the chord formula can't handle (the identity, or equal x
+    // coordinates) fall back to ordinary addition.
+    pub fn add_batch(points: &[Self], q: Self) -> Vec<Self> {
+        let Some((qx, qy)) = q.coords() else {
+            return points.to_vec();
+        };
+        let chord = |p: &Self| p.x.is_some_and(|x| x != qx);
+
+        let denominators: Vec<FieldElement<M>> = points.iter().filter(|p| chord(p)).map(|p| qx - p.x.unwrap()).collect();
+        let mut inverses = FieldElement::batch_inv(&denominators).into_iter();
+
+        points.iter().map(|&p| {
+            if !chord(&p) {
+                return p + q;
+            }
+            let (x, y) = p.coords().unwrap();
+            let s = (qy - y) * inverses.next().unwrap();
+            let x3 = s * s - x - qx;
+            Self::new_concrete(x3, s * (x - x3) - y)
+        }).collect()
+    }
+}
+
+// Chord-and-tangent addition in affine coordinates, None being the point at infinity.
+// Shared by Point<C> and PointFEU32 so there is one copy of the formulas.
+pub fn add_affine<F: Field>(p1: Option<(F, F)>, p2: Option<(F, F)>, a: F) -> Option<(F, F)> {
+    let Some((x1, y1)) = p1 else { return p2 };
+    let Some((x2, y2)) = p2 else { return p1 };
+
+    // P + (-P), which includes doubling a point with y = 0
+    if x1 == x2 && (y1 != y2 || y1.is_zero()) {
+        return None;
+    }
+
+    let s = if x1 == x2 {
+        let x_sq = x1 * x1;
+        (x_sq + x_sq + x_sq + a) * (y1 + y1).inv()
+    } else {
+        (y2 - y1) * (x2 - x1).inv()
+    };
+
+    let x3 = (s * s) - x1 - x2;
+    let y3 = s * (x1 - x3) - y1;
+
+    Some((x3, y3))
+}
+
+impl<C: Curve> Clone for Point<C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<C: Curve> Copy for Point<C> {}
+
+impl<C: Curve> PartialEq for Point<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.x == other.x && self.y == other.y
+    }
+}
+
+impl<C: Curve> Eq for Point<C> {}
+
+impl<C: Curve> ops::Add for Point<C> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        match add_affine(self.coords(), other.coords(), C::a()) {
+            Some((x, y)) => Self::new_concrete(x, y),
+            None => Self::new_inf(),
+        }
+    }
+}
+
+impl<C: Curve> ops::Neg for Point<C> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        match self.coords() {
+            Some((x, y)) => Self::new_concrete(x, -y),
+            None => self,
+        }
+    }
+}
+
+impl<C: Curve> ops::Sub for Point<C> {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self + (-other)
+    }
+}
+
+#[cfg(test)]
+mod point_tests {
+    use crypto_bigint::U256;
+    use crate::primitives::field_element::{FieldElement, Modulus};
+    use crate::primitives::field_element_u32::FieldElementU32;
+    use crate::{curve, fe32, p_fe32};
+    use super::*;
+
+    curve!(Curve223, FieldElementU32, fe32!(0, 223), fe32!(7, 223));
+
+    #[derive(Debug)]
+    pub struct Prime223;
+    impl Modulus for Prime223 {
+        fn modulus() -> U256 {
+            U256::from_u32(223u32)
+        }
+    }
+    type F223 = FieldElement<Prime223>;
+
+    curve!(Curve223Generic, F223, F223::new(U256::ZERO), F223::new(U256::from(7u32)));
+
+    fn f223(n: u32) -> F223 {
+        F223::new(U256::from(n))
+    }
+
+    #[test]
+    #[should_panic(expected = "is not on the curve")]
+    fn init_invalid() {
+        Point::<Curve223>::new_concrete(fe32!(200, 223), fe32!(119, 223));
+    }
+
+    #[test]
+    #[should_panic(expected = "both points should be None")]
+    fn init_inf_invalid() {
+        Point::<Curve223>::new(Some(fe32!(1, 223)), None);
+    }
+
+    #[test]
+    fn add_matches_point_fe_u32() {
+        let fe0 = fe32!(0, 223);
+        let fe7 = fe32!(7, 223);
+        let pairs = [((170, 142), (60, 139)), ((47, 71), (17, 56)), ((143, 98), (76, 66)), ((47, 71), (47, 71))];
+
+        for ((x1, y1), (x2, y2)) in pairs {
+            let expected = p_fe32!(fe32!(x1, 223), fe32!(y1, 223), fe0, fe7) + p_fe32!(fe32!(x2, 223), fe32!(y2, 223), fe0, fe7);
+
+            let p1 = Point::<Curve223>::new_concrete(fe32!(x1, 223), fe32!(y1, 223));
+            let p2 = Point::<Curve223>::new_concrete(fe32!(x2, 223), fe32!(y2, 223));
+            let sum = p1 + p2;
+            assert_eq!(sum.x(), expected.x);
+            assert_eq!(sum.y(), expected.y);
+
+            let p1 = Point::<Curve223Generic>::new_concrete(f223(x1), f223(y1));
+            let p2 = Point::<Curve223Generic>::new_concrete(f223(x2), f223(y2));
+            let sum = p1 + p2;
+            assert_eq!(sum.x().unwrap().num, U256::from(expected.x.unwrap().num));
+            assert_eq!(sum.y().unwrap().num, U256::from(expected.y.unwrap().num));
+        }
+    }
+
+    #[test]
+    fn add_identity_and_inverse() {
+        let p = Point::<Curve223Generic>::new_concrete(f223(47), f223(71));
+        let inf = Point::<Curve223Generic>::new_inf();
+
+        assert_eq!(p + inf, p);
+        assert_eq!(inf + p, p);
+        assert_eq!(-p, Point::new_concrete(f223(47), f223(152)));
+        assert!((p - p).is_inf());
+        assert!((p + (-p)).is_inf());
+    }
+
+    #[test]
+    fn add_batch() {
+        let q = Point::<Curve223Generic>::new_concrete(f223(47), f223(71));
+        let points = [
+            Point::new_concrete(f223(170), f223(142)),
+            Point::new_inf(),
+            q,
+            -q,
+            Point::new_concrete(f223(60), f223(139)),
+        ];
+
+        let sums = Point::add_batch(&points, q);
+        for (p, sum) in points.iter().zip(&sums) {
+            assert_eq!(*sum, *p + q);
+        }
+        assert_eq!(Point::add_batch(&points, Point::new_inf()), points);
+    }
+
+    #[test]
+    fn smul() {
+        let p = Point::<Curve223>::new_concrete(fe32!(47, 223), fe32!(71, 223));
+
+        let res = p.smul(U256::from(5u32));
+        assert_eq!(res.x(), Some(fe32!(126, 223)));
+        assert_eq!(res.y(), Some(fe32!(96, 223)));
+
+        // (47, 71) generates a group of order 21
+        assert!(p.smul(U256::from(21u32)).is_inf());
+        assert_eq!(p.smul(U256::from(22u32)), p);
+        assert!(p.smul(U256::ZERO).is_inf());
+    }
+}
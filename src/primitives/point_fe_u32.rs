@@ -1,5 +1,6 @@
 use std::ops;
 use crate::primitives::field_element_u32::FieldElementU32;
+use crate::primitives::point::add_affine;
 
 #[derive(PartialEq, Eq, Debug, Copy, Clone)]
 pub struct PointFEU32 {
@@ -53,49 +54,24 @@ impl ops::Add for PointFEU32 {
     fn add(self, other: PointFEU32) -> PointFEU32 {
         assert!(self.a == other.a && self.b == other.b, "Points are not in the same curve");
 
-        let zero = FieldElementU32::new(0, self.a.prime);
-
-        if self.x == None {
-            return other;
-        } else if other.x == None {
-            return self;
-        } else if self.x == other.x && self.y != other.y {
-            return PointFEU32::new_inf(self.a, self.b);
-        } else if self.x != other.x {
-            let x1 = self.x.unwrap();
-            let y1 = self.y.unwrap();
-            let x2 = other.x.unwrap();
-            let y2 = other.y.unwrap();
-
-            let s = (y2 - y1)/(x2 - x1);
-            
-            let x3 = (s * s) - x1 - x2;
-            let y3 =  s * (x1 - x3) - y1;
-
-            PointFEU32::new_concrete(x3, y3, self.a, self.b)
-        } else if self == other && self.y == Some(zero) {
-            return PointFEU32::new_inf(self.a, self.b);
-        } else if self == other && self.y != Some(zero) {
-            let x = self.x.unwrap();
-            let y = self.y.unwrap();
-            let a = self.a;
-
-            let fe_3 = FieldElementU32::new(3, x.prime);
-            let fe_2 = FieldElementU32::new(2, x.prime);
-
-            let s = (fe_3 * x * x + a)/(fe_2 * y);
-            
-            let x3 = (s * s) - (fe_2 * x);
-            let y3 = (s * (x - x3)) - y;
-
-            PointFEU32::new_concrete(x3, y3, self.a, self.b)
-        } else {
-            // Should never come here.
-            self
+        let p1 = self.x.zip(self.y);
+        let p2 = other.x.zip(other.y);
+
+        match add_affine(p1, p2, self.a) {
+            Some((x3, y3)) => PointFEU32::new_concrete(x3, y3, self.a, self.b),
+            None => PointFEU32::new_inf(self.a, self.b),
         }
     }
 }
 
+impl ops::Neg for PointFEU32 {
+    type Output = PointFEU32;
+
+    fn neg(self) -> PointFEU32 {
+        PointFEU32 { x: self.x, y: self.y.map(|y| -y), a: self.a, b: self.b }
+    }
+}
+
 #[cfg(test)]
 mod point_fe_u32_tests_init {
     use crate::p_fe32;
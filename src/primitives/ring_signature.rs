@@ -0,0 +1,212 @@
+use std::io::{self, Error, ErrorKind};
+use crypto_bigint::U256;
+use crate::{algorithms::hash256::hash256, primitives::{ecdsa::EcdsaCurve, field::Field, field_element::FieldElement, point::Point}};
+
+// AOS ring signature (Abe, Ohkubo, Suzuki): proves the signer holds the key of one
+// ring member without saying which. Each member i gets R_i = s_i G - e_i P_i and
+// e_{i+1} = H(ring, msg, R_i), closing the loop back to e_0. Only the real signer can
+// close it, by choosing its R = kG first and solving s = k + e x at the end.
+#[derive(Debug)]
+pub struct RingSignature<C: EcdsaCurve> {
+    pub e0: FieldElement<C::Order>,
+    pub s: Vec<FieldElement<C::Order>>,
+}
+
+impl<C: EcdsaCurve> Clone for RingSignature<C> {
+    fn clone(&self) -> Self {
+        Self { e0: self.e0, s: self.s.clone() }
+    }
+}
+
+impl<C: EcdsaCurve> PartialEq for RingSignature<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.e0 == other.e0 && self.s == other.s
+    }
+}
+
+impl<C: EcdsaCurve> RingSignature<C> {
+    pub fn sign(msg: &[u8], ring: &[Point<C>], secret: U256, index: usize) -> Self {
+        let n = ring.len();
+        assert!(index < n, "signer index outside the ring");
+        assert!(C::g().smul(secret) == ring[index], "secret does not match ring member {}", index);
+
+        let ring_digest = ring_digest(ring);
+        let x = scalar::<C>(secret % C::n());
+
+        // Deterministic nonce and fake responses, all derived from the secret and the message
+        let mut seed = secret.to_be_bytes().to_vec();
+        seed.extend_from_slice(&ring_digest);
+        seed.extend_from_slice(msg);
+        let nonce = |i: usize| {
+            let mut preimage = seed.clone();
+            preimage.extend_from_slice(&(i as u32).to_be_bytes());
+            hash_to_scalar::<C>(&preimage)
+        };
+
+        let k = nonce(n);
+        let mut s = vec![scalar::<C>(U256::ZERO); n];
+        let mut e = vec![scalar::<C>(U256::ZERO); n];
+
+        // Walk from the signer round the ring back to it
+        e[(index + 1) % n] = challenge(&ring_digest, msg, C::g().smul(k.num));
+        for step in 1..n {
+            let i = (index + step) % n;
+            s[i] = nonce(i);
+            let r = C::g().smul(s[i].num) - ring[i].smul(e[i].num);
+            e[(i + 1) % n] = challenge(&ring_digest, msg, r);
+        }
+        s[index] = k + e[index] * x;
+
+        Self { e0: e[0], s }
+    }
+
+    pub fn verify(&self, msg: &[u8], ring: &[Point<C>]) -> bool {
+        if ring.is_empty() || ring.len() != self.s.len() {
+            return false;
+        }
+
+        let ring_digest = ring_digest(ring);
+        let mut e = self.e0;
+        for (point, s) in ring.iter().zip(&self.s) {
+            let r = C::g().smul(s.num) - point.smul(e.num);
+            e = challenge(&ring_digest, msg, r);
+        }
+        e == self.e0
+    }
+
+    // e0 || s_0 || ... || s_{n-1}, 32 bytes each
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut res = Vec::with_capacity(32 * (self.s.len() + 1));
+        res.extend_from_slice(&self.e0.num.to_be_bytes());
+        for s in &self.s {
+            res.extend_from_slice(&s.num.to_be_bytes());
+        }
+        res
+    }
+
+    pub fn parse(bytes: &[u8]) -> io::Result<Self> {
+        if bytes.len() < 64 || !bytes.len().is_multiple_of(32) {
+            return Err(Error::new(ErrorKind::InvalidData, "Bad ring signature length"));
+        }
+
+        let mut values = Vec::with_capacity(bytes.len() / 32);
+        for chunk in bytes.chunks(32) {
+            let num = U256::from_be_slice(chunk);
+            if num >= C::n() {
+                return Err(Error::new(ErrorKind::InvalidData, "Ring signature value out of range"));
+            }
+            values.push(scalar::<C>(num));
+        }
+
+        let e0 = values.remove(0);
+        Ok(Self { e0, s: values })
+    }
+}
+
+fn ring_digest<C: EcdsaCurve>(ring: &[Point<C>]) -> [u8; 32] {
+    let mut preimage = Vec::with_capacity(33 * ring.len());
+    for point in ring {
+        preimage.extend_from_slice(&point_bytes(*point));
+    }
+    hash256(&preimage)
+}
+
+fn challenge<C: EcdsaCurve>(ring_digest: &[u8; 32], msg: &[u8], r: Point<C>) -> FieldElement<C::Order> {
+    let mut preimage = ring_digest.to_vec();
+    preimage.extend_from_slice(msg);
+    preimage.extend_from_slice(&point_bytes(r));
+    hash_to_scalar::<C>(&preimage)
+}
+
+// Compressed-SEC-like encoding that works for any field, with 0x00 for the point at infinity
+fn point_bytes<C: EcdsaCurve>(point: Point<C>) -> Vec<u8> {
+    match (point.x(), point.y()) {
+        (Some(x), Some(y)) => {
+            let mut res = vec![if bool::from(y.as_u256().bit(0)) { 0x03 } else { 0x02 }];
+            res.extend_from_slice(&x.as_u256().to_be_bytes());
+            res
+        }
+        _ => vec![0x00],
+    }
+}
+
+fn hash_to_scalar<C: EcdsaCurve>(data: &[u8]) -> FieldElement<C::Order> {
+    scalar::<C>(U256::from_be_slice(&hash256(data)) % C::n())
+}
+
+fn scalar<C: EcdsaCurve>(num: U256) -> FieldElement<C::Order> {
+    FieldElement::new(num)
+}
+
+#[cfg(test)]
+mod ring_signature_tests {
+    use crate::primitives::toy_curves::Toy1051;
+    use crate::s256::s256_point::Secp256k1;
+    use super::*;
+
+    fn toy_ring(size: u64) -> (Vec<U256>, Vec<Point<Toy1051>>) {
+        let secrets: Vec<U256> = (0..size).map(|i| U256::from_u64(17 + 101 * i)).collect();
+        let ring = secrets.iter().map(|&e| Toy1051::g().smul(e)).collect();
+        (secrets, ring)
+    }
+
+    #[test]
+    fn toy_rings_of_many_sizes() {
+        for size in 1..=8u64 {
+            let (secrets, ring) = toy_ring(size);
+            for (index, &secret) in secrets.iter().enumerate() {
+                let sig = RingSignature::sign(b"proof of reserves", &ring, secret, index);
+                assert!(sig.verify(b"proof of reserves", &ring));
+                assert!(!sig.verify(b"another message", &ring));
+            }
+        }
+    }
+
+    #[test]
+    fn toy_ring_tampering() {
+        let (secrets, ring) = toy_ring(5);
+        let sig = RingSignature::sign(b"msg", &ring, secrets[2], 2);
+
+        // Different ring order, missing member, altered response
+        let mut swapped = ring.clone();
+        swapped.swap(0, 1);
+        assert!(!sig.verify(b"msg", &swapped));
+        assert!(!sig.verify(b"msg", &ring[..4]));
+
+        let mut altered = sig.clone();
+        altered.s[3] = altered.s[3] + scalar::<Toy1051>(U256::ONE);
+        assert!(!altered.verify(b"msg", &ring));
+    }
+
+    #[test]
+    #[should_panic(expected = "secret does not match ring member")]
+    fn wrong_secret() {
+        let (secrets, ring) = toy_ring(3);
+        RingSignature::sign(b"msg", &ring, secrets[0], 1);
+    }
+
+    #[test]
+    fn serialize_roundtrip() -> Result<(), Box<dyn std::error::Error>> {
+        let (secrets, ring) = toy_ring(4);
+        let sig = RingSignature::sign(b"msg", &ring, secrets[1], 1);
+        let bytes = sig.serialize();
+        assert_eq!(bytes.len(), 32 * 5);
+        assert_eq!(RingSignature::<Toy1051>::parse(&bytes)?, sig);
+
+        assert!(RingSignature::<Toy1051>::parse(&bytes[..33]).is_err());
+        assert!(RingSignature::<Toy1051>::parse(&[0xff; 64]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn secp256k1_ring() -> Result<(), Box<dyn std::error::Error>> {
+        // Small secrets keep key generation cheap; the signature itself uses full-size scalars
+        let secrets = [U256::from_u64(1001), U256::from_u64(2002)];
+        let ring: Vec<Point<Secp256k1>> = secrets.iter().map(|&e| Secp256k1::g().smul(e)).collect();
+
+        let sig = RingSignature::sign(b"we hold one of these keys", &ring, secrets[1], 1);
+        assert!(sig.verify(b"we hold one of these keys", &ring));
+        assert_eq!(RingSignature::<Secp256k1>::parse(&sig.serialize())?, sig);
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use crate::primitives::field::Field;
+
+// Shamir secret sharing: a share is a point on a polynomial of degree threshold - 1,
+// and any threshold shares fix it. Splitting and recovery are both interpolation.
+
+// Lagrange interpolation through points, evaluated at x
+pub fn interpolate<F: Field>(points: &[(F, F)], x: F) -> F {
+    assert!(!points.is_empty(), "Need at least one point");
+
+    points.iter()
+        .enumerate()
+        .map(|(i, &(x_i, y_i))| {
+            // y_i times the Lagrange basis polynomial for x_i
+            points.iter().enumerate().filter(|&(j, _)| j != i).fold(y_i, |acc, (_, &(x_j, _))| {
+                assert!(x_i != x_j, "Duplicate share index {:?}", x_i);
+                acc * (x - x_j) / (x_i - x_j)
+            })
+        })
+        .reduce(|a, b| a + b)
+        .unwrap()
+}
+
+// Interpolates share values position by position, e.g. bytes of a secret
+pub fn interpolate_shares<F: Field>(shares: &[(F, Vec<F>)], x: F) -> Vec<F> {
+    assert!(!shares.is_empty(), "Need at least one share");
+    let len = shares[0].1.len();
+    assert!(shares.iter().all(|(_, values)| values.len() == len), "Shares have different lengths");
+
+    (0..len)
+        .map(|pos| {
+            let points: Vec<(F, F)> = shares.iter().map(|(x_i, values)| (*x_i, values[pos])).collect();
+            interpolate(&points, x)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod shamir_tests {
+    use crate::fe32;
+    use crate::primitives::binary_field::GF256;
+    use super::*;
+
+    #[test]
+    fn prime_field() {
+        // f(x) = 3 + 2x + x^2 over F_97
+        let f = |x: u32| fe32!((3 + 2 * x + x * x) % 97, 97);
+        let points = [(fe32!(1, 97), f(1)), (fe32!(5, 97), f(5)), (fe32!(9, 97), f(9))];
+
+        assert_eq!(interpolate(&points, fe32!(0, 97)), f(0));
+        assert_eq!(interpolate(&points, fe32!(20, 97)), f(20));
+        // Two points only fix a line
+        assert_ne!(interpolate(&points[..2], fe32!(0, 97)), f(0));
+    }
+
+    #[test]
+    fn bytes_over_gf256() {
+        let secret: Vec<GF256> = b"shamir".iter().map(|&b| GF256::new(b)).collect();
+        let random: Vec<GF256> = b"random".iter().map(|&b| GF256::new(b)).collect();
+
+        // Threshold 2: the secret at x = 0 and one random share define the line
+        let base = [(GF256::new(0), secret.clone()), (GF256::new(1), random)];
+        let shares: Vec<(GF256, Vec<GF256>)> = (2..5).map(|x| (GF256::new(x), interpolate_shares(&base, GF256::new(x)))).collect();
+
+        assert_eq!(interpolate_shares(&shares[1..], GF256::new(0)), secret);
+        assert_eq!(interpolate_shares(&[shares[0].clone(), shares[2].clone()], GF256::new(0)), secret);
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate share index")]
+    fn duplicate_index() {
+        interpolate(&[(GF256::new(1), GF256::new(2)), (GF256::new(1), GF256::new(3))], GF256::new(0));
+    }
+}
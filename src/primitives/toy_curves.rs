@@ -0,0 +1,27 @@
+use crypto_bigint::U256;
+use crate::primitives::{ecdsa::EcdsaCurve, field_element::Modulus, field_element_u32::FieldElementU32, point::Point};
+use crate::{curve, fe32};
+
+// Small curves for teaching. Primes stay below 2^16 so FieldElementU32 products fit in a u32.
+
+// y^2 = x^3 + 7 over F_223, the chapter 3 curve. Its group order (252) is not prime.
+curve!(Toy223, FieldElementU32, fe32!(0, 223), fe32!(7, 223));
+
+// y^2 = x^3 + 7 over F_1051, whose group has prime order 1093, so any point is a generator
+curve!(Toy1051, FieldElementU32, fe32!(0, 1051), fe32!(7, 1051));
+
+#[derive(Debug)]
+pub struct Toy1051Order;
+impl Modulus for Toy1051Order {
+    fn modulus() -> U256 {
+        U256::from_u32(1093u32)
+    }
+}
+
+impl EcdsaCurve for Toy1051 {
+    type Order = Toy1051Order;
+
+    fn g() -> Point<Self> {
+        Point::new_concrete(fe32!(3, 1051), fe32!(385, 1051))
+    }
+}
@@ -0,0 +1,210 @@
+use crypto_bigint::U256;
+use sha2::{Digest, Sha256};
+use crate::{primitives::field_element::Modulus, s256::{s256_field::{S256Field, Secp256k1Prime}, s256_point::S256Point}};
+
+// Points nobody knows the discrete log of (NUMS: nothing up my sleeve).
+// Try-and-increment is the simple, variable-time way; SSWU is RFC 9380's
+// secp256k1_XMD:SHA-256_SSWU_RO_ suite.
+
+// x = sha256(seed), bumped by one until it lands on the curve; even y
+pub fn try_and_increment(seed: &[u8]) -> S256Point {
+    let mut x = S256Field::new(U256::from_be_slice(&Sha256::digest(seed)) % Secp256k1Prime::modulus());
+    loop {
+        if let Some(point) = S256Point::lift_x(x) {
+            return point;
+        }
+        x = x + S256Field::new_one();
+    }
+}
+
+// BIP341's H, the internal key for script-path-only taproot outputs:
+// lift_x(sha256(uncompressed G)), which needs no increments
+pub fn nums_h() -> S256Point {
+    try_and_increment(&S256Point::g().sec(false))
+}
+
+// hash_to_curve from RFC 9380: two field elements, mapped and added (secp256k1 has cofactor 1)
+pub fn hash_to_curve(msg: &[u8], dst: &[u8]) -> S256Point {
+    let [u0, u1] = hash_to_field(msg, dst);
+    map_to_curve(u0) + map_to_curve(u1)
+}
+
+pub fn hash_to_field(msg: &[u8], dst: &[u8]) -> [S256Field; 2] {
+    // 48 bytes per element, so the bias of reducing mod p is negligible
+    let uniform = expand_message_xmd(msg, dst, 96);
+    [reduce_48(&uniform[..48]), reduce_48(&uniform[48..])]
+}
+
+pub fn expand_message_xmd(msg: &[u8], dst: &[u8], len: usize) -> Vec<u8> {
+    let ell = len.div_ceil(32);
+    assert!(ell <= 255 && len <= 0xffff && dst.len() <= 255, "expand_message_xmd input too long");
+
+    let mut dst_prime = dst.to_vec();
+    dst_prime.push(dst.len() as u8);
+
+    let mut hasher = Sha256::new();
+    hasher.update([0u8; 64]);
+    hasher.update(msg);
+    hasher.update((len as u16).to_be_bytes());
+    hasher.update([0u8]);
+    hasher.update(&dst_prime);
+    let b0 = hasher.finalize();
+
+    let mut res = Vec::with_capacity(ell * 32);
+    let mut prev = [0u8; 32];
+    for i in 1..=ell {
+        // b_i = H((b_0 xor b_{i-1}) || i || dst'), with b_0 xor 0 = b_0 for the first block
+        let mixed: Vec<u8> = b0.iter().zip(prev.iter()).map(|(a, b)| a ^ b).collect();
+        let mut hasher = Sha256::new();
+        hasher.update(&mixed);
+        hasher.update([i as u8]);
+        hasher.update(&dst_prime);
+        prev = hasher.finalize().into();
+        res.extend_from_slice(&prev);
+    }
+
+    res.truncate(len);
+    res
+}
+
+// Simplified SWU onto the isogenous curve E': y^2 = x^3 + A'x + B', then the 3-isogeny to secp256k1.
+// secp256k1 itself has A = 0, which plain SSWU cannot handle.
+pub fn map_to_curve(u: S256Field) -> S256Point {
+    let (x, y) = map_to_curve_sswu(u);
+    iso_map(x, y)
+}
+
+fn map_to_curve_sswu(u: S256Field) -> (S256Field, S256Field) {
+    let a = fe("3f8731abdd661adca08a5558f0f5d272e953d363cb6f0e5d405447c01a444533");
+    let b = S256Field::new(U256::from_u32(1771));
+    let z = -S256Field::new(U256::from_u32(11));
+
+    let z_u2 = z * u * u;
+    let tv1 = z_u2 * z_u2 + z_u2;
+
+    let x1 = if tv1.num == U256::ZERO {
+        b / (z * a)
+    } else {
+        (-b / a) * (S256Field::new_one() + tv1.inv())
+    };
+    let gx1 = x1 * x1 * x1 + a * x1 + b;
+
+    let (x, y) = match gx1.sqrt() {
+        Some(y1) => (x1, y1),
+        None => {
+            let x2 = z_u2 * x1;
+            let gx2 = x2 * x2 * x2 + a * x2 + b;
+            (x2, gx2.sqrt().expect("gx1 or gx2 is always square"))
+        }
+    };
+
+    // Match the sign (parity) of u so the map is well defined
+    if sgn0(u) != sgn0(y) { (x, -y) } else { (x, y) }
+}
+
+// RFC 9380 appendix E.1
+fn iso_map(x: S256Field, y: S256Field) -> S256Point {
+    let x_num = horner(x, &[
+        "8e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38daaaaa8c7",
+        "07d3d4c80bc321d5b9f315cea7fd44c5d595d2fc0bf63b92dfff1044f17c6581",
+        "534c328d23f234e6e2a413deca25caece4506144037c40314ecbd0b53d9dd262",
+        "8e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38daaaaa88c",
+    ]);
+    let x_den = horner(x, &[
+        "d35771193d94918a9ca34ccbb7b640dd86cd409542f8487d9fe6b745781eb49b",
+        "edadc6f64383dc1df7c4b2d51b54225406d36b641f5e41bbc52a56612a8c6d14",
+        "0000000000000000000000000000000000000000000000000000000000000001",
+    ]);
+    let y_num = horner(x, &[
+        "4bda12f684bda12f684bda12f684bda12f684bda12f684bda12f684b8e38e23c",
+        "c75e0c32d5cb7c0fa9d0a54b12a0a6d5647ab046d686da6fdffc90fc201d71a3",
+        "29a6194691f91a73715209ef6512e576722830a201be2018a765e85a9ecee931",
+        "2f684bda12f684bda12f684bda12f684bda12f684bda12f684bda12f38e38d84",
+    ]);
+    let y_den = horner(x, &[
+        "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffff93b",
+        "7a06534bb8bdb49fd5e9e6632722c2989467c1bfc8e8d978dfb425d2685c2573",
+        "6484aa716545ca2cf3a70c3fa8fe337e0a3d21162f0d6299a7bf8192bfd2a76f",
+        "0000000000000000000000000000000000000000000000000000000000000001",
+    ]);
+
+    S256Point::new_concrete(x_num / x_den, y * y_num / y_den)
+}
+
+// Evaluates k_0 + k_1 x + k_2 x^2 + ...
+fn horner(x: S256Field, coefficients: &[&str]) -> S256Field {
+    coefficients.iter().rev().fold(S256Field::new_zero(), |acc, k| acc * x + fe(k))
+}
+
+// 48 big-endian bytes mod p, using 2^256 = 2^32 + 977 (mod p)
+fn reduce_48(bytes: &[u8]) -> S256Field {
+    let p = Secp256k1Prime::modulus();
+    let mut hi = [0u8; 32];
+    hi[16..].copy_from_slice(&bytes[..16]);
+    let hi = U256::from_be_slice(&hi).wrapping_mul(&U256::from_u64(0x1000003d1));
+    let lo = U256::from_be_slice(&bytes[16..]);
+    S256Field::new(hi % p) + S256Field::new(lo % p)
+}
+
+fn sgn0(x: S256Field) -> bool {
+    x.num.bit(0).into()
+}
+
+fn fe(hex: &str) -> S256Field {
+    S256Field::new(U256::from_be_hex(hex))
+}
+
+#[cfg(test)]
+mod hash_to_curve_tests {
+    use super::*;
+
+    const DST: &[u8] = b"QUUX-V01-CS02-with-secp256k1_XMD:SHA-256_SSWU_RO_";
+
+    fn point(x: &str, y: &str) -> S256Point {
+        S256Point::new_concrete(fe(x), fe(y))
+    }
+
+    #[test]
+    fn bip341_h() {
+        let h = nums_h();
+        assert_eq!(h.x().unwrap(), fe("50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0"));
+        assert!(!bool::from(h.y().unwrap().num.bit(0)));
+    }
+
+    #[test]
+    fn try_and_increment_is_deterministic() {
+        let p1 = try_and_increment(b"pedersen/H");
+        assert_eq!(p1, try_and_increment(b"pedersen/H"));
+        assert_ne!(p1, try_and_increment(b"pedersen/J"));
+        assert_ne!(p1, S256Point::g());
+    }
+
+    #[test]
+    fn lift_x_invalid() {
+        // x = 5 gives 132, a non-residue mod p
+        assert_eq!(S256Point::lift_x(S256Field::new(U256::from_u32(5))), None);
+    }
+
+    // RFC 9380 appendix J.8.1
+    #[test]
+    fn rfc9380_empty() {
+        let [u0, u1] = hash_to_field(b"", DST);
+        assert_eq!(u0, fe("6b0f9910dd2ba71c78f2ee9f04d73b5f4c5f7fc773a701abea1e573cab002fb3"));
+        assert_eq!(u1, fe("1ae6c212e08fe1a5937f6202f929a2cc8ef4ee5b9782db68b0d5799fd8f09e16"));
+        assert_eq!(map_to_curve(u0), point("74519ef88b32b425a095e4ebcc84d81b64e9e2c2675340a720bb1a1857b99f1e", "c174fa322ab7c192e11748beed45b508e9fdb1ce046dee9c2cd3a2a86b410936"));
+        assert_eq!(map_to_curve(u1), point("44548adb1b399263ded3510554d28b4bead34b8cf9a37b4bd0bd2ba4db87ae63", "96eb8e2faf05e368efe5957c6167001760233e6dd2487516b46ae725c4cce0c6"));
+        assert_eq!(hash_to_curve(b"", DST), point("c1cae290e291aee617ebaef1be6d73861479c48b841eaba9b7b5852ddfeb1346", "64fa678e07ae116126f08b022a94af6de15985c996c3a91b64c406a960e51067"));
+    }
+
+    #[test]
+    fn rfc9380_messages() {
+        let cases: [(&[u8], &str, &str); 3] = [
+            (b"abc", "3377e01eab42db296b512293120c6cee72b6ecf9f9205760bd9ff11fb3cb2c4b", "7f95890f33efebd1044d382a01b1bee0900fb6116f94688d487c6c7b9c8371f6"),
+            (b"abcdef0123456789", "bac54083f293f1fe08e4a70137260aa90783a5cb84d3f35848b324d0674b0e3a", "4436476085d4c3c4508b60fcf4389c40176adce756b398bdee27bca19758d828"),
+            (b"q128_qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqq", "e2167bc785333a37aa562f021f1e881defb853839babf52a7f72b102e41890e9", "f2401dd95cc35867ffed4f367cd564763719fbc6a53e969fb8496a1e6685d873"),
+        ];
+        for (msg, x, y) in cases {
+            assert_eq!(hash_to_curve(msg, DST), point(x, y));
+        }
+    }
+}
@@ -2,4 +2,6 @@ pub mod s256_field;
 pub mod s256_point;
 pub mod signature;
 pub mod scalr;
-pub mod private_key;
\ No newline at end of file
+pub mod private_key;
+pub mod hash_to_curve;
+pub mod pedersen;
\ No newline at end of file
@@ -0,0 +1,203 @@
+use std::io::{self, Error, ErrorKind};
+use std::ops;
+use crypto_bigint::U256;
+use crate::{algorithms::hash256::hash256, s256::{hash_to_curve::nums_h, s256_field::S256Field, s256_point::S256Point, scalr::Scalar}};
+
+// Pedersen commitment C = vH + rG to an amount v with blinding factor r.
+// Nobody knows log_G(H) (H is the BIP341 / secp256k1-zkp NUMS point), so C binds v,
+// and r hides it. Commitments add up like the amounts they hide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Commitment {
+    pub point: S256Point,
+}
+
+impl Commitment {
+    pub fn new(value: u64, blinding: Scalar) -> Self {
+        let point = nums_h().rmul(U256::from_u64(value)) + S256Point::g().rmul(blinding.num);
+        Self { point }
+    }
+
+    pub fn verify_opening(&self, value: u64, blinding: Scalar) -> bool {
+        Self::new(value, blinding) == *self
+    }
+
+    // Elements format: 0x08 when y is a quadratic residue, 0x09 when it is not, then x
+    pub fn serialize(&self) -> Vec<u8> {
+        let x = self.point.x().expect("cannot serialize the zero commitment");
+        let y = self.point.y().unwrap();
+
+        let mut res = Vec::with_capacity(33);
+        res.push(if y.is_square() { 0x08 } else { 0x09 });
+        res.extend_from_slice(&x.num.to_be_bytes());
+        res
+    }
+
+    pub fn parse(bytes: &[u8]) -> io::Result<Commitment> {
+        if bytes.len() != 33 || (bytes[0] != 0x08 && bytes[0] != 0x09) {
+            return Err(Error::new(ErrorKind::InvalidData, "Bad commitment prefix or length"));
+        }
+
+        let x = U256::from_be_slice(&bytes[1..]);
+        if x >= S256Field::new_zero().get_prime() {
+            return Err(Error::new(ErrorKind::InvalidData, "Commitment x out of range"));
+        }
+
+        let point = S256Point::lift_x(S256Field::new(x))
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Commitment x not on the curve"))?;
+
+        // -1 is not a square mod p, so exactly one of y and -y is
+        let y = point.y().unwrap();
+        let point = if y.is_square() == (bytes[0] == 0x08) { point } else { -point };
+        Ok(Commitment { point })
+    }
+}
+
+impl ops::Add for Commitment {
+    type Output = Commitment;
+
+    fn add(self, other: Commitment) -> Commitment {
+        Commitment { point: self.point + other.point }
+    }
+}
+
+impl ops::Sub for Commitment {
+    type Output = Commitment;
+
+    fn sub(self, other: Commitment) -> Commitment {
+        Commitment { point: self.point - other.point }
+    }
+}
+
+// sum(inputs) - sum(outputs) - fee*H, which is rG for the leftover blinding r if the amounts balance
+pub fn excess(inputs: &[Commitment], outputs: &[Commitment], fee: u64) -> S256Point {
+    let sum = |commitments: &[Commitment]| commitments.iter().fold(S256Point::new_inf(), |acc, c| acc + c.point);
+    sum(inputs) - sum(outputs) - nums_h().rmul(U256::from_u64(fee))
+}
+
+// Elements style: the last output blinding is picked so the excess is exactly zero
+pub fn verify_balance(inputs: &[Commitment], outputs: &[Commitment], fee: u64) -> bool {
+    excess(inputs, outputs, fee).is_inf()
+}
+
+// Blinding factor for the last output that makes sum(inputs) = sum(outputs) + fee*H
+pub fn balancing_blinding(inputs: &[Scalar], outputs: &[Scalar]) -> Scalar {
+    let zero = Scalar::new(U256::ZERO);
+    let sum_in = inputs.iter().fold(zero, |acc, &r| acc + r);
+    let sum_out = outputs.iter().fold(zero, |acc, &r| acc + r);
+    sum_in - sum_out
+}
+
+// Mimblewimble style: when the blindings do not cancel, a Schnorr signature with the
+// excess blinding as key shows the excess is rG alone, i.e. commits to a zero amount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BalanceProof {
+    pub nonce_point: S256Point,
+    pub s: Scalar,
+}
+
+impl BalanceProof {
+    pub fn sign(excess_blinding: Scalar, msg: &[u8]) -> Self {
+        // Deterministic nonce from the key and message
+        let mut preimage = excess_blinding.num.to_be_bytes().to_vec();
+        preimage.extend_from_slice(msg);
+        let k = to_scalar(&hash256(&preimage));
+
+        let nonce_point = S256Point::g().rmul(k.num);
+        let excess = S256Point::g().rmul(excess_blinding.num);
+        let e = challenge(nonce_point, excess, msg);
+
+        Self { nonce_point, s: k + e * excess_blinding }
+    }
+
+    // sG = R + eE
+    pub fn verify(&self, excess: S256Point, msg: &[u8]) -> bool {
+        if excess.is_inf() || self.nonce_point.is_inf() {
+            return false;
+        }
+        let e = challenge(self.nonce_point, excess, msg);
+        S256Point::g().rmul(self.s.num) == self.nonce_point + excess.rmul(e.num)
+    }
+}
+
+fn challenge(nonce_point: S256Point, excess: S256Point, msg: &[u8]) -> Scalar {
+    let mut preimage = nonce_point.sec(true);
+    preimage.extend_from_slice(&excess.sec(true));
+    preimage.extend_from_slice(msg);
+    to_scalar(&hash256(&preimage))
+}
+
+fn to_scalar(bytes: &[u8; 32]) -> Scalar {
+    Scalar::new(U256::from_be_slice(bytes) % S256Point::n())
+}
+
+#[cfg(test)]
+mod pedersen_tests {
+    use super::*;
+
+    // Small blinding factors keep the (debug build) scalar multiplications cheap
+    fn blinding(n: u64) -> Scalar {
+        Scalar::new(U256::from_u64(n))
+    }
+
+    #[test]
+    fn homomorphic() {
+        let c1 = Commitment::new(3, blinding(11));
+        let c2 = Commitment::new(4, blinding(22));
+        assert_eq!(c1 + c2, Commitment::new(7, blinding(33)));
+        assert_eq!((c1 + c2) - c2, c1);
+        assert!(c1.verify_opening(3, blinding(11)));
+        assert!(!c1.verify_opening(4, blinding(11)));
+    }
+
+    #[test]
+    fn serialize_roundtrip() -> Result<(), Box<dyn std::error::Error>> {
+        let mut prefixes = Vec::new();
+        for v in 1..=6u64 {
+            let c = Commitment::new(v, blinding(v * 7));
+            let bytes = c.serialize();
+            assert_eq!(bytes.len(), 33);
+            assert_eq!(Commitment::parse(&bytes)?, c);
+            prefixes.push(bytes[0]);
+        }
+        assert!(prefixes.contains(&0x08) && prefixes.contains(&0x09));
+
+        assert!(Commitment::parse(&[0x02; 33]).is_err());
+        assert!(Commitment::parse(&[0x08; 32]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn balance() {
+        let r_in = [blinding(101), blinding(202)];
+        let r_out0 = blinding(150);
+        let r_out1 = balancing_blinding(&r_in, &[r_out0]);
+
+        let inputs = [Commitment::new(10, r_in[0]), Commitment::new(5, r_in[1])];
+        let outputs = [Commitment::new(12, r_out0), Commitment::new(2, r_out1)];
+
+        assert!(verify_balance(&inputs, &outputs, 1));
+        assert!(!verify_balance(&inputs, &outputs, 2));
+
+        // Inflating an output breaks the balance even with matching blindings
+        let inflated = [Commitment::new(13, r_out0), Commitment::new(2, r_out1)];
+        assert!(!verify_balance(&inputs, &inflated, 1));
+    }
+
+    #[test]
+    fn balance_proof() {
+        let inputs = [Commitment::new(10, blinding(500))];
+        let outputs = [Commitment::new(9, blinding(123))];
+
+        let excess_point = excess(&inputs, &outputs, 1);
+        let excess_blinding = blinding(500) - blinding(123);
+        assert_eq!(excess_point, S256Point::g().rmul(excess_blinding.num));
+
+        let proof = BalanceProof::sign(excess_blinding, b"kernel");
+        assert!(proof.verify(excess_point, b"kernel"));
+        assert!(!proof.verify(excess_point, b"other"));
+
+        // An output that hides one more unit leaves H in the excess, which nobody can sign for
+        let cheating = excess(&inputs, &[Commitment::new(10, blinding(123))], 1);
+        assert!(!proof.verify(cheating, b"kernel"));
+    }
+}
@@ -1,4 +1,6 @@
 use crypto_bigint::{U256};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
 use crate::{algorithms::base58::base58_check, s256::{s256_field::S256Field, s256_point::S256Point, scalr::Scalar, signature::Signature}};
 
 pub struct PrivateKey {
@@ -15,25 +17,57 @@ impl PrivateKey {
         }
     }
 
-    pub fn sign(self, z: U256) -> Signature {
-        let k = U256::from_u128(1234567890u128); //TODO: U256::random(&mut OsRng);
+    pub fn sign(&self, z: U256) -> Signature {
+        self.sign_with_nonce(z, self.deterministic_k(z))
+    }
+
+    // Only for reproducing bad signers (tests, audits); real signing must go through sign()
+    pub fn sign_with_nonce(&self, z: U256, k: U256) -> Signature {
         let r = S256Point::g().rmul(k).x().unwrap().num;
 
         let k_inv = Scalar::new(k).inv();
-        println!("{}", hex::encode(k_inv.num.to_be_bytes()));
 
-        let z_scalar = Scalar::new(z);
-        let r_scalar = Scalar::new(r);
+        let z_scalar = Scalar::new(z % S256Point::n());
+        let r_scalar = Scalar::new(r % S256Point::n());
         let secret_scalar = Scalar::new(self.secret);
 
         // s = k_inv * (z + r * secret) mod N
-        let s_scalar = k_inv * (z_scalar + (r_scalar * secret_scalar));
+        let mut s = (k_inv * (z_scalar + (r_scalar * secret_scalar))).num;
 
-        let s = s_scalar.num;
+        // Low-s, as required by standardness rules
+        if s > S256Point::n() >> 1 {
+            s = S256Point::n() - s;
+        }
 
         Signature { r: S256Field::new(r), s: S256Field::new(s) }
     }
 
+    // RFC 6979 nonce: HMAC-SHA256 keyed by the secret and the message, so k never repeats across messages
+    pub fn deterministic_k(&self, z: U256) -> U256 {
+        let n = S256Point::n();
+        let z = if z >= n { z - n } else { z };
+        let secret_bytes = self.secret.to_be_bytes();
+        let z_bytes = z.to_be_bytes();
+
+        let mut k = [0u8; 32];
+        let mut v = [1u8; 32];
+
+        k = hmac_sha256(&k, &[&v[..], &[0x00], &secret_bytes, &z_bytes]);
+        v = hmac_sha256(&k, &[&v]);
+        k = hmac_sha256(&k, &[&v[..], &[0x01], &secret_bytes, &z_bytes]);
+        v = hmac_sha256(&k, &[&v]);
+
+        loop {
+            v = hmac_sha256(&k, &[&v]);
+            let candidate = U256::from_be_slice(&v);
+            if candidate >= U256::ONE && candidate < n {
+                return candidate;
+            }
+            k = hmac_sha256(&k, &[&v[..], &[0x00]]);
+            v = hmac_sha256(&k, &[&v]);
+        }
+    }
+
     pub fn wif(self, compressed: bool, testnet: bool) -> String {
         let bytes = self.secret.to_be_bytes();
         let mut res: Vec<u8> = Vec::new();
@@ -53,10 +87,28 @@ impl PrivateKey {
         base58_check(&res)
     }
 }
+fn hmac_sha256(key: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any size");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().into()
+}
+
 #[cfg(test)]
 mod private_keys_test {
+    use sha2::Digest;
+    use super::*;
 
     #[test]
     fn sign() {
+        // Same r and s as OpenSSL's deterministic (RFC 6979) signer after low-s normalization
+        let key = PrivateKey::new(U256::from_u32(12345u32));
+        let z = U256::from_be_slice(&Sha256::digest(b"Programming Bitcoin!"));
+        let sig = key.sign(z);
+        assert_eq!(sig.r.num, U256::from_be_hex("adb4c3f1ad863d1b4ba54a1d3fd498a9256cc194cac22580d6780d9772ffc405"));
+        assert_eq!(sig.s.num, U256::from_be_hex("34d984ca54094406e3337448db7e1213b6d24b465ca68b84585a8ac447dcdbed"));
+        assert!(key.point.verify(S256Field::new(z), sig));
+        assert_eq!(key.sign(z), sig);
     }
 }
\ No newline at end of file
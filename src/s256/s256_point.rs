@@ -1,155 +1,56 @@
-use std::ops;
-use crypto_bigint::{I256, U256};
-use crate::{algorithms::{base58::base58_check, hash160::hash160}, s256::{s256_field::S256Field, scalr::Scalar, signature::Signature}};
+use crypto_bigint::U256;
+use crate::{algorithms::{base58::base58_check, bech32::{convert_bits, encode, Variant}, hash160::hash160, tagged_hash::tagged_hash}, primitives::{ecdsa::EcdsaCurve, point::{Curve, Point}}, s256::{s256_field::S256Field, scalr::ScalarPrime, signature::Signature}};
 
-#[derive(PartialEq, Eq, Debug, Copy, Clone)]
-pub struct S256Point(Option<S256Field>, Option<S256Field>);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Secp256k1;
+impl Curve for Secp256k1 {
+    type Field = S256Field;
 
-impl S256Point {
-    pub fn new(x: Option<S256Field>, y: Option<S256Field>) -> Self {
-        if x == None || y == None {
-            assert_eq!(x, y, "For Point at Infinity, both points should be None");
-            Self(None, None)
-        } else {
-            let _x = x.unwrap();
-            let _y = y.unwrap();
-            let two = I256::from(2);
-            let three = I256::from(3);
-            assert_eq!(_y.pow(two), _x.pow(three) + Self::a() * _x + Self::b(), "({:?}, {:?}) is not on the curve", _x, _y);
-            Self(x, y)
-        }
+    fn a() -> S256Field {
+        S256Field::new(U256::ZERO)
     }
 
-    pub fn new_concrete(x: S256Field, y: S256Field) -> Self {
-        Self::new(Some(x), Some(y))
+    fn b() -> S256Field {
+        S256Field::new(U256::from_be_hex("0000000000000000000000000000000000000000000000000000000000000007"))
     }
+}
 
-    pub fn x(self) -> Option<S256Field> {
-        self.0
-    }
+impl EcdsaCurve for Secp256k1 {
+    type Order = ScalarPrime;
 
-    pub fn y(self) -> Option<S256Field> {
-        self.1
+    fn g() -> S256Point {
+        S256Point::new_concrete(
+            S256Field::new(U256::from_be_hex("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")),
+            S256Field::new(U256::from_be_hex("483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8"))
+        )
     }
+}
 
-    pub fn rmul(self, coefficeint: U256) -> Self {
-        let mut coef = coefficeint % Self::n();
-
-        let mut result = S256Point::new(None, None);
-        let mut current = self;
-
-        while coef > U256::ZERO {
-            if coef.bit(0).into() {
-                result = result + current;
-            }
-            current = current + current;
-            coef >>= 1;
-        }
+pub type S256Point = Point<Secp256k1>;
 
-        result
+impl Point<Secp256k1> {
+    pub fn rmul(self, coefficeint: U256) -> Self {
+        self.smul(coefficeint % Self::n())
     }
 
     pub fn g() -> Self {
-        S256Point(
-            Some(S256Field::new(U256::from_be_hex("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"))),
-            Some(S256Field::new(U256::from_be_hex("483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8")))
-        )
-    }
-
-    pub fn a() -> S256Field {
-        S256Field::new(U256::ZERO)
-    }
-
-    pub fn b() -> S256Field {
-        S256Field::new(U256::from_be_hex("0000000000000000000000000000000000000000000000000000000000000007"))
+        Secp256k1::g()
     }
 
     pub fn n() -> U256 {
-        U256::from_be_hex(
-            "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141"
-        )
-    }
-
-    pub fn verify(self, z: S256Field, sig: Signature) -> bool {
-        let s_inv = Scalar::new(sig.s.num).inv();
-        let u = Scalar::new(z.num)* s_inv;
-        let v = Scalar::new(sig.r.num) * s_inv;
-
-        let total = Self::g().rmul(u.num) + self.rmul(v.num);
-
-        let x = total.0.unwrap().num;
-        let x_mod_n = x % Self::n();
-
-        x_mod_n == sig.r.num
+        Secp256k1::n()
     }
 
-    pub fn sec(self, compressed: bool) -> Vec<u8> {
-        let x_bytes = self.x().unwrap().num.to_be_bytes();
-        let y_bytes = self.y().unwrap().num.to_be_bytes();
-
-        let mut result;
-        if compressed {
-            result = Vec::with_capacity(33);
-            if self.y().unwrap().num % S256Field::new_two().num == U256::ZERO {
-                result.push(0x02);
-            } else {
-                result.push(0x03);
-            }
-            result.extend_from_slice(&x_bytes);
-        } else {
-            result = Vec::with_capacity(65);
-            result.push(0x04);
-            result.extend_from_slice(&x_bytes);
-            result.extend_from_slice(&y_bytes);
-        }
-
-        result
+    // BIP340 lift_x: the point with this x and an even y, if there is one
+    pub fn lift_x(x: S256Field) -> Option<Self> {
+        let alpha = x * x * x + Secp256k1::b();
+        let beta = alpha.sqrt()?;
+        let y = if bool::from(beta.num.bit(0)) { -beta } else { beta };
+        Some(Self::new_concrete(x, y))
     }
 
-    pub fn parse(sec: &[u8]) -> Self {
-        assert!(!sec.is_empty(), "SEC data empty");
-
-        // Uncompressed: 04 || x || y
-        if sec[0] == 0x04 {
-            assert!(sec.len() == 65, "Invalid uncompressed SEC length");
-
-            let x = U256::from_be_slice(&sec[1..33]);
-            let y = U256::from_be_slice(&sec[33..65]);
-
-            return S256Point::new(Some(S256Field::new(x)), 
-                                  Some(S256Field::new(y)));
-        }
-
-        // Compressed: 02 || x  OR  03 || x
-        assert!(sec.len() == 33, "Invalid compressed SEC length");
-
-        let is_even = sec[0] == 0x02;
-
-        let x = S256Field::new(U256::from_be_slice(&sec[1..33]));
-
-        // y^2 = x^3 + 7
-        let alpha = x.pow(I256::from(3)) + S256Field::new(U256::from(7u8));
-
-        let beta = alpha.sqrt();
-
-        let even_beta;
-        let odd_beta;
-
-        let p = x.get_prime();
-
-        if bool::from(beta.num % S256Field::new_two().num == U256::ZERO) {
-            even_beta = beta;
-            odd_beta = S256Field::new(p - beta.num);
-        } else {
-            even_beta = S256Field::new(p - beta.num);
-            odd_beta = beta;
-        }
-
-        if is_even {
-            S256Point::new(Some(x), Some(even_beta))
-        } else {
-            S256Point::new(Some(x), Some(odd_beta))
-        }
+    pub fn verify(self, z: S256Field, sig: Signature) -> bool {
+        self.verify_ecdsa(z.num, sig.r.num, sig.s.num)
     }
 
     pub fn address(self, compressed: bool, testnet: bool) -> String {
@@ -165,53 +66,42 @@ impl S256Point {
         base58_check(&payload)
     }
 
-}
+    // BIP49 nested segwit: P2SH of the redeem script 0 <hash160(pubkey)>
+    pub fn p2sh_p2wpkh_address(self, testnet: bool) -> String {
+        let mut redeem_script = vec![0x00, 0x14];
+        redeem_script.extend_from_slice(&hash160(&self.sec(true)));
 
-impl ops::Add for S256Point {
-    type Output = S256Point;
-
-    fn add(self, other: S256Point) -> S256Point {
-        let zero = S256Field::new_zero();
-        if self.0 == None {
-            return other;
-        } else if other.0 == None {
-            return self;
-        } else if self.0 == other.0 && self.1 != other.1 {
-            return S256Point::new(None, None);
-        } else if self.0 != other.0 {
-            let x1 = self.0.unwrap();
-            let y1 = self.1.unwrap();
-            let x2 = other.0.unwrap();
-            let y2 = other.1.unwrap();
-
-            let s = (y2 - y1) * (x2 - x1).inv();
-            
-            let x3 = (s * s) - x1 - x2;
-            let y3 =  s * (x1 - x3) - y1;
-
-            S256Point::new(Some(x3), Some(y3))
-        } else if self == other && self.1 == Some(zero) {
-            return S256Point::new(None, None);
-        } else if self == other && self.1 != Some(zero) {
-            let x = self.0.unwrap();
-            let y = self.1.unwrap();
-            let a = Self::a();
-
-            let two= S256Field::new_two();
-            let three= S256Field::new_three();
-
-            let s = (three * x * x + a) * (two * y).inv();
-            
-            let x3 = (s * s) - (two * x);
-            let y3 = (s * (x - x3)) - y;
-
-            S256Point::new(Some(x3), Some(y3))
-        } else {
-            // Should never come here.
-            self
-        }
+        let mut payload = vec![if testnet { 0xc4 } else { 0x05 }];
+        payload.extend_from_slice(&hash160(&redeem_script));
+        base58_check(&payload)
     }
 
+    // BIP84 native segwit v0
+    pub fn p2wpkh_address(self, testnet: bool) -> String {
+        segwit_address(0, &hash160(&self.sec(true)), testnet)
+    }
+
+    // BIP86 key-path-only taproot: Q = P + hash_TapTweak(x(P))G, P taken with even y
+    pub fn tap_tweak(self) -> Self {
+        let x = self.x().unwrap();
+        let internal = Self::lift_x(x).unwrap();
+        let tweak = U256::from_be_slice(&tagged_hash("TapTweak", &x.num.to_be_bytes()));
+        assert!(tweak < Self::n(), "Tweak out of range");
+        internal + Self::g().rmul(tweak)
+    }
+
+    pub fn p2tr_address(self, testnet: bool) -> String {
+        segwit_address(1, &self.tap_tweak().x().unwrap().num.to_be_bytes(), testnet)
+    }
+
+}
+
+// BIP173/BIP350: version 0 uses bech32, later versions bech32m
+fn segwit_address(version: u8, program: &[u8], testnet: bool) -> String {
+    let mut data = vec![version];
+    data.extend(convert_bits(program, 8, 5, true).unwrap());
+    let variant = if version == 0 { Variant::Bech32 } else { Variant::Bech32m };
+    encode(if testnet { "tb" } else { "bc" }, &data, variant)
 }
 
 #[cfg(test)]
@@ -220,14 +110,36 @@ mod s256_point_tests_temp {
 
     #[test]
     fn g_order() {
-        let g = S256Point(
-            Some(S256Field::new(U256::from_be_hex("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"))),
-            Some(S256Field::new(U256::from_be_hex("483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8")))
+        let g = S256Point::new_concrete(
+            S256Field::new(U256::from_be_hex("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")),
+            S256Field::new(U256::from_be_hex("483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8"))
         );
 
-        let res = g.rmul(S256Point::n());
-        assert_eq!(res.0, None);
-        assert_eq!(res.1, None);
+        let res = g.smul(S256Point::n());
+        assert_eq!(res.x(), None);
+        assert_eq!(res.y(), None);
+    }
+
+    #[test]
+    fn parse_compressed() {
+        let g = S256Point::g();
+        assert_eq!(S256Point::parse(&g.sec(true)).unwrap(), g);
+        assert_eq!(S256Point::parse(&g.sec(false)).unwrap(), g);
+    }
+
+    #[test]
+    fn parse_invalid() {
+        // x = 5 gives x^3 + 7 = 132, which is not a square mod p
+        let mut sec = vec![0x02];
+        sec.extend_from_slice(&U256::from(5u8).to_be_bytes());
+        assert!(S256Point::parse(&sec).is_err());
+
+        let mut off_curve = S256Point::g().sec(false);
+        off_curve[64] ^= 1;
+        assert!(S256Point::parse(&off_curve).is_err());
+        assert!(S256Point::parse(&S256Point::g().sec(false)[..64]).is_err());
+        assert!(S256Point::parse(&[]).is_err());
+        assert!(S256Point::parse(&[0x05; 33]).is_err());
     }
 
     #[test]
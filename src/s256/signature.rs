@@ -1,6 +1,6 @@
-use crypto_bigint::U256;
+use std::io;
 
-use crate::s256::s256_field::S256Field;
+use crate::{algorithms::der::{encode_signature, parse_signature}, s256::s256_field::S256Field};
 
 #[derive(PartialEq, Eq, Debug, Copy, Clone)]
 pub struct Signature {
@@ -10,47 +10,16 @@ pub struct Signature {
 
 impl Signature {
 
-    fn encode_int(x: U256) -> Vec<u8> {
-        let mut bytes = x.to_be_bytes().to_vec();
-
-        // Remove leading zeros
-        while bytes.len() > 1 && bytes[0] == 0 {
-            bytes.remove(0);
-        }
-
-        // If high bit is set, prepend 0x00
-        if bytes[0] & 0x80 != 0 {
-            let mut prefixed = vec![0x00];
-            prefixed.extend_from_slice(&bytes);
-            bytes = prefixed;
-        }
-
-        bytes
-    }
-
     pub fn der(&self) -> Vec<u8> {
+        encode_signature(self.r.num, self.s.num)
+    }
 
-        let rbin = Self::encode_int(self.r.num);
-        let sbin = Self::encode_int(self.s.num);
-
-        let mut result = Vec::with_capacity(72);
-
-        // INTEGER r
-        result.push(0x02);
-        result.push(rbin.len() as u8);
-        result.extend_from_slice(&rbin);
-
-        // INTEGER s
-        result.push(0x02);
-        result.push(sbin.len() as u8);
-        result.extend_from_slice(&sbin);
-
-        // Wrap in SEQUENCE
-        let mut der = Vec::with_capacity(result.len() + 2);
-        der.push(0x30);
-        der.push(result.len() as u8);
-        der.extend_from_slice(&result);
-
-        der
+    pub fn parse(der: &[u8]) -> io::Result<Signature> {
+        let (r, s) = parse_signature(der)?;
+        let p = S256Field::new_zero().get_prime();
+        if r >= p || s >= p {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Signature value out of range"));
+        }
+        Ok(Signature { r: S256Field::new(r), s: S256Field::new(s) })
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,185 @@
+use std::io::{self, Error, ErrorKind};
+use crate::{bitcoin::tx::tx::Network, wallet::bip32::{ExtendedPrivKey, ExtendedPubKey, HARDENED}};
+
+// Single-signature account layouts m/purpose'/coin_type'/account'/change/index
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Purpose {
+    // Legacy P2PKH
+    Bip44,
+    // P2WPKH nested in P2SH
+    Bip49,
+    // Native P2WPKH
+    Bip84,
+    // Key-path-only P2TR
+    Bip86,
+}
+
+impl Purpose {
+    pub fn number(self) -> u32 {
+        match self {
+            Purpose::Bip44 => 44,
+            Purpose::Bip49 => 49,
+            Purpose::Bip84 => 84,
+            Purpose::Bip86 => 86,
+        }
+    }
+
+    // SLIP-132: ypub/zpub (upub/vpub on testnet) mark the script type; BIP44 and BIP86 keep xpub/tpub
+    pub fn xpub_version(self, network: Network) -> [u8; 4] {
+        match (self, network) {
+            (Purpose::Bip49, Network::MAINNET) => [0x04, 0x9d, 0x7c, 0xb2],
+            (Purpose::Bip49, Network::TESTNET) => [0x04, 0x4a, 0x52, 0x62],
+            (Purpose::Bip84, Network::MAINNET) => [0x04, 0xb2, 0x47, 0x46],
+            (Purpose::Bip84, Network::TESTNET) => [0x04, 0x5f, 0x1c, 0xf6],
+            (_, Network::MAINNET) => [0x04, 0x88, 0xb2, 0x1e],
+            (_, Network::TESTNET) => [0x04, 0x35, 0x87, 0xcf],
+        }
+    }
+
+    pub fn xprv_version(self, network: Network) -> [u8; 4] {
+        match (self, network) {
+            (Purpose::Bip49, Network::MAINNET) => [0x04, 0x9d, 0x78, 0x78],
+            (Purpose::Bip49, Network::TESTNET) => [0x04, 0x4a, 0x4e, 0x28],
+            (Purpose::Bip84, Network::MAINNET) => [0x04, 0xb2, 0x43, 0x0c],
+            (Purpose::Bip84, Network::TESTNET) => [0x04, 0x5f, 0x18, 0xbc],
+            (_, Network::MAINNET) => [0x04, 0x88, 0xad, 0xe4],
+            (_, Network::TESTNET) => [0x04, 0x35, 0x83, 0x94],
+        }
+    }
+
+    pub fn address(self, key: &ExtendedPubKey) -> String {
+        let testnet = matches!(key.network, Network::TESTNET);
+        match self {
+            Purpose::Bip44 => key.point.address(true, testnet),
+            Purpose::Bip49 => key.point.p2sh_p2wpkh_address(testnet),
+            Purpose::Bip84 => key.point.p2wpkh_address(testnet),
+            Purpose::Bip86 => key.point.p2tr_address(testnet),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Account {
+    pub purpose: Purpose,
+    pub xprv: ExtendedPrivKey,
+    pub xpub: ExtendedPubKey,
+}
+
+impl Account {
+    // Coin type 0 on mainnet, 1 on every test network
+    pub fn from_seed(seed: &[u8], purpose: Purpose, network: Network, account: u32) -> io::Result<Self> {
+        if account >= HARDENED {
+            return Err(Error::new(ErrorKind::InvalidInput, "Account index must be below 2^31"));
+        }
+        let coin_type = match network {
+            Network::MAINNET => 0,
+            Network::TESTNET => 1,
+        };
+
+        let xprv = ExtendedPrivKey::new_master(seed, network)
+            .derive_child(purpose.number() + HARDENED)?
+            .derive_child(coin_type + HARDENED)?
+            .derive_child(account + HARDENED)?;
+
+        Ok(Self { purpose, xprv, xpub: xprv.to_public() })
+    }
+
+    // Account-level keys with SLIP-132 version bytes, as hardware wallets export them
+    pub fn xpub(&self) -> String {
+        self.xpub.serialize_with_version(self.purpose.xpub_version(self.xpub.network))
+    }
+
+    pub fn xprv(&self) -> String {
+        self.xprv.serialize_with_version(self.purpose.xprv_version(self.xprv.network))
+    }
+
+    // Derived from the account xpub, so watch-only copies produce the same addresses
+    pub fn address(&self, change: bool, index: u32) -> io::Result<String> {
+        let key = self.xpub.derive_child(change as u32)?.derive_child(index)?;
+        Ok(self.purpose.address(&key))
+    }
+
+    pub fn receive_address(&self, index: u32) -> io::Result<String> {
+        self.address(false, index)
+    }
+
+    pub fn change_address(&self, index: u32) -> io::Result<String> {
+        self.address(true, index)
+    }
+}
+
+#[cfg(test)]
+mod account_tests {
+    use crate::s256::s256_point::S256Point;
+    use super::*;
+
+    // Seed of "abandon abandon ... about", the mnemonic used by the BIP49/84/86 vectors
+    fn seed() -> Vec<u8> {
+        hex::decode("5eb00bbddcf069084889a8ab9155568165f5c453ccb85e70811aaed6f6da5fc19a5ac40b389cd370d086206dec8aa6c43daea6690f20ad3d8d48b2d2ce9e38e4").unwrap()
+    }
+
+    #[test]
+    fn bip44() -> Result<(), Box<dyn std::error::Error>> {
+        let account = Account::from_seed(&seed(), Purpose::Bip44, Network::MAINNET, 0)?;
+        assert_eq!(account.xpub(), "xpub6BosfCnifzxcFwrSzQiqu2DBVTshkCXacvNsWGYJVVhhawA7d4R5WSWGFNbi8Aw6ZRc1brxMyWMzG3DSSSSoekkudhUd9yLb6qx39T9nMdj");
+        assert_eq!(account.receive_address(0)?, "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA");
+        Ok(())
+    }
+
+    #[test]
+    fn bip49() -> Result<(), Box<dyn std::error::Error>> {
+        let account = Account::from_seed(&seed(), Purpose::Bip49, Network::MAINNET, 0)?;
+        assert_eq!(account.xpub(), "ypub6Ww3ibxVfGzLrAH1PNcjyAWenMTbbAosGNB6VvmSEgytSER9azLDWCxoJwW7Ke7icmizBMXrzBx9979FfaHxHcrArf3zbeJJJUZPf663zsP");
+        assert_eq!(account.receive_address(0)?, "37VucYSaXLCAsxYyAPfbSi9eh4iEcbShgf");
+
+        // The BIP49 reference vector itself is on testnet
+        let account = Account::from_seed(&seed(), Purpose::Bip49, Network::TESTNET, 0)?;
+        assert_eq!(account.xpub(), "upub5EFU65HtV5TeiSHmZZm7FUffBGy8UKeqp7vw43jYbvZPpoVsgU93oac7Wk3u6moKegAEWtGNF8DehrnHtv21XXEMYRUocHqguyjknFHYfgY");
+        assert_eq!(account.xprv(), "uprv91G7gZkzehuMVxDJTYE6tLivdF8e4rvzSu1LFfKw3b2Qx1Aj8vpoFnHdfUZ3hmi9jsvPifmZ24RTN2KhwB8BfMLTVqaBReibyaFFcTP1s9n");
+        assert_eq!(account.receive_address(0)?, "2Mww8dCYPUpKHofjgcXcBCEGmniw9CoaiD2");
+        Ok(())
+    }
+
+    #[test]
+    fn bip84() -> Result<(), Box<dyn std::error::Error>> {
+        let account = Account::from_seed(&seed(), Purpose::Bip84, Network::MAINNET, 0)?;
+        assert_eq!(account.xpub(), "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs");
+        assert_eq!(account.xprv(), "zprvAdG4iTXWBoARxkkzNpNh8r6Qag3irQB8PzEMkAFeTRXxHpbF9z4QgEvBRmfvqWvGp42t42nvgGpNgYSJA9iefm1yYNZKEm7z6qUWCroSQnE");
+        assert_eq!(account.receive_address(0)?, "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+        assert_eq!(account.change_address(0)?, "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el");
+        Ok(())
+    }
+
+    #[test]
+    fn bip86() -> Result<(), Box<dyn std::error::Error>> {
+        let account = Account::from_seed(&seed(), Purpose::Bip86, Network::MAINNET, 0)?;
+        assert_eq!(account.xpub(), "xpub6BgBgsespWvERF3LHQu6CnqdvfEvtMcQjYrcRzx53QJjSxarj2afYWcLteoGVky7D3UKDP9QyrLprQ3VCECoY49yfdDEHGCtMMj92pReUsQ");
+        assert_eq!(account.receive_address(0)?, "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr");
+        assert!(Account::from_seed(&seed(), Purpose::Bip86, Network::MAINNET, HARDENED).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn address_types() -> Result<(), Box<dyn std::error::Error>> {
+        // First receive keys of the BIP44, BIP49 (mainnet and testnet) and BIP84 testnet accounts of seed()
+        let key = |sec: &str| S256Point::parse(&hex::decode(sec).unwrap());
+
+        assert_eq!(key("03aaeb52dd7494c361049de67cc680e83ebcbbbdbeb13637d92cd845f70308af5e")?.address(true, false), "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA");
+        assert_eq!(key("039b3b694b8fc5b5e07fb069c783cac754f5d38c3e08bed1960e31fdb1dda35c24")?.p2sh_p2wpkh_address(false), "37VucYSaXLCAsxYyAPfbSi9eh4iEcbShgf");
+        assert_eq!(key("03a1af804ac108a8a51782198c2d034b28bf90c8803f5a53f76276fa69a4eae77f")?.p2sh_p2wpkh_address(true), "2Mww8dCYPUpKHofjgcXcBCEGmniw9CoaiD2");
+        assert_eq!(key("02e7ab2537b5d49e970309aae06e9e49f36ce1c9febbd44ec8e0d1cca0b4f9c319")?.p2wpkh_address(true), "tb1q6rz28mcfaxtmd6v789l9rrlrusdprr9pqcpvkl");
+        Ok(())
+    }
+
+    #[test]
+    fn slip132_versions() -> Result<(), Box<dyn std::error::Error>> {
+        let bip49 = ExtendedPubKey::parse("xpub6C6nQwHaWbSrzs5tZ1q7m5R9cPK9eYpNMFesiXsYrgc1P8bvLLAet9JfHjYXKjToD8cBRswJXXbbFpXgwsswVPAZzKMa1jUp2kVkGVUaJa7")?;
+        assert_eq!(bip49.serialize_with_version(Purpose::Bip49.xpub_version(Network::MAINNET)), "ypub6Ww3ibxVfGzLrAH1PNcjyAWenMTbbAosGNB6VvmSEgytSER9azLDWCxoJwW7Ke7icmizBMXrzBx9979FfaHxHcrArf3zbeJJJUZPf663zsP");
+
+        let bip84 = ExtendedPubKey::parse("tpubDC8msFGeGuwnKG9Upg7DM2b4DaRqg3CUZa5g8v2SRQ6K4NSkxUgd7HsL2XVWbVm39yBA4LAxysQAm397zwQSQoQgewGiYZqrA9DsP4zbQ1M")?;
+        assert_eq!(bip84.serialize_with_version(Purpose::Bip84.xpub_version(Network::TESTNET)), "vpub5Y6cjg78GGuNLsaPhmYsiw4gYX3HoQiRBiSwDaBXKUafCt9bNwWQiitDk5VZ5BVxYnQdwoTyXSs2JHRPAgjAvtbBrf8ZhDYe2jWAqvZVnsc");
+        assert_eq!(Purpose::Bip86.xpub_version(Network::MAINNET), Purpose::Bip44.xpub_version(Network::MAINNET));
+        Ok(())
+    }
+}
@@ -0,0 +1,362 @@
+use std::io::{self, Error, ErrorKind};
+use crypto_bigint::U256;
+use crate::{algorithms::{base58::{base58_check, decode_base58_check}, hash160::hash160, hmac_sha512::hmac_sha512}, bitcoin::tx::tx::Network, s256::{s256_point::S256Point, scalr::Scalar}};
+
+// BIP32 hierarchical deterministic keys
+
+pub const HARDENED: u32 = 0x80000000;
+
+const XPRV: [u8; 4] = [0x04, 0x88, 0xad, 0xe4];
+const XPUB: [u8; 4] = [0x04, 0x88, 0xb2, 0x1e];
+const TPRV: [u8; 4] = [0x04, 0x35, 0x83, 0x94];
+const TPUB: [u8; 4] = [0x04, 0x35, 0x87, 0xcf];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExtendedPrivKey {
+    pub network: Network,
+    pub depth: u8,
+    pub parent_fingerprint: [u8; 4],
+    pub child_number: u32,
+    pub chain_code: [u8; 32],
+    pub secret: U256,
+    // Cached secret * G, needed for fingerprints and non-hardened children
+    pub point: S256Point,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExtendedPubKey {
+    pub network: Network,
+    pub depth: u8,
+    pub parent_fingerprint: [u8; 4],
+    pub child_number: u32,
+    pub chain_code: [u8; 32],
+    pub point: S256Point,
+}
+
+impl ExtendedPrivKey {
+    pub fn new_master(seed: &[u8], network: Network) -> Self {
+        let i = hmac_sha512(b"Bitcoin seed", seed);
+        let secret = U256::from_be_slice(&i[..32]);
+        assert!(secret != U256::ZERO && secret < S256Point::n(), "Invalid master key, use another seed");
+
+        Self {
+            network,
+            depth: 0,
+            parent_fingerprint: [0u8; 4],
+            child_number: 0,
+            chain_code: i[32..].try_into().unwrap(),
+            secret,
+            point: S256Point::g().rmul(secret),
+        }
+    }
+
+    // index >= HARDENED gives a hardened child
+    pub fn derive_child(&self, index: u32) -> io::Result<Self> {
+        let depth = child_depth(self.depth)?;
+        let (secret, chain_code) = ckd_priv(self.secret, &self.chain_code, index, || self.point.sec(true));
+
+        Ok(Self {
+            network: self.network,
+            depth,
+            parent_fingerprint: self.fingerprint(),
+            child_number: index,
+            chain_code,
+            secret,
+            point: S256Point::g().rmul(secret),
+        })
+    }
+
+    pub fn derive_path(&self, path: &str) -> io::Result<Self> {
+        parse_path(path)?.into_iter().try_fold(*self, |key, index| key.derive_child(index))
+    }
+
+    // Secret at a fully hardened path, without the public key derive_path computes at every level
+    pub fn derive_secret(&self, path: &str) -> io::Result<U256> {
+        let indexes = parse_path(path)?;
+        if indexes.iter().any(|&index| index < HARDENED) {
+            return Err(Error::new(ErrorKind::InvalidInput, "derive_secret only takes hardened paths"));
+        }
+        let (secret, _) = indexes.into_iter().fold((self.secret, self.chain_code), |(secret, chain_code), index| {
+            ckd_priv(secret, &chain_code, index, || unreachable!())
+        });
+        Ok(secret)
+    }
+
+    pub fn fingerprint(&self) -> [u8; 4] {
+        fingerprint(self.point)
+    }
+
+    pub fn to_public(&self) -> ExtendedPubKey {
+        ExtendedPubKey {
+            network: self.network,
+            depth: self.depth,
+            parent_fingerprint: self.parent_fingerprint,
+            child_number: self.child_number,
+            chain_code: self.chain_code,
+            point: self.point,
+        }
+    }
+
+    // xprv / tprv
+    pub fn serialize(&self) -> String {
+        let version = match self.network {
+            Network::MAINNET => XPRV,
+            Network::TESTNET => TPRV,
+        };
+        self.serialize_with_version(version)
+    }
+
+    pub fn serialize_with_version(&self, version: [u8; 4]) -> String {
+        let mut key = vec![0x00];
+        key.extend_from_slice(&self.secret.to_be_bytes());
+        base58_check(&serialize_fields(version, self.depth, self.parent_fingerprint, self.child_number, &self.chain_code, &key))
+    }
+
+    pub fn parse(s: &str) -> io::Result<Self> {
+        let (version, depth, parent_fingerprint, child_number, chain_code, key) = parse_fields(s)?;
+        let network = match version {
+            XPRV => Network::MAINNET,
+            TPRV => Network::TESTNET,
+            _ => return Err(Error::new(ErrorKind::InvalidData, "Unknown extended private key version")),
+        };
+
+        let secret = U256::from_be_slice(&key[1..]);
+        if key[0] != 0x00 || secret == U256::ZERO || secret >= S256Point::n() {
+            return Err(Error::new(ErrorKind::InvalidData, "Invalid extended private key data"));
+        }
+
+        Ok(Self { network, depth, parent_fingerprint, child_number, chain_code, secret, point: S256Point::g().rmul(secret) })
+    }
+}
+
+impl ExtendedPubKey {
+    // Non-hardened only: hardened children need the private key
+    pub fn derive_child(&self, index: u32) -> io::Result<Self> {
+        if index >= HARDENED {
+            return Err(Error::new(ErrorKind::InvalidInput, "Hardened derivation needs the private key"));
+        }
+        let depth = child_depth(self.depth)?;
+
+        let mut data = self.point.sec(true);
+        data.extend_from_slice(&index.to_be_bytes());
+
+        let i = hmac_sha512(&self.chain_code, &data);
+        let tweak = U256::from_be_slice(&i[..32]);
+        assert!(tweak < S256Point::n(), "Invalid child key at index {}, use the next one", index);
+
+        let point = S256Point::g().rmul(tweak) + self.point;
+        assert!(!point.is_inf(), "Invalid child key at index {}, use the next one", index);
+
+        Ok(Self {
+            network: self.network,
+            depth,
+            parent_fingerprint: self.fingerprint(),
+            child_number: index,
+            chain_code: i[32..].try_into().unwrap(),
+            point,
+        })
+    }
+
+    pub fn derive_path(&self, path: &str) -> io::Result<Self> {
+        parse_path(path)?.into_iter().try_fold(*self, |key, index| key.derive_child(index))
+    }
+
+    pub fn fingerprint(&self) -> [u8; 4] {
+        fingerprint(self.point)
+    }
+
+    // xpub / tpub
+    pub fn serialize(&self) -> String {
+        let version = match self.network {
+            Network::MAINNET => XPUB,
+            Network::TESTNET => TPUB,
+        };
+        self.serialize_with_version(version)
+    }
+
+    pub fn serialize_with_version(&self, version: [u8; 4]) -> String {
+        base58_check(&serialize_fields(version, self.depth, self.parent_fingerprint, self.child_number, &self.chain_code, &self.point.sec(true)))
+    }
+
+    pub fn parse(s: &str) -> io::Result<Self> {
+        let (version, depth, parent_fingerprint, child_number, chain_code, key) = parse_fields(s)?;
+        let network = match version {
+            XPUB => Network::MAINNET,
+            TPUB => Network::TESTNET,
+            _ => return Err(Error::new(ErrorKind::InvalidData, "Unknown extended public key version")),
+        };
+
+        Ok(Self { network, depth, parent_fingerprint, child_number, chain_code, point: S256Point::parse(&key)? })
+    }
+}
+
+// "m/84'/0'/0'/0/5"; ', h and H all mark hardened steps
+pub fn parse_path(path: &str) -> io::Result<Vec<u32>> {
+    let invalid = |msg: String| Error::new(ErrorKind::InvalidInput, msg);
+
+    let mut parts = path.split('/');
+    if parts.next() != Some("m") {
+        return Err(invalid(format!("Path {:?} must start with m", path)));
+    }
+
+    parts.map(|part| {
+        let (digits, hardened) = match part.strip_suffix(['\'', 'h', 'H']) {
+            Some(digits) => (digits, true),
+            None => (part, false),
+        };
+        let index: u32 = digits.parse()
+            .ok()
+            .filter(|&index| index < HARDENED && digits.bytes().all(|b| b.is_ascii_digit()))
+            .ok_or_else(|| invalid(format!("Invalid path component {:?}", part)))?;
+        Ok(if hardened { index + HARDENED } else { index })
+    }).collect()
+}
+
+// The depth byte caps a chain at 255 derivations
+fn child_depth(depth: u8) -> io::Result<u8> {
+    depth.checked_add(1).ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Extended key is already at the maximum depth"))
+}
+
+// CKDpriv on a bare secret and chain code; public_key is only called for non-hardened indexes
+fn ckd_priv(secret: U256, chain_code: &[u8; 32], index: u32, public_key: impl FnOnce() -> Vec<u8>) -> (U256, [u8; 32]) {
+    let mut data = Vec::with_capacity(37);
+    if index >= HARDENED {
+        data.push(0x00);
+        data.extend_from_slice(&secret.to_be_bytes());
+    } else {
+        data.extend_from_slice(&public_key());
+    }
+    data.extend_from_slice(&index.to_be_bytes());
+
+    let i = hmac_sha512(chain_code, &data);
+    let tweak = U256::from_be_slice(&i[..32]);
+    // Probability below 2^-127; BIP32 says to skip to the next index
+    assert!(tweak < S256Point::n(), "Invalid child key at index {}, use the next one", index);
+
+    let child = (Scalar::new(tweak) + Scalar::new(secret)).num;
+    assert!(child != U256::ZERO, "Invalid child key at index {}, use the next one", index);
+
+    (child, i[32..].try_into().unwrap())
+}
+
+// First 4 bytes of hash160 of the compressed public key
+pub fn fingerprint(point: S256Point) -> [u8; 4] {
+    hash160(&point.sec(true))[..4].try_into().unwrap()
+}
+
+fn serialize_fields(version: [u8; 4], depth: u8, parent_fingerprint: [u8; 4], child_number: u32, chain_code: &[u8; 32], key: &[u8]) -> Vec<u8> {
+    let mut res = Vec::with_capacity(78);
+    res.extend_from_slice(&version);
+    res.push(depth);
+    res.extend_from_slice(&parent_fingerprint);
+    res.extend_from_slice(&child_number.to_be_bytes());
+    res.extend_from_slice(chain_code);
+    res.extend_from_slice(key);
+    res
+}
+
+type Fields = ([u8; 4], u8, [u8; 4], u32, [u8; 32], Vec<u8>);
+
+fn parse_fields(s: &str) -> io::Result<Fields> {
+    let data = decode_base58_check(s)?;
+    if data.len() != 78 {
+        return Err(Error::new(ErrorKind::InvalidData, "Extended key must be 78 bytes"));
+    }
+
+    let depth = data[4];
+    let parent_fingerprint: [u8; 4] = data[5..9].try_into().unwrap();
+    let child_number = u32::from_be_bytes(data[9..13].try_into().unwrap());
+    if depth == 0 && (parent_fingerprint != [0u8; 4] || child_number != 0) {
+        return Err(Error::new(ErrorKind::InvalidData, "Master key with a parent"));
+    }
+
+    Ok((data[..4].try_into().unwrap(), depth, parent_fingerprint, child_number, data[13..45].try_into().unwrap(), data[45..].to_vec()))
+}
+
+#[cfg(test)]
+mod bip32_tests {
+    use super::*;
+
+    #[test]
+    fn vector_1() -> Result<(), Box<dyn std::error::Error>> {
+        let master = ExtendedPrivKey::new_master(&hex::decode("000102030405060708090a0b0c0d0e0f")?, Network::MAINNET);
+        assert_eq!(hex::encode(master.fingerprint()), "3442193e");
+
+        // Walk m/0h/1/2h/2/1000000000 checking every level
+        let expected = [
+            (HARDENED, "xprv9uHRZZhk6KAJC1avXpDAp4MDc3sQKNxDiPvvkX8Br5ngLNv1TxvUxt4cV1rGL5hj6KCesnDYUhd7oWgT11eZG7XnxHrnYeSvkzY7d2bhkJ7", "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw"),
+            (1, "xprv9wTYmMFdV23N2TdNG573QoEsfRrWKQgWeibmLntzniatZvR9BmLnvSxqu53Kw1UmYPxLgboyZQaXwTCg8MSY3H2EU4pWcQDnRnrVA1xe8fs", "xpub6ASuArnXKPbfEwhqN6e3mwBcDTgzisQN1wXN9BJcM47sSikHjJf3UFHKkNAWbWMiGj7Wf5uMash7SyYq527Hqck2AxYysAA7xmALppuCkwQ"),
+            (2 + HARDENED, "xprv9z4pot5VBttmtdRTWfWQmoH1taj2axGVzFqSb8C9xaxKymcFzXBDptWmT7FwuEzG3ryjH4ktypQSAewRiNMjANTtpgP4mLTj34bhnZX7UiM", "xpub6D4BDPcP2GT577Vvch3R8wDkScZWzQzMMUm3PWbmWvVJrZwQY4VUNgqFJPMM3No2dFDFGTsxxpG5uJh7n7epu4trkrX7x7DogT5Uv6fcLW5"),
+            (2, "xprvA2JDeKCSNNZky6uBCviVfJSKyQ1mDYahRjijr5idH2WwLsEd4Hsb2Tyh8RfQMuPh7f7RtyzTtdrbdqqsunu5Mm3wDvUAKRHSC34sJ7in334", "xpub6FHa3pjLCk84BayeJxFW2SP4XRrFd1JYnxeLeU8EqN3vDfZmbqBqaGJAyiLjTAwm6ZLRQUMv1ZACTj37sR62cfN7fe5JnJ7dh8zL4fiyLHV"),
+            (1000000000, "xprvA41z7zogVVwxVSgdKUHDy1SKmdb533PjDz7J6N6mV6uS3ze1ai8FHa8kmHScGpWmj4WggLyQjgPie1rFSruoUihUZREPSL39UNdE3BBDu76", "xpub6H1LXWLaKsWFhvm6RVpEL9P4KfRZSW7abD2ttkWP3SSQvnyA8FSVqNTEcYFgJS2UaFcxupHiYkro49S8yGasTvXEYBVPamhGW6cFJodrTHy"),
+        ];
+
+        assert_eq!(master.serialize(), "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi");
+        assert_eq!(master.to_public().serialize(), "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8");
+
+        let mut key = master;
+        for (index, xprv, xpub) in expected {
+            key = key.derive_child(index)?;
+            assert_eq!(key.serialize(), xprv);
+            assert_eq!(key.to_public().serialize(), xpub);
+        }
+
+        assert_eq!(master.derive_path("m/0h/1/2h/2/1000000000")?, key);
+        Ok(())
+    }
+
+    #[test]
+    fn vector_3_leading_zeros() {
+        // The derived key has a leading zero byte that must be kept
+        let seed = hex::decode("4b381541583be4423346c643850da4b320e46a87ae3d2a4e6da11eba819cd4acba45d239319ac14f863b8d5ab5a0d0c64d2e8a1e7d1457df2e5a3c51c73235be").unwrap();
+        let key = ExtendedPrivKey::new_master(&seed, Network::MAINNET).derive_child(HARDENED).unwrap();
+        assert_eq!(key.serialize(), "xprv9uPDJpEQgRQfDcW7BkF7eTya6RPxXeJCqCJGHuCJ4GiRVLzkTXBAJMu2qaMWPrS7AANYqdq6vcBcBUdJCVVFceUvJFjaPdGZ2y9WACViL4L");
+        assert_eq!(key.to_public().serialize(), "xpub68NZiKmJWnxxS6aaHmn81bvJeTESw724CRDs6HbuccFQN9Ku14VQrADWgqbhhTHBaohPX4CjNLf9fq9MYo6oDaPPLPxSb7gwQN3ih19Zm4Y");
+    }
+
+    #[test]
+    fn public_derivation() -> Result<(), Box<dyn std::error::Error>> {
+        // Vector 2: m/0 from xpub alone matches the private route
+        let xpub = ExtendedPubKey::parse("xpub661MyMwAqRbcFW31YEwpkMuc5THy2PSt5bDMsktWQcFF8syAmRUapSCGu8ED9W6oDMSgv6Zz8idoc4a6mr8BDzTJY47LJhkJ8UB7WEGuduB")?;
+        assert_eq!(xpub.derive_path("m/0")?.serialize(), "xpub69H7F5d8KSRgmmdJg2KhpAK8SR3DjMwAdkxj3ZuxV27CprR9LgpeyGmXUbC6wb7ERfvrnKZjXoUmmDznezpbZb7ap6r1D3tgFxHmwMkQTPH");
+        assert!(xpub.derive_path("m/0h").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn maximum_depth() -> Result<(), Box<dyn std::error::Error>> {
+        let master = ExtendedPrivKey::new_master(&hex::decode("000102030405060708090a0b0c0d0e0f")?, Network::MAINNET);
+        let deepest = ExtendedPrivKey { depth: u8::MAX, ..master.derive_child(0)? };
+        assert!(deepest.derive_child(0).is_err());
+        assert!(deepest.to_public().derive_child(0).is_err());
+        assert_eq!(ExtendedPrivKey { depth: u8::MAX - 1, ..deepest }.derive_child(0)?.depth, u8::MAX);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_roundtrip() -> Result<(), Box<dyn std::error::Error>> {
+        let xprv = "xprv9wTYmMFdV23N2TdNG573QoEsfRrWKQgWeibmLntzniatZvR9BmLnvSxqu53Kw1UmYPxLgboyZQaXwTCg8MSY3H2EU4pWcQDnRnrVA1xe8fs";
+        let key = ExtendedPrivKey::parse(xprv)?;
+        assert_eq!((key.depth, key.child_number), (2, 1));
+        assert_eq!(key.serialize(), xprv);
+
+        let testnet = ExtendedPrivKey { network: Network::TESTNET, ..key };
+        assert!(testnet.serialize().starts_with("tprv"));
+        assert!(testnet.to_public().serialize().starts_with("tpub"));
+
+        assert!(ExtendedPrivKey::parse(&xprv.replace('W', "X")).is_err());
+        assert!(ExtendedPubKey::parse(xprv).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn paths() {
+        assert_eq!(parse_path("m").unwrap(), Vec::<u32>::new());
+        assert_eq!(parse_path("m/84'/0h/0H/0/5").unwrap(), vec![84 + HARDENED, HARDENED, HARDENED, 0, 5]);
+        assert!(parse_path("84'/0'").is_err());
+        assert!(parse_path("m/x").is_err());
+        assert!(parse_path("m/2147483648").is_err());
+        assert!(parse_path("m/1/").is_err());
+        assert!(parse_path("m/+1").is_err());
+    }
+}
@@ -0,0 +1,223 @@
+use std::io::{self, Error, ErrorKind};
+use aes::{Aes256, cipher::{BlockDecrypt, BlockEncrypt, KeyInit, generic_array::GenericArray}};
+use crypto_bigint::U256;
+use crate::{algorithms::{base58::{base58_check, decode_base58_check}, hash256::hash256}, s256::{private_key::PrivateKey, s256_point::S256Point, scalr::Scalar}};
+
+// BIP38 passphrase-protected private keys ("6P..." strings), mainnet P2PKH addresses only.
+// Passphrases are used as given: callers should NFC-normalize non-ASCII text first.
+
+const NON_EC: u8 = 0x42;
+const EC_MULTIPLY: u8 = 0x43;
+const FLAG_NON_EC: u8 = 0xc0;
+const FLAG_COMPRESSED: u8 = 0x20;
+const FLAG_LOT_SEQUENCE: u8 = 0x04;
+const MAGIC: [u8; 7] = [0x2c, 0xe9, 0xb3, 0xe1, 0xff, 0x39, 0xe2];
+
+// Non-EC-multiply mode: the key itself is encrypted with scrypt(passphrase, addresshash)
+pub fn encrypt(key: &PrivateKey, passphrase: &str, compressed: bool) -> String {
+    let address_hash = address_hash(key.point, compressed);
+    let derived = scrypt(passphrase.as_bytes(), &address_hash, 14, 8, 8);
+    let secret = key.secret.to_be_bytes();
+
+    let mut res = vec![0x01, NON_EC, FLAG_NON_EC | if compressed { FLAG_COMPRESSED } else { 0 }];
+    res.extend_from_slice(&address_hash);
+    res.extend_from_slice(&aes_encrypt(&derived[32..], &xor(&secret[..16], &derived[..16])));
+    res.extend_from_slice(&aes_encrypt(&derived[32..], &xor(&secret[16..], &derived[16..32])));
+    base58_check(&res)
+}
+
+// Owner side of EC-multiply mode. owner_salt is 8 random bytes, or 4 when a lot and sequence
+// number are encoded (lot < 2^20, sequence < 4096).
+pub fn intermediate_code(passphrase: &str, owner_salt: &[u8], lot_sequence: Option<(u32, u32)>) -> String {
+    let (owner_entropy, magic_last) = match lot_sequence {
+        Some((lot, sequence)) => {
+            assert!(owner_salt.len() == 4, "Owner salt must be 4 bytes with a lot and sequence");
+            assert!(lot < 1 << 20 && sequence < 4096, "Lot or sequence number out of range");
+            ([owner_salt, &(lot * 4096 + sequence).to_be_bytes()].concat(), 0x51)
+        }
+        None => {
+            assert!(owner_salt.len() == 8, "Owner salt must be 8 bytes");
+            (owner_salt.to_vec(), 0x53)
+        }
+    };
+
+    let pass_factor = pass_factor(passphrase, &owner_entropy, lot_sequence.is_some());
+
+    let mut res = MAGIC.to_vec();
+    res.push(magic_last);
+    res.extend_from_slice(&owner_entropy);
+    res.extend_from_slice(&S256Point::g().rmul(pass_factor).sec(true));
+    base58_check(&res)
+}
+
+// Printer side of EC-multiply mode: builds a new key from the intermediate code and 24 random
+// bytes without learning its secret
+pub fn encrypt_from_intermediate(code: &str, seed_b: &[u8; 24], compressed: bool) -> io::Result<String> {
+    let data = decode_base58_check(code)?;
+    if data.len() != 49 || data[..7] != MAGIC || (data[7] != 0x51 && data[7] != 0x53) {
+        return Err(Error::new(ErrorKind::InvalidData, "Invalid BIP38 intermediate code"));
+    }
+    let lot_sequence = data[7] == 0x51;
+    let owner_entropy = &data[8..16];
+    let pass_point = S256Point::parse(&data[16..49])?;
+
+    let factor_b = U256::from_be_slice(&hash256(seed_b));
+    let address_hash = address_hash(pass_point.rmul(factor_b), compressed);
+    let derived = scrypt(&pass_point.sec(true), &[&address_hash[..], owner_entropy].concat(), 10, 1, 1);
+
+    let encrypted_1 = aes_encrypt(&derived[32..], &xor(&seed_b[..16], &derived[..16]));
+    let encrypted_2 = aes_encrypt(&derived[32..], &xor(&[&encrypted_1[8..], &seed_b[16..]].concat(), &derived[16..32]));
+
+    let mut flag = if compressed { FLAG_COMPRESSED } else { 0 };
+    if lot_sequence {
+        flag |= FLAG_LOT_SEQUENCE;
+    }
+
+    let mut res = vec![0x01, EC_MULTIPLY, flag];
+    res.extend_from_slice(&address_hash);
+    res.extend_from_slice(owner_entropy);
+    res.extend_from_slice(&encrypted_1[..8]);
+    res.extend_from_slice(&encrypted_2);
+    Ok(base58_check(&res))
+}
+
+// Both modes. Returns the key and whether its address uses the compressed public key.
+pub fn decrypt(encrypted: &str, passphrase: &str) -> io::Result<(PrivateKey, bool)> {
+    let data = decode_base58_check(encrypted)?;
+    if data.len() != 39 || data[0] != 0x01 {
+        return Err(Error::new(ErrorKind::InvalidData, "Invalid BIP38 key"));
+    }
+    let flag = data[2];
+    let compressed = flag & FLAG_COMPRESSED != 0;
+    let stored_hash = &data[3..7];
+
+    let secret = match data[1] {
+        NON_EC if flag & !FLAG_COMPRESSED == FLAG_NON_EC => {
+            let derived = scrypt(passphrase.as_bytes(), stored_hash, 14, 8, 8);
+            let half_1 = xor(&aes_decrypt(&derived[32..], &data[7..23]), &derived[..16]);
+            let half_2 = xor(&aes_decrypt(&derived[32..], &data[23..39]), &derived[16..32]);
+            U256::from_be_slice(&[half_1, half_2].concat())
+        }
+        EC_MULTIPLY if flag & !(FLAG_COMPRESSED | FLAG_LOT_SEQUENCE) == 0 => {
+            let owner_entropy = &data[7..15];
+            let pass_factor = pass_factor(passphrase, owner_entropy, flag & FLAG_LOT_SEQUENCE != 0);
+            let pass_point = S256Point::g().rmul(pass_factor);
+            let derived = scrypt(&pass_point.sec(true), &[stored_hash, owner_entropy].concat(), 10, 1, 1);
+
+            // encrypted_2 holds the second half of encrypted_1 and the last 8 bytes of seed_b
+            let decrypted_2 = xor(&aes_decrypt(&derived[32..], &data[23..39]), &derived[16..32]);
+            let encrypted_1 = [&data[15..23], &decrypted_2[..8]].concat();
+            let seed_b = [xor(&aes_decrypt(&derived[32..], &encrypted_1), &derived[..16]), decrypted_2[8..].to_vec()].concat();
+
+            let factor_b = U256::from_be_slice(&hash256(&seed_b));
+            (Scalar::new(pass_factor) * Scalar::new(factor_b)).num
+        }
+        _ => return Err(Error::new(ErrorKind::InvalidData, "Unsupported BIP38 mode or flags")),
+    };
+
+    if secret == U256::ZERO || secret >= S256Point::n() {
+        return Err(Error::new(ErrorKind::InvalidData, "Wrong passphrase"));
+    }
+    let key = PrivateKey::new(secret);
+    if stored_hash != address_hash(key.point, compressed) {
+        return Err(Error::new(ErrorKind::InvalidData, "Wrong passphrase"));
+    }
+    Ok((key, compressed))
+}
+
+fn pass_factor(passphrase: &str, owner_entropy: &[u8], lot_sequence: bool) -> U256 {
+    if lot_sequence {
+        let pre_factor = scrypt(passphrase.as_bytes(), &owner_entropy[..4], 14, 8, 8);
+        U256::from_be_slice(&hash256(&[&pre_factor[..32], owner_entropy].concat()))
+    } else {
+        U256::from_be_slice(&scrypt(passphrase.as_bytes(), owner_entropy, 14, 8, 8)[..32])
+    }
+}
+
+// First 4 bytes of hash256 of the base58 address
+fn address_hash(point: S256Point, compressed: bool) -> [u8; 4] {
+    hash256(point.address(compressed, false).as_bytes())[..4].try_into().unwrap()
+}
+
+fn scrypt(password: &[u8], salt: &[u8], log_n: u8, r: u32, p: u32) -> [u8; 64] {
+    let params = scrypt::Params::new(log_n, r, p, 64).expect("Valid scrypt parameters");
+    let mut res = [0u8; 64];
+    scrypt::scrypt(password, salt, &params, &mut res).expect("64-byte output is supported");
+    res
+}
+
+fn aes_encrypt(key: &[u8], block: &[u8]) -> Vec<u8> {
+    let mut block = GenericArray::clone_from_slice(block);
+    Aes256::new(GenericArray::from_slice(key)).encrypt_block(&mut block);
+    block.to_vec()
+}
+
+fn aes_decrypt(key: &[u8], block: &[u8]) -> Vec<u8> {
+    let mut block = GenericArray::clone_from_slice(block);
+    Aes256::new(GenericArray::from_slice(key)).decrypt_block(&mut block);
+    block.to_vec()
+}
+
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b).map(|(x, y)| x ^ y).collect()
+}
+
+#[cfg(test)]
+mod bip38_tests {
+    use super::*;
+
+    #[test]
+    fn non_ec_multiply() -> Result<(), Box<dyn std::error::Error>> {
+        // BIP38 vectors: no compression, then compression
+        let vectors = [
+            ("6PRVWUbkzzsbcVac2qwfssoUJAN1Xhrg6bNk8J7Nzm5H7kxEbn2Nh2ZoGg", "TestingOneTwoThree", "5KN7MzqK5wt2TP1fQCYyHBtDrXdJuXbUzm4A9rKAteGu3Qi5CVR"),
+            ("6PYLtMnXvfG3oJde97zRyLYFZCYizPU5T3LwgdYJz1fRhh16bU7u6PPmY7", "Satoshi", "KwYgW8gcxj1JWJXhPSu4Fqwzfhp5Yfi42mdYmMa4XqK7NJxXUSK7"),
+        ];
+
+        for (encrypted, passphrase, wif) in vectors {
+            let (key, compressed) = decrypt(encrypted, passphrase)?;
+            assert_eq!(encrypt(&key, passphrase, compressed), encrypted);
+            assert_eq!(key.wif(compressed, false), wif);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn ec_multiply() -> Result<(), Box<dyn std::error::Error>> {
+        // BIP38 vectors: without, then with lot and sequence numbers
+        let vectors = [
+            ("6PfQu77ygVyJLZjfvMLyhLMQbYnu5uguoJJ4kMCLqWwPEdfpwANVS76gTX", "TestingOneTwoThree", "5K4caxezwjGCGfnoPTZ8tMcJBLB7Jvyjv4xxeacadhq8nLisLR2"),
+            ("6PgNBNNzDkKdhkT6uJntUXwwzQV8Rr2tZcbkDcuC9DZRsS6AtHts4Ypo1j", "MOLON LABE", "5JLdxTtcTHcfYcmJsNVy1v2PMDx432JPoYcBTVVRHpPaxUrdtf8"),
+        ];
+
+        for (encrypted, passphrase, wif) in vectors {
+            let (key, compressed) = decrypt(encrypted, passphrase)?;
+            assert_eq!(key.wif(compressed, false), wif);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn intermediate_code_roundtrip() -> Result<(), Box<dyn std::error::Error>> {
+        // Owner salt of the first EC-multiply vector, which gives its published intermediate code
+        let code = intermediate_code("TestingOneTwoThree", &hex::decode("a50dba6772cb9383")?, None);
+        assert_eq!(code, "passphrasepxFy57B9v8HtUsszJYKReoNDV6VHjUSGt8EVJmux9n1J3Ltf1gRxyDGXqnf9qm");
+
+        let seed_b: [u8; 24] = core::array::from_fn(|i| i as u8);
+        let encrypted = encrypt_from_intermediate(&code, &seed_b, true)?;
+        assert_eq!(encrypted, "6PnT8gpBDk9K4J1r82G2ZV9paccZBYZvjyYQjimYdMrQx416TL4Zb7ueNi");
+
+        let (key, compressed) = decrypt(&encrypted, "TestingOneTwoThree")?;
+        assert!(compressed);
+        assert_eq!(key.wif(true, false), "KyVprLVMMhQaV8K2fA7zrKMQ6JxdjiUaqKMfB49hee7TpCG2ZHs9");
+        Ok(())
+    }
+
+    #[test]
+    fn wrong_passphrase() {
+        let err = decrypt("6PRVWUbkzzsbcVac2qwfssoUJAN1Xhrg6bNk8J7Nzm5H7kxEbn2Nh2ZoGg", "TestingOneTwoFour").err().unwrap();
+        assert_eq!(err.to_string(), "Wrong passphrase");
+        assert!(decrypt("5KN7MzqK5wt2TP1fQCYyHBtDrXdJuXbUzm4A9rKAteGu3Qi5CVR", "TestingOneTwoThree").is_err());
+        assert!(encrypt_from_intermediate("6PRVWUbkzzsbcVac2qwfssoUJAN1Xhrg6bNk8J7Nzm5H7kxEbn2Nh2ZoGg", &[0u8; 24], true).is_err());
+    }
+}
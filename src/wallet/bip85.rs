@@ -0,0 +1,136 @@
+use std::io::{self, Error, ErrorKind};
+use crypto_bigint::U256;
+use crate::{algorithms::{base58::base58_check, hmac_sha512::hmac_sha512}, bitcoin::tx::tx::Network, s256::s256_point::S256Point, wallet::{bip32::ExtendedPrivKey, bip39::entropy_to_mnemonic}};
+
+// BIP85: child entropy is HMAC-SHA512("bip-entropy-from-k", k) for the key k at a hardened
+// path under m/83696968', and each application formats that entropy differently.
+
+const PURPOSE: u32 = 83696968;
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// path relative to m/83696968', e.g. "0'/0'"
+pub fn derive_entropy(master: &ExtendedPrivKey, path: &str) -> io::Result<[u8; 64]> {
+    let k = master.derive_secret(&format!("m/{}'/{}", PURPOSE, path))?;
+    Ok(hmac_sha512(b"bip-entropy-from-k", &k.to_be_bytes()))
+}
+
+// English (language 0) mnemonic of 12, 18 or 24 words
+pub fn bip39(master: &ExtendedPrivKey, words: u32, index: u32) -> io::Result<String> {
+    if ![12, 18, 24].contains(&words) {
+        return Err(invalid("BIP85 mnemonics have 12, 18 or 24 words"));
+    }
+    let entropy = derive_entropy(master, &format!("39'/0'/{}'/{}'", words, index))?;
+    Ok(entropy_to_mnemonic(&entropy[..words as usize * 4 / 3]))
+}
+
+// Compressed mainnet WIF from the first 32 bytes
+pub fn wif(master: &ExtendedPrivKey, index: u32) -> io::Result<String> {
+    let entropy = derive_entropy(master, &format!("2'/{}'", index))?;
+    check_secret(&entropy[..32])?;
+
+    let mut res = vec![0x80];
+    res.extend_from_slice(&entropy[..32]);
+    res.push(0x01);
+    Ok(base58_check(&res))
+}
+
+// Master xprv with the chain code from the first 32 bytes and the key from the last 32
+pub fn xprv(master: &ExtendedPrivKey, index: u32) -> io::Result<ExtendedPrivKey> {
+    let entropy = derive_entropy(master, &format!("32'/{}'", index))?;
+    let secret = check_secret(&entropy[32..])?;
+
+    Ok(ExtendedPrivKey {
+        network: Network::MAINNET,
+        depth: 0,
+        parent_fingerprint: [0u8; 4],
+        child_number: 0,
+        chain_code: entropy[..32].try_into().unwrap(),
+        secret,
+        point: S256Point::g().rmul(secret),
+    })
+}
+
+pub fn hex(master: &ExtendedPrivKey, num_bytes: u32, index: u32) -> io::Result<Vec<u8>> {
+    if !(16..=64).contains(&num_bytes) {
+        return Err(invalid("BIP85 hex takes 16 to 64 bytes"));
+    }
+    let entropy = derive_entropy(master, &format!("128169'/{}'/{}'", num_bytes, index))?;
+    Ok(entropy[..num_bytes as usize].to_vec())
+}
+
+// Base64 of the entropy, truncated to pwd_len characters
+pub fn password_base64(master: &ExtendedPrivKey, pwd_len: u32, index: u32) -> io::Result<String> {
+    if !(20..=86).contains(&pwd_len) {
+        return Err(invalid("BIP85 base64 passwords are 20 to 86 characters"));
+    }
+    let entropy = derive_entropy(master, &format!("707764'/{}'/{}'", pwd_len, index))?;
+    Ok(base64(&entropy)[..pwd_len as usize].to_string())
+}
+
+fn check_secret(bytes: &[u8]) -> io::Result<U256> {
+    let secret = U256::from_be_slice(bytes);
+    if secret == U256::ZERO || secret >= S256Point::n() {
+        return Err(invalid("Derived entropy is not a valid private key"));
+    }
+    Ok(secret)
+}
+
+// Standard alphabet with = padding
+fn base64(data: &[u8]) -> String {
+    let mut res = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |acc, (i, &b)| acc | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                res.push(BASE64_ALPHABET[(n >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                res.push('=');
+            }
+        }
+    }
+    res
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidInput, msg.to_string())
+}
+
+#[cfg(test)]
+mod bip85_tests {
+    use super::*;
+
+    fn master() -> ExtendedPrivKey {
+        ExtendedPrivKey::parse("xprv9s21ZrQH143K2LBWUUQRFXhucrQqBpKdRRxNVq2zBqsx8HVqFk2uYo8kmbaLLHRdqtQpUm98uKfu3vca1LqdGhUtyoFnCNkfmXRyPXLjbKb").unwrap()
+    }
+
+    #[test]
+    fn vectors() -> Result<(), Box<dyn std::error::Error>> {
+        let master = master();
+
+        assert_eq!(hex::encode(derive_entropy(&master, "0'/0'")?), "efecfbccffea313214232d29e71563d941229afb4338c21f9517c41aaa0d16f00b83d2a09ef747e7a64e8e2bd5a14869e693da66ce94ac2da570ab7ee48618f7");
+        assert_eq!(bip39(&master, 12, 0)?, "girl mad pet galaxy egg matter matrix prison refuse sense ordinary nose");
+        assert_eq!(bip39(&master, 18, 0)?, "near account window bike charge season chef number sketch tomorrow excuse sniff circle vital hockey outdoor supply token");
+        assert_eq!(bip39(&master, 24, 0)?, "puppy ocean match cereal symbol another shed magic wrap hammer bulb intact gadget divorce twin tonight reason outdoor destroy simple truth cigar social volcano");
+        assert_eq!(wif(&master, 0)?, "Kzyv4uF39d4Jrw2W7UryTHwZr1zQVNk4dAFyqE6BuMrMh1Za7uhp");
+        assert_eq!(xprv(&master, 0)?.serialize(), "xprv9s21ZrQH143K2srSbCSg4m4kLvPMzcWydgmKEnMmoZUurYuBuYG46c6P71UGXMzmriLzCCBvKQWBUv3vPB3m1SATMhp3uEjXHJ42jFg7myX");
+        assert_eq!(hex::encode(hex(&master, 64, 0)?), "492db4698cf3b73a5a24998aa3e9d7fa96275d85724a91e71aa2d645442f878555d078fd1f1f67e368976f04137b1f7a0d19232136ca50c44614af72b5582a5c");
+        assert_eq!(password_base64(&master, 21, 0)?, "dKLoepugzdVJvdL56ogNV");
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_parameters() {
+        let master = master();
+        assert!(bip39(&master, 15, 0).is_err());
+        assert!(hex(&master, 15, 0).is_err());
+        assert!(password_base64(&master, 87, 0).is_err());
+        assert!(derive_entropy(&master, "0'/0").is_err());
+    }
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+}
@@ -0,0 +1,222 @@
+use std::io::{self, Error, ErrorKind};
+use crate::{algorithms::bech32::{CHARSET, convert_bits}, primitives::{binary_field::GF32, shamir::interpolate_shares}};
+
+// BIP93 codex32: "ms1" || threshold || identifier (4) || share index || payload || checksum (13).
+// Shares are points over GF(32), one polynomial per character, with the secret at index 's'.
+// Only the short (13-character checksum) format is supported.
+
+const GENERATOR: [u128; 5] = [0x19dc500ce73fde210, 0x1bfae00def77fe529, 0x1fbd920fffe7bee52, 0x1739640bdeee3fdad, 0x07729a039cfc75f5a];
+const RESIDUE: u128 = 0x23181b3;
+const CONSTANT: u128 = 0x10ce0795c2fd1e62a;
+const CHECKSUM_LEN: usize = 13;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Codex32 {
+    // 0 for an unshared secret, otherwise 2 to 9
+    pub threshold: u8,
+    pub identifier: String,
+    pub share_index: char,
+    // 5-bit values
+    pub payload: Vec<u8>,
+}
+
+impl Codex32 {
+    // The secret share ('s') of a 16 to 64 byte master seed
+    pub fn from_seed(threshold: u8, identifier: &str, seed: &[u8]) -> io::Result<Self> {
+        if !(16..=64).contains(&seed.len()) {
+            return Err(invalid("Seed must be 16 to 64 bytes"));
+        }
+        let share = Self { threshold, identifier: identifier.to_string(), share_index: 's', payload: convert_bits(seed, 8, 5, true)? };
+        share.check_header()?;
+        Ok(share)
+    }
+
+    pub fn parse(s: &str) -> io::Result<Self> {
+        if s.chars().any(|c| c.is_ascii_lowercase()) && s.chars().any(|c| c.is_ascii_uppercase()) {
+            return Err(invalid("Mixed case codex32 string"));
+        }
+        let s = s.to_ascii_lowercase();
+        let (hrp, data) = s.rsplit_once('1').ok_or_else(|| invalid("Missing separator"))?;
+        if hrp != "ms" {
+            return Err(invalid("Codex32 strings start with ms1"));
+        }
+        if s.len() > 93 {
+            return Err(invalid("Long codex32 strings are not supported"));
+        }
+        if s.len() < 48 {
+            return Err(invalid("Codex32 string too short"));
+        }
+
+        let values = data.bytes()
+            .map(|c| CHARSET.iter().position(|&x| x == c).map(|v| v as u8))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(|| invalid("Invalid codex32 character"))?;
+        if polymod(&values) != CONSTANT {
+            return Err(invalid("Codex32 checksum mismatch"));
+        }
+
+        let share = Self {
+            threshold: data.as_bytes()[0].wrapping_sub(b'0'),
+            identifier: data[1..5].to_string(),
+            share_index: data.as_bytes()[5] as char,
+            payload: values[6..values.len() - CHECKSUM_LEN].to_vec(),
+        };
+        share.check_header()?;
+        Ok(share)
+    }
+
+    pub fn encode(&self) -> String {
+        let mut values = self.header();
+        values.extend_from_slice(&self.payload);
+
+        let residue = polymod(&[&values[..], &[0u8; CHECKSUM_LEN]].concat()) ^ CONSTANT;
+        values.extend((0..CHECKSUM_LEN).map(|i| ((residue >> (5 * (CHECKSUM_LEN - 1 - i))) & 31) as u8));
+
+        let data: String = values.iter().map(|&v| CHARSET[v as usize] as char).collect();
+        format!("ms1{}", data)
+    }
+
+    // Payload as bytes; trailing padding bits (fewer than 5) are dropped whatever their value
+    pub fn seed(&self) -> Vec<u8> {
+        let mut bytes = convert_bits(&self.payload, 5, 8, true).expect("Payload holds 5-bit values");
+        bytes.truncate(self.payload.len() * 5 / 8);
+        bytes
+    }
+
+    // Share at any index from threshold shares of the same secret
+    pub fn interpolate(shares: &[Codex32], index: char) -> io::Result<Self> {
+        let first = shares.first().ok_or_else(|| invalid("No shares given"))?;
+        let x = char_value(index).ok_or_else(|| invalid("Invalid share index"))?;
+
+        if shares.iter().any(|s| s.threshold != first.threshold || s.identifier != first.identifier || s.payload.len() != first.payload.len()) {
+            return Err(invalid("Shares have different thresholds, identifiers or lengths"));
+        }
+        if first.threshold == 0 {
+            return Err(invalid("An unshared secret cannot be interpolated"));
+        }
+        let shares = &shares[..shares.len().min(first.threshold as usize)];
+        if shares.len() < first.threshold as usize {
+            return Err(invalid("Not enough shares to reach the threshold"));
+        }
+        for (i, share) in shares.iter().enumerate() {
+            if shares[..i].iter().any(|s| s.share_index == share.share_index) {
+                return Err(invalid("Duplicate share index"));
+            }
+        }
+
+        let points: Vec<(GF32, Vec<GF32>)> = shares.iter()
+            .map(|s| (GF32::new(char_value(s.share_index).unwrap()), s.payload.iter().map(|&v| GF32::new(v)).collect()))
+            .collect();
+        let payload = interpolate_shares(&points, GF32::new(x)).into_iter().map(|v| v.num).collect();
+
+        Ok(Self { threshold: first.threshold, identifier: first.identifier.clone(), share_index: index, payload })
+    }
+
+    pub fn recover_seed(shares: &[Codex32]) -> io::Result<Vec<u8>> {
+        match shares {
+            [share] if share.share_index == 's' => Ok(share.seed()),
+            _ => Ok(Self::interpolate(shares, 's')?.seed()),
+        }
+    }
+
+    fn header(&self) -> Vec<u8> {
+        let mut chars = vec![b'0' + self.threshold];
+        chars.extend(self.identifier.bytes());
+        chars.push(self.share_index as u8);
+        chars.iter().map(|&c| char_value(c as char).unwrap()).collect()
+    }
+
+    fn check_header(&self) -> io::Result<()> {
+        if self.threshold == 1 || self.threshold > 9 {
+            return Err(invalid("Threshold must be 0 or 2 to 9"));
+        }
+        if self.identifier.len() != 4 || !self.identifier.chars().all(|c| char_value(c).is_some()) {
+            return Err(invalid("Identifier must be 4 bech32 characters"));
+        }
+        if char_value(self.share_index).is_none() {
+            return Err(invalid("Invalid share index"));
+        }
+        if self.threshold == 0 && self.share_index != 's' {
+            return Err(invalid("Threshold 0 requires share index s"));
+        }
+        Ok(())
+    }
+}
+
+fn char_value(c: char) -> Option<u8> {
+    CHARSET.iter().position(|&x| x as char == c).map(|v| v as u8)
+}
+
+fn polymod(values: &[u8]) -> u128 {
+    let mut residue = RESIDUE;
+    for &v in values {
+        let top = residue >> 60;
+        residue = (residue & 0x0fffffffffffffff) << 5 ^ v as u128;
+        for (i, g) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                residue ^= g;
+            }
+        }
+    }
+    residue
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.to_string())
+}
+
+#[cfg(test)]
+mod codex32_tests {
+    use super::*;
+
+    #[test]
+    fn vector_1() -> Result<(), Box<dyn std::error::Error>> {
+        let s = "ms10testsxxxxxxxxxxxxxxxxxxxxxxxxxx4nzvca9cmczlw";
+        let share = Codex32::parse(s)?;
+        assert_eq!((share.threshold, share.identifier.as_str(), share.share_index), (0, "test", 's'));
+        assert_eq!(hex::encode(share.seed()), "318c6318c6318c6318c6318c6318c631");
+        assert_eq!(share.encode(), s);
+        assert_eq!(Codex32::recover_seed(&[share])?, hex::decode("318c6318c6318c6318c6318c6318c631")?);
+        Ok(())
+    }
+
+    #[test]
+    fn vector_2() -> Result<(), Box<dyn std::error::Error>> {
+        let a = Codex32::parse("MS12NAMEA320ZYXWVUTSRQPNMLKJHGFEDCAXRPP870HKKQRM")?;
+        let c = Codex32::parse("MS12NAMECACDEFGHJKLMNPQRSTUVWXYZ023FTR2GDZMPY6PN")?;
+
+        let secret = Codex32::interpolate(&[a.clone(), c.clone()], 's')?;
+        assert_eq!(secret.encode().to_uppercase(), "MS12NAMES6XQGUZTTXKEQNJSJZV4JV3NZ5K3KWGSPHUH6EVW");
+        assert_eq!(Codex32::interpolate(&[a.clone(), c.clone()], 'd')?.encode(), "ms12namedll4f8jlh4e5vdvuldlfxu2jhdnlsm97xvenrxeg");
+        assert_eq!(Codex32::recover_seed(&[c, a])?, hex::decode("d1808e096b35b209ca12132b264662a5")?);
+        Ok(())
+    }
+
+    #[test]
+    fn from_seed() -> Result<(), Box<dyn std::error::Error>> {
+        let seed = hex::decode("d1808e096b35b209ca12132b264662a5")?;
+        let secret = Codex32::from_seed(2, "name", &seed)?;
+        let encoded = secret.encode();
+        assert_eq!(Codex32::parse(&encoded)?.seed(), seed);
+
+        // Pick share a freely, derive c from it and the secret, then recover from a and c
+        let a = Codex32::parse("ms12namea320zyxwvutsrqpnmlkjhgfedcaxrpp870hkkqrm")?;
+        let c = Codex32::interpolate(&[secret, a.clone()], 'c')?;
+        assert_eq!(Codex32::recover_seed(&[a, c])?, seed);
+        assert!(Codex32::from_seed(0, "name", &seed[..15]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn invalid() {
+        let err = |s: &str| Codex32::parse(s).unwrap_err().to_string();
+        assert_eq!(err("ms10testsxxxxxxxxxxxxxxxxxxxxxxxxxx4nzvca9cmczlx"), "Codex32 checksum mismatch");
+        assert_eq!(err("ms10testsxxxxxxxxxxxxxxxxxxxxxxxxxx4nzvca9cmczLW"), "Mixed case codex32 string");
+        assert_eq!(err("bc10testsxxxxxxxxxxxxxxxxxxxxxxxxxx4nzvca9cmczlw"), "Codex32 strings start with ms1");
+        assert_eq!(Codex32::from_seed(0, "tbst", &[0u8; 16]).unwrap_err().to_string(), "Identifier must be 4 bech32 characters");
+
+        let a = Codex32::parse("MS12NAMEA320ZYXWVUTSRQPNMLKJHGFEDCAXRPP870HKKQRM").unwrap();
+        assert_eq!(Codex32::interpolate(std::slice::from_ref(&a), 's').unwrap_err().to_string(), "Not enough shares to reach the threshold");
+        assert_eq!(Codex32::interpolate(&[a.clone(), a.clone()], 's').unwrap_err().to_string(), "Duplicate share index");
+    }
+}
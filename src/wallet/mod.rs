@@ -0,0 +1,10 @@
+pub mod bip32;
+pub mod bip39;
+pub mod english;
+pub mod account;
+pub mod bip38;
+pub mod codex32;
+pub mod slip39;
+pub mod slip39_words;
+pub mod bip85;
+pub mod vanity;
\ No newline at end of file
@@ -0,0 +1,454 @@
+use std::io::{self, Error, ErrorKind};
+use hmac::{Hmac, Mac};
+use pbkdf2::pbkdf2_hmac;
+use sha2::Sha256;
+use crate::{primitives::{binary_field::GF256, shamir::interpolate_shares}, wallet::slip39_words::WORDS};
+
+// SLIP-39 Shamir backups: the master secret is encrypted with a passphrase, split into
+// group shares, and each group share is split again into member shares, all over GF(256).
+// Each share is a mnemonic of 10-bit words ending in an RS1024 checksum.
+
+const SECRET_INDEX: u8 = 255;
+const DIGEST_INDEX: u8 = 254;
+const DIGEST_LEN: usize = 4;
+const ROUNDS: u8 = 4;
+const BASE_ITERATIONS: u32 = 10000;
+const CHECKSUM_WORDS: usize = 3;
+const HEADER_WORDS: usize = 4;
+const GENERATOR: [u32; 10] = [0xe0e040, 0x1c1c080, 0x3838100, 0x7070200, 0xe0e0009, 0x1c0c2412, 0x38086c24, 0x3090fc48, 0x21b1f890, 0x3f3f120];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Share {
+    // 15 bits, random per backup
+    pub identifier: u16,
+    pub extendable: bool,
+    // PBKDF2 runs 10000 << iteration_exponent rounds in total
+    pub iteration_exponent: u8,
+    pub group_index: u8,
+    pub group_threshold: u8,
+    pub group_count: u8,
+    pub member_index: u8,
+    pub member_threshold: u8,
+    pub value: Vec<u8>,
+}
+
+impl Share {
+    // id (15) | ext (1) | e (4) | GI (4) | Gt - 1 (4) | g - 1 (4) | I (4) | t - 1 (4) | padded value | checksum (30)
+    pub fn to_words(&self) -> Vec<u16> {
+        let mut bits = Vec::new();
+        push_bits(&mut bits, self.identifier as u32, 15);
+        push_bits(&mut bits, self.extendable as u32, 1);
+        push_bits(&mut bits, self.iteration_exponent as u32, 4);
+        push_bits(&mut bits, self.group_index as u32, 4);
+        push_bits(&mut bits, self.group_threshold as u32 - 1, 4);
+        push_bits(&mut bits, self.group_count as u32 - 1, 4);
+        push_bits(&mut bits, self.member_index as u32, 4);
+        push_bits(&mut bits, self.member_threshold as u32 - 1, 4);
+
+        // Left-pad the value with zero bits to a whole number of words
+        let value_bits = self.value.len() * 8;
+        bits.extend(std::iter::repeat_n(false, value_bits.div_ceil(10) * 10 - value_bits));
+        for &b in &self.value {
+            push_bits(&mut bits, b as u32, 8);
+        }
+
+        let mut words: Vec<u16> = bits.chunks(10).map(|c| c.iter().fold(0u16, |acc, &bit| (acc << 1) | bit as u16)).collect();
+        let residue = rs1024_polymod(customization(self.extendable), &[&words[..], &[0; CHECKSUM_WORDS]].concat()) ^ 1;
+        words.extend((0..CHECKSUM_WORDS).map(|i| ((residue >> (10 * (CHECKSUM_WORDS - 1 - i))) & 1023) as u16));
+        words
+    }
+
+    pub fn to_mnemonic(&self) -> String {
+        self.to_words().into_iter().map(|w| WORDS[w as usize]).collect::<Vec<_>>().join(" ")
+    }
+
+    pub fn from_mnemonic(mnemonic: &str) -> io::Result<Self> {
+        let words = mnemonic.split_whitespace().map(|word| {
+            WORDS.binary_search(&word).map(|i| i as u16).map_err(|_| invalid(&format!("Unknown word {:?}", word)))
+        }).collect::<io::Result<Vec<u16>>>()?;
+        Self::from_words(&words)
+    }
+
+    pub fn from_words(words: &[u16]) -> io::Result<Self> {
+        if words.iter().any(|&w| w > 1023) {
+            return Err(invalid("Word index out of range"));
+        }
+        // 128-bit secrets give the shortest shares, 20 words
+        if words.len() < HEADER_WORDS + 13 + CHECKSUM_WORDS {
+            return Err(invalid("Too few words for a SLIP-39 share"));
+        }
+
+        let extendable = (words[1] >> 4) & 1 == 1;
+        if rs1024_polymod(customization(extendable), words) != 1 {
+            return Err(invalid("Invalid share checksum"));
+        }
+
+        let header = ((words[0] as u64) << 30) | ((words[1] as u64) << 20) | ((words[2] as u64) << 10) | words[3] as u64;
+        let field = |shift: u32, bits: u32| ((header >> shift) & ((1 << bits) - 1)) as u8;
+
+        let value_words = &words[HEADER_WORDS..words.len() - CHECKSUM_WORDS];
+        let padding = value_words.len() * 10 % 16;
+        if padding > 8 {
+            return Err(invalid("Invalid share length"));
+        }
+        let bits: Vec<bool> = value_words.iter().flat_map(|&w| (0..10).rev().map(move |i| (w >> i) & 1 == 1)).collect();
+        if bits[..padding].iter().any(|&b| b) {
+            return Err(invalid("Invalid share padding"));
+        }
+        let value: Vec<u8> = bits[padding..].chunks(8).map(|c| c.iter().fold(0u8, |acc, &bit| (acc << 1) | bit as u8)).collect();
+
+        let share = Self {
+            identifier: (header >> 25) as u16,
+            extendable,
+            iteration_exponent: field(20, 4),
+            group_index: field(16, 4),
+            group_threshold: field(12, 4) + 1,
+            group_count: field(8, 4) + 1,
+            member_index: field(4, 4),
+            member_threshold: field(0, 4) + 1,
+            value,
+        };
+        if share.group_threshold > share.group_count {
+            return Err(invalid("Group threshold exceeds group count"));
+        }
+        Ok(share)
+    }
+}
+
+// Splits master_secret into groups of (member threshold, member count) shares, of which
+// group_threshold groups are needed. random fills buffers with secure random bytes.
+pub fn generate_shares(
+    group_threshold: u8,
+    groups: &[(u8, u8)],
+    master_secret: &[u8],
+    passphrase: &[u8],
+    iteration_exponent: u8,
+    extendable: bool,
+    random: &mut impl FnMut(&mut [u8]),
+) -> io::Result<Vec<Vec<Share>>> {
+    if master_secret.len() < 16 || !master_secret.len().is_multiple_of(2) {
+        return Err(invalid("Master secret must be at least 128 bits and a whole number of 16-bit units"));
+    }
+    if group_threshold == 0 || group_threshold as usize > groups.len() || groups.len() > 16 {
+        return Err(invalid("Group threshold must be between 1 and the group count (at most 16)"));
+    }
+    if iteration_exponent > 15 {
+        return Err(invalid("Iteration exponent must fit in 4 bits"));
+    }
+    for &(threshold, count) in groups {
+        if threshold == 0 || threshold > count || count > 16 || (threshold == 1 && count > 1) {
+            return Err(invalid("Invalid member threshold or count"));
+        }
+    }
+
+    let mut id_bytes = [0u8; 2];
+    random(&mut id_bytes);
+    let identifier = u16::from_be_bytes(id_bytes) & 0x7fff;
+
+    let encrypted = encrypt(master_secret, passphrase, iteration_exponent, identifier, extendable);
+    let group_shares = split_secret(group_threshold, groups.len() as u8, &encrypted, random);
+
+    Ok(groups.iter().zip(group_shares).enumerate().map(|(group_index, (&(member_threshold, count), group_secret))| {
+        split_secret(member_threshold, count, &group_secret, random).into_iter().enumerate().map(|(member_index, value)| Share {
+            identifier,
+            extendable,
+            iteration_exponent,
+            group_index: group_index as u8,
+            group_threshold,
+            group_count: groups.len() as u8,
+            member_index: member_index as u8,
+            member_threshold,
+            value,
+        }).collect()
+    }).collect())
+}
+
+pub fn combine_shares(shares: &[Share], passphrase: &[u8]) -> io::Result<Vec<u8>> {
+    let first = shares.first().ok_or_else(|| invalid("No shares given"))?;
+    if shares.iter().any(|s| (s.identifier, s.extendable, s.iteration_exponent, s.group_threshold, s.group_count, s.value.len())
+        != (first.identifier, first.extendable, first.iteration_exponent, first.group_threshold, first.group_count, first.value.len()))
+    {
+        return Err(invalid("Shares belong to different backups"));
+    }
+
+    let mut group_indexes: Vec<u8> = shares.iter().map(|s| s.group_index).collect();
+    group_indexes.sort();
+    group_indexes.dedup();
+    if group_indexes.len() < first.group_threshold as usize {
+        return Err(invalid(&format!("Need shares from {} groups, got {}", first.group_threshold, group_indexes.len())));
+    }
+
+    let mut group_secrets = Vec::new();
+    for &group_index in group_indexes.iter().take(first.group_threshold as usize) {
+        let members: Vec<&Share> = shares.iter().filter(|s| s.group_index == group_index).collect();
+        let threshold = members[0].member_threshold;
+        if members.iter().any(|s| s.member_threshold != threshold) {
+            return Err(invalid("Member thresholds differ within a group"));
+        }
+        let points: Vec<(u8, Vec<u8>)> = members.iter().map(|s| (s.member_index, s.value.clone())).collect();
+        group_secrets.push((group_index, recover_secret(threshold, &points)?));
+    }
+
+    let encrypted = recover_secret(first.group_threshold, &group_secrets)?;
+    Ok(decrypt(&encrypted, passphrase, first.iteration_exponent, first.identifier, first.extendable))
+}
+
+// Shares are x = 0..count; for threshold > 1, x = 0..threshold-2 are random and the
+// polynomial also passes through the digest (x = 254) and the secret (x = 255)
+fn split_secret(threshold: u8, count: u8, secret: &[u8], random: &mut impl FnMut(&mut [u8])) -> Vec<Vec<u8>> {
+    if threshold == 1 {
+        return vec![secret.to_vec(); count as usize];
+    }
+
+    let mut base: Vec<(GF256, Vec<GF256>)> = (0..threshold - 2).map(|x| {
+        let mut value = vec![0u8; secret.len()];
+        random(&mut value);
+        (GF256::new(x), to_field(&value))
+    }).collect();
+
+    let mut digest_key = vec![0u8; secret.len() - DIGEST_LEN];
+    random(&mut digest_key);
+    let digest = [&share_digest(&digest_key, secret)[..], &digest_key].concat();
+    base.push((GF256::new(DIGEST_INDEX), to_field(&digest)));
+    base.push((GF256::new(SECRET_INDEX), to_field(secret)));
+
+    (0..count).map(|x| {
+        if x < threshold - 2 {
+            from_field(&base[x as usize].1)
+        } else {
+            from_field(&interpolate_shares(&base, GF256::new(x)))
+        }
+    }).collect()
+}
+
+fn recover_secret(threshold: u8, shares: &[(u8, Vec<u8>)]) -> io::Result<Vec<u8>> {
+    if shares.len() < threshold as usize {
+        return Err(invalid(&format!("Need {} shares, got {}", threshold, shares.len())));
+    }
+    let shares = &shares[..threshold as usize];
+    for (i, (x, _)) in shares.iter().enumerate() {
+        if shares[..i].iter().any(|(other, _)| other == x) {
+            return Err(invalid("Duplicate share index"));
+        }
+    }
+    if threshold == 1 {
+        return Ok(shares[0].1.clone());
+    }
+
+    let points: Vec<(GF256, Vec<GF256>)> = shares.iter().map(|(x, value)| (GF256::new(*x), to_field(value))).collect();
+    let secret = from_field(&interpolate_shares(&points, GF256::new(SECRET_INDEX)));
+    let digest = from_field(&interpolate_shares(&points, GF256::new(DIGEST_INDEX)));
+
+    if digest[..DIGEST_LEN] != share_digest(&digest[DIGEST_LEN..], &secret) {
+        return Err(invalid("Share digest mismatch"));
+    }
+    Ok(secret)
+}
+
+// Four-round Feistel network with PBKDF2-HMAC-SHA256 as the round function
+fn encrypt(master_secret: &[u8], passphrase: &[u8], iteration_exponent: u8, identifier: u16, extendable: bool) -> Vec<u8> {
+    feistel(master_secret, passphrase, iteration_exponent, identifier, extendable, 0..ROUNDS)
+}
+
+fn decrypt(encrypted: &[u8], passphrase: &[u8], iteration_exponent: u8, identifier: u16, extendable: bool) -> Vec<u8> {
+    feistel(encrypted, passphrase, iteration_exponent, identifier, extendable, (0..ROUNDS).rev())
+}
+
+fn feistel(input: &[u8], passphrase: &[u8], iteration_exponent: u8, identifier: u16, extendable: bool, rounds: impl Iterator<Item = u8>) -> Vec<u8> {
+    let half = input.len() / 2;
+    let (mut left, mut right) = (input[..half].to_vec(), input[half..].to_vec());
+    let salt = if extendable { Vec::new() } else { [&b"shamir"[..], &identifier.to_be_bytes()].concat() };
+    let iterations = (BASE_ITERATIONS << iteration_exponent) / ROUNDS as u32;
+
+    for round in rounds {
+        let mut key = vec![0u8; half];
+        pbkdf2_hmac::<Sha256>(&[&[round], passphrase].concat(), &[&salt[..], &right].concat(), iterations, &mut key);
+        let next = left.iter().zip(&key).map(|(a, b)| a ^ b).collect();
+        left = right;
+        right = next;
+    }
+
+    [right, left].concat()
+}
+
+// RS1024 over GF(1024): a checksum that detects any error affecting at most 3 words
+fn rs1024_polymod(customization: &[u8], words: &[u16]) -> u32 {
+    let mut chk = 1u32;
+    for v in customization.iter().map(|&b| b as u32).chain(words.iter().map(|&w| w as u32)) {
+        let top = chk >> 20;
+        chk = (chk & 0xfffff) << 10 ^ v;
+        for (i, g) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= g;
+            }
+        }
+    }
+    chk
+}
+
+fn customization(extendable: bool) -> &'static [u8] {
+    if extendable { b"shamir_extendable" } else { b"shamir" }
+}
+
+// First DIGEST_LEN bytes of HMAC-SHA256(random part, secret)
+fn share_digest(random_part: &[u8], secret: &[u8]) -> [u8; DIGEST_LEN] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(random_part).expect("HMAC takes keys of any size");
+    mac.update(secret);
+    mac.finalize().into_bytes()[..DIGEST_LEN].try_into().unwrap()
+}
+
+fn push_bits(bits: &mut Vec<bool>, value: u32, count: u32) {
+    bits.extend((0..count).rev().map(|i| (value >> i) & 1 == 1));
+}
+
+fn to_field(bytes: &[u8]) -> Vec<GF256> {
+    bytes.iter().map(|&b| GF256::new(b)).collect()
+}
+
+fn from_field(values: &[GF256]) -> Vec<u8> {
+    values.iter().map(|v| v.num).collect()
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.to_string())
+}
+
+#[cfg(test)]
+mod slip39_tests {
+    use super::*;
+
+    // Deterministic stand-in for a CSPRNG
+    fn counter_random() -> impl FnMut(&mut [u8]) {
+        let mut counter = 0u8;
+        move |buf: &mut [u8]| {
+            for b in buf.iter_mut() {
+                counter = counter.wrapping_mul(5).wrapping_add(17);
+                *b = counter;
+            }
+        }
+    }
+
+    const SECRET: &[u8] = b"ABCDEFGHIJKLMNOP";
+
+    #[test]
+    fn single_group() -> Result<(), Box<dyn std::error::Error>> {
+        let groups = generate_shares(1, &[(2, 3)], SECRET, b"TREZOR", 0, true, &mut counter_random())?;
+        let shares = &groups[0];
+        assert_eq!(shares.len(), 3);
+
+        for pair in [[0, 1], [0, 2], [2, 1]] {
+            let chosen: Vec<Share> = pair.iter().map(|&i| shares[i].clone()).collect();
+            assert_eq!(combine_shares(&chosen, b"TREZOR")?, SECRET);
+        }
+        // Any passphrase decrypts, just to a different secret
+        assert_ne!(combine_shares(&shares[..2], b"")?, SECRET);
+        assert_eq!(combine_shares(&shares[..1], b"TREZOR").unwrap_err().to_string(), "Need 2 shares, got 1");
+        Ok(())
+    }
+
+    #[test]
+    fn two_levels() -> Result<(), Box<dyn std::error::Error>> {
+        let secret = [7u8; 32];
+        let groups = generate_shares(2, &[(1, 1), (2, 3), (3, 5)], &secret, b"", 0, false, &mut counter_random())?;
+
+        let from_groups_1_2 = [groups[1][2].clone(), groups[2][4].clone(), groups[1][0].clone(), groups[2][0].clone(), groups[2][1].clone()];
+        assert_eq!(combine_shares(&from_groups_1_2, b"")?, secret);
+        assert_eq!(combine_shares(&[groups[0][0].clone(), groups[1][1].clone(), groups[1][2].clone()], b"")?, secret);
+        assert!(combine_shares(&[groups[0][0].clone(), groups[1][1].clone()], b"").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn words_roundtrip() -> Result<(), Box<dyn std::error::Error>> {
+        for extendable in [false, true] {
+            let groups = generate_shares(1, &[(2, 3)], SECRET, b"", 1, extendable, &mut counter_random())?;
+            let share = &groups[0][1];
+            let words = share.to_words();
+            assert_eq!(words.len(), 20);
+            assert_eq!(&Share::from_words(&words)?, share);
+
+            // Every single-word error is caught
+            for i in 0..words.len() {
+                let mut corrupted = words.clone();
+                corrupted[i] ^= 0x155;
+                assert!(Share::from_words(&corrupted).is_err());
+            }
+        }
+        Ok(())
+    }
+
+    // From the SLIP-39 vectors.json, all with passphrase TREZOR
+    #[test]
+    fn reference_vectors() -> Result<(), Box<dyn std::error::Error>> {
+        let vectors: [(&[&str], &str); 6] = [
+            // 1. Valid mnemonic without sharing (128 bits)
+            (&["duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision keyboard"], "bb54aac4b89dc868ba37d9cc21b2cece"),
+            // 4. Basic sharing 2-of-3 (128 bits)
+            (&[
+                "shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed",
+                "shadow pistol academic acid actress prayer class unknown daughter sweater depict flip twice unkind craft early superior advocate guest smoking",
+            ], "b43ceb7e57a0ea8766221624d01b0864"),
+            // Threshold number of groups and members in each group (128 bits)
+            (&[
+                "eraser senior beard romp adorn nuclear spill corner cradle style ancient family general leader ambition exchange unusual garlic promise voice",
+                "eraser senior ceramic snake clay various huge numb argue hesitate auction category timber browser greatest hanger petition script leaf pickup",
+                "eraser senior ceramic shaft dynamic become junior wrist silver peasant force math alto coal amazing segment yelp velvet image paces",
+                "eraser senior ceramic round column hawk trust auction smug shame alive greatest sheriff living perfect corner chest sled fumes adequate",
+            ], "7c3397a292a5941682d7a4ae2d898d11"),
+            // Valid mnemonic without sharing (256 bits)
+            (&["theory painting academic academic armed sweater year military elder discuss acne wildlife boring employer fused large satoshi bundle carbon diagnose anatomy hamster leaves tracks paces beyond phantom capital marvel lips brave detect luck"], "989baf9dcaad5b10ca33dfd8cc75e42477025dce88ae83e75a230086a0e00e92"),
+            // Basic sharing 2-of-3 (256 bits)
+            (&[
+                "humidity disease academic always aluminum jewelry energy woman receiver strategy amuse duckling lying evidence network walnut tactics forget hairy rebound impulse brother survive clothes stadium mailman rival ocean reward venture always armed unwrap",
+                "humidity disease academic agency actress jacket gross physics cylinder solution fake mortgage benefit public busy prepare sharp friar change work slow purchase ruler again tricycle involve viral wireless mixture anatomy desert cargo upgrade",
+            ], "c938b319067687e990e05e0da0ecce1278f75ff58d9853f19dcaeed5de104aae"),
+            // Valid extendable mnemonic without sharing (128 bits)
+            (&["testify swimming academic academic column loyalty smear include exotic bedroom exotic wrist lobe cover grief golden smart junior estimate learn"], "1679b4516e0ee5954351d288a838f45e"),
+        ];
+
+        for (mnemonics, secret) in vectors {
+            let shares = mnemonics.iter().map(|m| Share::from_mnemonic(m)).collect::<io::Result<Vec<_>>>()?;
+            assert_eq!(hex::encode(combine_shares(&shares, b"TREZOR")?), secret);
+            for (share, mnemonic) in shares.iter().zip(mnemonics) {
+                assert_eq!(share.to_mnemonic(), *mnemonic);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_reference_vectors() -> Result<(), Box<dyn std::error::Error>> {
+        let err = |m: &str| Share::from_mnemonic(m).unwrap_err().to_string();
+
+        // 2. Invalid checksum, 3. invalid padding
+        assert_eq!(err("duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision kidney"), "Invalid share checksum");
+        assert_eq!(err("duckling enlarge academic academic email result length solution fridge kidney coal piece deal husband erode duke ajar music cargo fitness"), "Invalid share padding");
+        assert_eq!(err("duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision bitcoin"), "Unknown word \"bitcoin\"");
+
+        // 5. Basic sharing 2-of-3 with only one share
+        let share = Share::from_mnemonic("shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed")?;
+        assert!(combine_shares(&[share], b"TREZOR").is_err());
+
+        // 6. Mnemonics with different identifiers
+        let shares = [
+            Share::from_mnemonic("adequate smoking academic acid debut wine petition glen cluster slow rhyme slow simple epidemic rumor junk tracks treat olympic tolerate")?,
+            Share::from_mnemonic("adequate stay academic agency agency formal party ting frequent learn upstairs remember smear leaf damage anatomy ladle market hush corner")?,
+        ];
+        assert_eq!(combine_shares(&shares, b"TREZOR").unwrap_err().to_string(), "Shares belong to different backups");
+        Ok(())
+    }
+
+    #[test]
+    fn wordlist() {
+        // Sorted, with unique four-letter prefixes
+        assert!(WORDS.windows(2).all(|pair| pair[0][..4] < pair[1][..4]));
+    }
+
+    #[test]
+    fn invalid_parameters() {
+        let mut random = counter_random();
+        assert!(generate_shares(1, &[(1, 2)], SECRET, b"", 0, true, &mut random).is_err());
+        assert!(generate_shares(2, &[(2, 3)], SECRET, b"", 0, true, &mut random).is_err());
+        assert!(generate_shares(1, &[(2, 3)], &SECRET[..15], b"", 0, true, &mut random).is_err());
+    }
+}
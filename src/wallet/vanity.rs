@@ -0,0 +1,201 @@
+use std::{io::{self, Error, ErrorKind}, sync::{Mutex, atomic::{AtomicBool, AtomicU64, Ordering}}, thread};
+use crypto_bigint::U256;
+use crate::{algorithms::{base58::BASE58_ALPHABET, bech32::CHARSET}, s256::{private_key::PrivateKey, s256_point::S256Point, scalr::Scalar}};
+
+// Vanity search over mainnet addresses of compressed keys. Candidates are the consecutive secrets
+// start, start + 1, ...: one rmul up front, after which every candidate costs a point addition
+// (batched, so the inversions are shared).
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressType {
+    P2pkh,
+    P2wpkh,
+}
+
+impl AddressType {
+    fn address(self, point: S256Point) -> String {
+        match self {
+            AddressType::P2pkh => point.address(true, false),
+            AddressType::P2wpkh => point.p2wpkh_address(false),
+        }
+    }
+}
+
+// Candidates each thread advances together
+const BATCH: usize = 64;
+
+pub struct VanityMatch {
+    pub key: PrivateKey,
+    pub address: String,
+    pub attempts: u64,
+}
+
+pub fn validate_prefix(prefix: &str, address_type: AddressType) -> io::Result<()> {
+    let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, msg.to_string());
+
+    match address_type {
+        AddressType::P2pkh => {
+            if !prefix.starts_with('1') {
+                return Err(invalid("P2PKH prefix must start with 1"));
+            }
+            if !prefix.bytes().all(|b| BASE58_ALPHABET.contains(&b)) {
+                return Err(invalid("Prefix has characters outside the base58 alphabet"));
+            }
+        }
+        AddressType::P2wpkh => {
+            let data = prefix.strip_prefix("bc1q").ok_or_else(|| invalid("P2WPKH prefix must start with bc1q"))?;
+            if !data.bytes().all(|b| CHARSET.contains(&b)) {
+                return Err(invalid("Prefix has characters outside the bech32 alphabet"));
+            }
+        }
+    }
+
+    if probability(prefix, address_type) == 0.0 {
+        return Err(invalid("No address can start with this prefix"));
+    }
+    Ok(())
+}
+
+// Expected number of candidates to find a match, like vanitygen's "difficulty"
+pub fn expected_attempts(prefix: &str, address_type: AddressType) -> io::Result<f64> {
+    validate_prefix(prefix, address_type)?;
+    Ok(1.0 / probability(prefix, address_type))
+}
+
+// Chance that a random key's address starts with an already well-formed prefix
+fn probability(prefix: &str, address_type: AddressType) -> f64 {
+    match address_type {
+        // The 20-byte program is exactly 32 characters of 5 bits each
+        AddressType::P2wpkh => {
+            let chars = prefix.len() - 4;
+            if chars > 32 { 0.0 } else { 32f64.powi(-(chars as i32)) }
+        }
+        // Version 0x00 is the leading 1. Every further 1 is a zero byte of hash160 + checksum,
+        // and the remaining characters are the base58 digits of the 192-bit number N they form.
+        AddressType::P2pkh => {
+            let rest = &prefix[1..];
+            let rest_digits = rest.trim_start_matches('1');
+            let zero_bytes = (rest.len() - rest_digits.len()) as i32;
+            if rest_digits.is_empty() {
+                return 256f64.powi(-zero_bytes);
+            }
+
+            // Exactly zero_bytes leading zero bytes puts N in [lo, hi)
+            let lo = 2f64.powi(184 - 8 * zero_bytes);
+            let hi = 2f64.powi(192 - 8 * zero_bytes);
+            let digits = rest_digits.len() as i32;
+            let value = rest_digits.bytes().fold(0f64, |acc, b| {
+                acc * 58.0 + BASE58_ALPHABET.iter().position(|&x| x == b).unwrap() as f64
+            });
+
+            // An encoding of len digits starting with the prefix is an interval of N
+            let count: f64 = (digits..=33)
+                .map(|len| {
+                    let scale = 58f64.powi(len - digits);
+                    ((value + 1.0) * scale).min(hi) - (value * scale).max(lo)
+                })
+                .filter(|&width| width > 0.0)
+                .sum();
+            count / 2f64.powi(192)
+        }
+    }
+}
+
+// Runs until some thread finds a match, so check expected_attempts before asking for a long
+// prefix. start must be unpredictable (and below n) for the key to be safe to use.
+pub fn search(prefix: &str, address_type: AddressType, start: U256, threads: usize) -> io::Result<VanityMatch> {
+    validate_prefix(prefix, address_type)?;
+    if threads == 0 {
+        return Err(Error::new(ErrorKind::InvalidInput, "Need at least one thread"));
+    }
+    if start == U256::ZERO || start >= S256Point::n() {
+        return Err(Error::new(ErrorKind::InvalidInput, "Start secret out of range"));
+    }
+
+    // Lane l tries the secrets start + l, start + l + lanes, ... Each thread owns BATCH lanes and
+    // steps them together, so a round of point additions costs one shared inversion.
+    let lanes = threads * BATCH;
+    let step = S256Point::g().rmul(U256::from_u64(lanes as u64));
+    let stop = AtomicBool::new(false);
+    let attempts = AtomicU64::new(0);
+    let found = Mutex::new(None);
+
+    thread::scope(|scope| {
+        let mut point = S256Point::g().rmul(start);
+        for t in 0..threads {
+            let mut points = Vec::with_capacity(BATCH);
+            for _ in 0..BATCH {
+                points.push(point);
+                point = point + S256Point::g();
+            }
+            let (stop, attempts, found) = (&stop, &attempts, &found);
+            scope.spawn(move || {
+                let mut base = (t * BATCH) as u64;
+                while !stop.load(Ordering::Relaxed) {
+                    for (i, &point) in points.iter().enumerate() {
+                        attempts.fetch_add(1, Ordering::Relaxed);
+                        let address = address_type.address(point);
+                        if address.starts_with(prefix) {
+                            found.lock().unwrap().get_or_insert((base + i as u64, point, address));
+                            stop.store(true, Ordering::Relaxed);
+                            return;
+                        }
+                    }
+                    points = S256Point::add_batch(&points, step);
+                    base += lanes as u64;
+                }
+            });
+        }
+    });
+
+    let (offset, point, address) = found.into_inner().unwrap().expect("Search stops only on a match");
+    let secret = (Scalar::new(start) + Scalar::new(U256::from_u64(offset))).num;
+    Ok(VanityMatch { key: PrivateKey { secret, point }, address, attempts: attempts.into_inner() })
+}
+
+#[cfg(test)]
+mod vanity_tests {
+    use super::*;
+
+    #[test]
+    fn difficulty() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(expected_attempts("1", AddressType::P2pkh)?, 1.0);
+        assert_eq!(expected_attempts("11", AddressType::P2pkh)?, 256.0);
+        assert_eq!(expected_attempts("bc1q", AddressType::P2wpkh)?, 1.0);
+        assert_eq!(expected_attempts("bc1qqq", AddressType::P2wpkh)?, 1024.0);
+
+        // Leading digits are skewed: 33-character addresses never start with a high one
+        let a = expected_attempts("1A", AddressType::P2pkh)?;
+        let z = expected_attempts("1z", AddressType::P2pkh)?;
+        assert!((22.9..23.0).contains(&a), "{a}");
+        assert!((1353.0..1354.0).contains(&z), "{z}");
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_prefixes() {
+        assert!(validate_prefix("3J98", AddressType::P2pkh).is_err());
+        assert!(validate_prefix("1Love0", AddressType::P2pkh).is_err());
+        assert!(validate_prefix("1zzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzz", AddressType::P2pkh).is_err());
+        assert!(validate_prefix("bc1p", AddressType::P2wpkh).is_err());
+        assert!(validate_prefix("bc1qb", AddressType::P2wpkh).is_err());
+        assert!(validate_prefix("bc1QQ", AddressType::P2wpkh).is_err());
+    }
+
+    #[test]
+    fn finds_prefix() -> Result<(), Box<dyn std::error::Error>> {
+        let start = U256::from_u64(1000);
+        for (prefix, address_type) in [("1A", AddressType::P2pkh), ("bc1qq", AddressType::P2wpkh)] {
+            let found = search(prefix, address_type, start, 3)?;
+            assert!(found.address.starts_with(prefix));
+            assert_eq!(address_type.address(found.key.point), found.address);
+            assert_eq!(S256Point::g().rmul(found.key.secret), found.key.point);
+            assert!(found.key.secret >= start && found.attempts > 0);
+        }
+
+        assert_eq!(search("1A", AddressType::P2pkh, start, 0).err().map(|e| e.kind()), Some(ErrorKind::InvalidInput));
+        assert!(search("1A", AddressType::P2pkh, U256::ZERO, 1).is_err());
+        assert!(search("1A", AddressType::P2pkh, S256Point::n(), 1).is_err());
+        Ok(())
+    }
+}
@@ -280,7 +280,8 @@ fn ex07() {
     let z = hash256(b"Programming Bitcoin!");
     let z_u256 = U256::from_be_slice(&z);
 
-    let sig = e.sign(z_u256);
+    // The book signs with a fixed k; sign() would derive one per RFC 6979
+    let sig = e.sign_with_nonce(z_u256, U256::from_u32(1234567890u32));
 
     assert_eq!(hex::encode(z), "969f6056aa26f7d2795fd013fe88868d09c9f6aed96965016e1936ae47060d48");
     assert_eq!(hex::encode(sig.r.num.to_be_bytes()), "2b698a0f0a4041b77e63488ad48c23e8e8838dd1fb7520408b121697b782ef22");